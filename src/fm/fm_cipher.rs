@@ -0,0 +1,38 @@
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+
+use super::fm_file_handler::BlockId;
+
+// 块级加密接口：FileHandle 在写块前加密、读块后解密
+// 实现必须是对称的（同一块号上调用两次 apply 还原原文），且不改变数据长度
+pub trait BlockCipher: Send {
+    // 对块内容原地加密/解密，block 用于派生每块独立的 nonce
+    fn apply(&self, block: BlockId, buf: &mut [u8]);
+}
+
+// 基于 ChaCha20 流密码的块加密：密钥由调用方提供，nonce 由块号派生，
+// 因此内容相同的不同块得到不同的密文
+// 注意：同一块反复改写时复用同一 nonce，只提供静态数据的机密性，不提供完整性校验
+pub struct ChaCha20Cipher {
+    key: [u8; 32],
+}
+
+impl ChaCha20Cipher {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+
+    // 由块号派生 12 字节 nonce：前 4 字节为小端块号，其余为 0
+    fn nonce(block: BlockId) -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        nonce[..4].copy_from_slice(&block.get().to_le_bytes());
+        nonce
+    }
+}
+
+impl BlockCipher for ChaCha20Cipher {
+    fn apply(&self, block: BlockId, buf: &mut [u8]) {
+        let mut cipher = ChaCha20::new(&self.key.into(), &Self::nonce(block).into());
+        cipher.apply_keystream(buf);
+    }
+}
@@ -0,0 +1,47 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use super::fm_file_handler::BlockId;
+
+// 写路径拦截接口：FileHandle 每次向文件写入字节前询问是否真正写入，
+// 用于注入故障（模拟断电、写丢失等），验证崩溃后重新打开时的一致性
+pub trait WriteInterceptor: Send {
+    // block 为本次写入的块号（文件头为 0）；返回 false 时丢弃本次写入，调用方仍视为写入成功
+    fn allow_write(&mut self, block: BlockId) -> bool;
+
+    // 每次向磁盘发出 fsync 之前调用，可用来验证写入与同步之间的先后顺序
+    fn on_sync(&mut self) {}
+}
+
+// 断电模拟：放行前 n 次写入，之后的写入全部静默丢弃，
+// 相当于第 n 次写入完成后机器断电，之后的修改都没有落盘；重新打开文件即得到“恢复”后的状态
+pub struct CrashSim {
+    remaining: usize,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl CrashSim {
+    // 在 writes 次写入之后“断电”
+    pub fn after_writes(writes: usize) -> Self {
+        Self {
+            remaining: writes,
+            dropped: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    // 被丢弃的写入次数计数器，拦截器交给 FileHandle 后仍可据此确认断电是否已经发生
+    pub fn dropped_writes(&self) -> Arc<AtomicUsize> {
+        Arc::clone(&self.dropped)
+    }
+}
+
+impl WriteInterceptor for CrashSim {
+    fn allow_write(&mut self, _block: BlockId) -> bool {
+        if self.remaining == 0 {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        self.remaining -= 1;
+        true
+    }
+}
@@ -0,0 +1,147 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::fm_file_handler::BlockId;
+
+// 扩展段（extent）：表文件按固定块数拆分成多个物理文件。
+// 下标 0 为主文件 <path>，下标 N 为 <path>.N；逻辑块号 b 位于第 b / blocks_per_extent 个段的
+// 第 b % blocks_per_extent 块。blocks_per_extent 为 0 表示不拆分，所有块都在主文件中。
+// 扩展段文件在第一次访问时打开，可写模式下不存在时创建
+pub struct ExtentManager {
+    base_path: PathBuf,
+    blocks_per_extent: u32,
+    read_only: bool,
+    // 已打开的段文件，下标即段号；主文件始终打开
+    files: Vec<File>,
+}
+
+impl ExtentManager {
+    pub fn new(primary: File, base_path: PathBuf, blocks_per_extent: u32, read_only: bool) -> Self {
+        Self {
+            base_path,
+            blocks_per_extent,
+            read_only,
+            files: vec![primary],
+        }
+    }
+
+    // 每个段容纳的块数（0 表示不拆分）
+    pub fn blocks_per_extent(&self) -> u32 {
+        self.blocks_per_extent
+    }
+
+    // 逻辑块号 -> (段号, 段内块号)
+    pub fn locate(&self, block: BlockId) -> (usize, u32) {
+        let block = u32::from(block);
+        match self.blocks_per_extent {
+            0 => (0, block),
+            n => ((block / n) as usize, block % n),
+        }
+    }
+
+    // 主文件（块 0 所在的文件）
+    #[cfg(feature = "mmap")]
+    pub fn primary(&self) -> &File {
+        &self.files[0]
+    }
+
+    // 打开存放指定块的段文件并定位到该块起始处
+    pub fn seek_to_block(&mut self, block: BlockId, block_size: usize) -> io::Result<&mut File> {
+        let (index, local) = self.locate(block);
+        let file = self.file(index)?;
+        file.seek(SeekFrom::Start(local as u64 * block_size as u64))?;
+        Ok(file)
+    }
+
+    // 指定段文件的当前长度（字节）
+    pub fn len(&mut self, index: usize) -> io::Result<u64> {
+        Ok(self.file(index)?.metadata()?.len())
+    }
+
+    // 设置指定段文件的长度
+    pub fn set_len(&mut self, index: usize, len: u64) -> io::Result<()> {
+        self.file(index)?.set_len(len)
+    }
+
+    // 所有段文件的总长度（不存在的段不计入）
+    pub fn total_len(&mut self) -> io::Result<u64> {
+        let mut total = 0;
+        for index in 0.. {
+            if index >= self.files.len() && !extent_path(&self.base_path, index).exists() {
+                break;
+            }
+            total += self.len(index)?;
+        }
+        Ok(total)
+    }
+
+    // 截断到只保留前 blocks 个块：主文件（或最后一个保留的段）截短，其后的段文件全部删除
+    pub fn truncate(&mut self, blocks: u32, block_size: usize) -> io::Result<()> {
+        let (last, local) = match blocks {
+            0 => (0, 0),
+            n => {
+                let (index, local) = self.locate(BlockId::new(n - 1));
+                (index, local + 1)
+            }
+        };
+        self.set_len(last, local as u64 * block_size as u64)?;
+        self.files.truncate(last + 1);
+        for index in last + 1.. {
+            let path = extent_path(&self.base_path, index);
+            if !path.exists() {
+                break;
+            }
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    // 把所有已打开段文件的缓冲写出
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.files.iter_mut().try_for_each(|file| file.flush())
+    }
+
+    // 对所有已打开的段文件 fsync（数据与元数据）
+    pub fn sync_all(&self) -> io::Result<()> {
+        self.files.iter().try_for_each(|file| file.sync_all())
+    }
+
+    // 对所有已打开的段文件 fsync（仅数据）
+    pub fn sync_data(&self) -> io::Result<()> {
+        self.files.iter().try_for_each(|file| file.sync_data())
+    }
+
+    // 按段号取文件，按需依次打开（可写时创建）中间的段
+    fn file(&mut self, index: usize) -> io::Result<&mut File> {
+        while self.files.len() <= index {
+            let path = extent_path(&self.base_path, self.files.len());
+            let file = OpenOptions::new()
+                .read(true)
+                .write(!self.read_only)
+                .create(!self.read_only)
+                .truncate(false)
+                .open(&path)?;
+            self.files.push(file);
+        }
+        Ok(&mut self.files[index])
+    }
+}
+
+// 段文件路径：段 0 为主文件本身，段 N 为 <path>.N
+pub fn extent_path(base: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return base.to_path_buf();
+    }
+    let mut name = base.as_os_str().to_os_string();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+// 列出主文件之后已存在的段文件路径（<path>.1、<path>.2 ...，遇到第一个缺失的段为止）
+pub fn existing_extent_paths(base: &Path) -> Vec<PathBuf> {
+    (1..)
+        .map(|index| extent_path(base, index))
+        .take_while(|path| path.exists())
+        .collect()
+}
@@ -1,47 +1,537 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::File;
-use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
-use std::path::PathBuf;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
 
-type BlockId = u32;
-use super::fm_file_header::FileHeader;
+use super::fm_cipher::BlockCipher;
+use super::fm_crash_sim::WriteInterceptor;
+use super::fm_extent::ExtentManager;
+use super::fm_file_header::{FileHeader, FLAG_SCHEMA};
 use super::fm_page_header::PageHeader;
+use crate::mm::page::Page;
+use crate::rm::schema::Schema;
+
+// 块号：文件内从 0 开始的块序号（块 0 为文件头），fm/mm/rm 统一使用这一个类型。
+// 用新类型而不是 u32 别名，避免块号与槽号、块数量等普通整数混用；与 u32 之间通过 From 显式转换
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockId(u32);
+
+impl BlockId {
+    pub const fn new(block: u32) -> Self {
+        BlockId(block)
+    }
+
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for BlockId {
+    fn from(block: u32) -> Self {
+        BlockId(block)
+    }
+}
+
+impl From<BlockId> for u32 {
+    fn from(block: BlockId) -> Self {
+        block.0
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
 
 // 文件头块编号常量（块 0）
-const HEADER_BLOCK_NUMBER: u32 = 0;
+const HEADER_BLOCK_NUMBER: BlockId = BlockId::new(0);
+
+// 表结构元数据块编号（仅在文件头设置 FLAG_SCHEMA 时存在）
+const SCHEMA_BLOCK_NUMBER: BlockId = BlockId::new(1);
+
+// 空闲空间映射块布局：[条目数 u32][12 字节保留][(块号 u32, 可用字节数 u32) ...]
+// 保留区覆盖块内偏移 4..16，其中偏移 12 的字节恒为 0（数据页在该位置存放页格式版本），
+// 使映射块不会被误当作数据页解析
+const FREE_SPACE_MAP_HEADER_BYTES: usize = 16;
+const FREE_SPACE_ENTRY_BYTES: usize = 8;
+
+// 持久化模式：决定 flush 时如何调用 fsync
+// - Full: sync_all，数据与元数据（文件长度等）都落盘
+// - DataOnly: sync_data，只保证数据落盘，适合文件长度很少变化的场景
+// - None: 不做 fsync，仅交给操作系统缓存（临时表）
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DurabilityMode {
+    #[default]
+    Full,
+    DataOnly,
+    None,
+}
+
+// 文件增长策略：append_block 发现文件长度不足时一次扩展多少
+// - Fixed(n): 每次扩展 n 个块（n 为 0 时按 1 处理）
+// - Doubling: 每次把文件长度扩展到当前的两倍（至少满足本次需要）
+// 多扩展出的块串到文件头的空闲链表上，之后的 allocate_block 优先取用，不再调用 set_len
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GrowthPolicy {
+    Fixed(u32),
+    Doubling,
+}
+
+impl Default for GrowthPolicy {
+    fn default() -> Self {
+        GrowthPolicy::Fixed(1)
+    }
+}
+
+// 空闲链表分配策略：从满足空间要求的空闲块中选哪一个
+// - FirstFit: 沿链表找到的第一个满足要求的块
+// - BestFit: 遍历整条链表，选 free_bytes 最小且满足要求的块，把大块留给大请求
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum FreeListPolicy {
+    #[default]
+    FirstFit,
+    BestFit,
+}
+
+impl FreeListPolicy {
+    // 在 (块号, 可用字节数) 列表中按策略挑出可用字节数不少于 min_free_bytes 的块
+    pub fn pick(self, entries: &[(BlockId, u32)], min_free_bytes: u32) -> Option<BlockId> {
+        let fits = entries
+            .iter()
+            .filter(|&&(_, bytes)| bytes >= min_free_bytes);
+        match self {
+            FreeListPolicy::FirstFit => fits.map(|&(block, _)| block).next(),
+            FreeListPolicy::BestFit => fits
+                .min_by_key(|&&(_, bytes)| bytes)
+                .map(|&(block, _)| block),
+        }
+    }
+}
+
+// 表文件的整体统计信息（FileHandle::stat）
+// total_blocks 包含块 0（文件头块），因此 used_blocks + free_blocks + 1 == total_blocks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileStat {
+    pub total_blocks: u32,
+    pub free_blocks: u32,
+    pub used_blocks: u32,
+    // 空闲链表中各块页头记录的可用字节数之和
+    pub total_free_bytes: u64,
+    // 文件在磁盘上的实际长度（包含创建文件时预分配、尚未使用的尾部空间）
+    pub file_size_bytes: u64,
+}
 
 // FileHandle: 对单个表/文件的抽象，封装了对块的读写、分配和释放逻辑
 pub struct FileHandle {
-    file: File,
+    // 块的物理存储：主文件以及按需打开的扩展段文件，所有块读写都经由它定位
+    extents: ExtentManager,
     path: PathBuf,
     block_size: usize,
     header: FileHeader,
     header_dirty: bool,
+    durability: DurabilityMode,
+    read_only: bool,
+    // 块加密器：设置后 read_block/write_block 透明解密/加密整块内容
+    // 文件头块与空闲链表块的页头始终以明文读写
+    cipher: Option<Box<dyn BlockCipher>>,
+    growth: GrowthPolicy,
+    free_list_policy: FreeListPolicy,
+    // 从元数据块读出的表结构
+    schema: Option<Schema>,
+    // 元数据块中记录的表名（没有元数据块或旧文件未记录时为 None）
+    table_name: Option<String>,
+    // 部分使用的数据页及其可用字节数（空闲空间映射块的内存副本），按加入顺序排列
+    free_space: Vec<(BlockId, u32)>,
+    free_space_dirty: bool,
+    // 写路径拦截器：设置后每次写入文件前询问是否真正写入（故障注入）
+    write_interceptor: Option<Box<dyn WriteInterceptor>>,
 }
 
 impl FileHandle {
     // 内部构造器，FileManager 打开文件后返回 FileHandle
-    pub(crate) fn new(file: File, path: PathBuf, block_size: usize, header: FileHeader) -> Self {
+    pub(crate) fn new(
+        file: File,
+        path: PathBuf,
+        block_size: usize,
+        header: FileHeader,
+        durability: DurabilityMode,
+        read_only: bool,
+        cipher: Option<Box<dyn BlockCipher>>,
+    ) -> Self {
         Self {
-            file,
+            extents: ExtentManager::new(file, path.clone(), header.extent_blocks(), read_only),
             path,
             block_size,
             header,
             header_dirty: false,
+            durability,
+            read_only,
+            cipher,
+            growth: GrowthPolicy::default(),
+            free_list_policy: FreeListPolicy::default(),
+            schema: None,
+            table_name: None,
+            free_space: Vec::new(),
+            free_space_dirty: false,
+            write_interceptor: None,
         }
     }
 
+    // 设置（或用 None 取消）写路径拦截器，用于在测试中注入写入故障（见 CrashSim）
+    pub fn set_write_interceptor(&mut self, interceptor: Option<Box<dyn WriteInterceptor>>) {
+        self.write_interceptor = interceptor;
+    }
+
     // 返回块大小（字节）
     pub fn block_size(&self) -> usize {
         self.block_size
     }
 
+    // 返回打开时设定的持久化模式
+    pub fn durability(&self) -> DurabilityMode {
+        self.durability
+    }
+
+    // 设置文件增长策略
+    pub fn set_growth_policy(&mut self, growth: GrowthPolicy) {
+        self.growth = growth;
+    }
+
+    // 返回当前的文件增长策略
+    pub fn growth_policy(&self) -> GrowthPolicy {
+        self.growth
+    }
+
+    // 设置空闲链表分配策略
+    pub fn set_free_list_policy(&mut self, policy: FreeListPolicy) {
+        self.free_list_policy = policy;
+    }
+
+    // 返回当前的空闲链表分配策略
+    pub fn free_list_policy(&self) -> FreeListPolicy {
+        self.free_list_policy
+    }
+
+    // 是否以只读模式打开
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    // 是否启用了块加密
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    // 每个扩展段的块数；文件未拆分时为 0
+    pub fn extent_blocks(&self) -> u32 {
+        self.extents.blocks_per_extent()
+    }
+
+    // 底层主文件句柄，供映射文件后端建立内存映射
+    #[cfg(feature = "mmap")]
+    pub(crate) fn file(&self) -> &File {
+        self.extents.primary()
+    }
+
+    // 表结构（文件带元数据块时存在）
+    pub fn schema(&self) -> Option<&Schema> {
+        self.schema.as_ref()
+    }
+
+    // 元数据块中记录的表名
+    pub fn table_name(&self) -> Option<&str> {
+        self.table_name.as_deref()
+    }
+
+    // 第一个数据块的块号：块 0 为文件头，带表结构时块 1 为元数据块
+    pub fn first_data_block(&self) -> BlockId {
+        if self.header.has_schema() {
+            BlockId::new(SCHEMA_BLOCK_NUMBER.get() + 1)
+        } else {
+            SCHEMA_BLOCK_NUMBER
+        }
+    }
+
+    // 文件头块与表结构元数据块之后的所有块号（first_data_block..block_count），按调用时的块数量确定范围；
+    // 空闲块和空闲空间映射块同样包含在内
+    pub fn data_blocks(&self) -> impl Iterator<Item = BlockId> {
+        (self.first_data_block().get()..self.header.block_count).map(BlockId::from)
+    }
+
+    // 是否为元数据块（文件头块、表结构元数据块、空闲空间映射块），这些块不能当作数据页使用
+    pub fn is_metadata_block(&self, block: BlockId) -> bool {
+        block < self.first_data_block() || Some(block) == self.free_space_map_block()
+    }
+
+    // 在新建的空文件上写入表结构元数据块（占用块 1）
+    // 元数据块内容：4 字节小端长度 + 序列化的 Schema，之后是 2 字节小端长度 + UTF-8 表名，
+    // 全部内容不能超过一个块；旧文件表名长度处为 0，视为未记录表名
+    pub(crate) fn write_schema(&mut self, schema: &Schema, table_name: &str) -> io::Result<()> {
+        self.ensure_writable()?;
+        if self.header.block_count != 1 || self.header.has_schema() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "只能在没有数据块的新文件上写入表结构",
+            ));
+        }
+        let buffer = self.encode_metadata_block(schema, table_name)?;
+        let block = self.allocate_block()?;
+        debug_assert_eq!(block, SCHEMA_BLOCK_NUMBER);
+        self.write_block(block, &buffer)?;
+        self.header.flags |= FLAG_SCHEMA;
+        self.header_dirty = true;
+        self.schema = Some(schema.clone());
+        self.table_name = Some(table_name.to_string());
+        Ok(())
+    }
+
+    // 改写元数据块中的表名（表结构不变），文件没有元数据块时报 InvalidInput
+    pub(crate) fn write_table_name(&mut self, table_name: &str) -> io::Result<()> {
+        self.ensure_writable()?;
+        let Some(schema) = &self.schema else {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "文件没有元数据块，无法记录表名",
+            ));
+        };
+        let buffer = self.encode_metadata_block(schema, table_name)?;
+        self.write_block(SCHEMA_BLOCK_NUMBER, &buffer)?;
+        self.table_name = Some(table_name.to_string());
+        Ok(())
+    }
+
+    // 按元数据块布局编码表结构与表名
+    fn encode_metadata_block(&self, schema: &Schema, table_name: &str) -> io::Result<Vec<u8>> {
+        let bytes = schema.to_bytes()?;
+        let name = table_name.as_bytes();
+        let name_start = 4 + bytes.len();
+        if name.len() > u16::MAX as usize || name_start + 2 + name.len() > self.block_size {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "表结构序列化后 {} 字节、表名 {} 字节，超出一个块（{} 字节）",
+                    bytes.len(),
+                    name.len(),
+                    self.block_size
+                ),
+            ));
+        }
+        let mut buffer = vec![0u8; self.block_size];
+        buffer[..4].copy_from_slice(&(bytes.len() as u32).to_le_bytes());
+        buffer[4..name_start].copy_from_slice(&bytes);
+        buffer[name_start..name_start + 2].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        buffer[name_start + 2..name_start + 2 + name.len()].copy_from_slice(name);
+        Ok(buffer)
+    }
+
+    // 打开文件时读取元数据块中的表结构与表名
+    pub(crate) fn load_schema(&mut self) -> io::Result<()> {
+        if !self.header.has_schema() {
+            return Ok(());
+        }
+        let mut buffer = vec![0u8; self.block_size];
+        self.read_block(SCHEMA_BLOCK_NUMBER, &mut buffer)?;
+        let len = u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+        if 4 + len > self.block_size {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("元数据块声明的表结构长度 {} 超出块大小", len),
+            ));
+        }
+        self.schema = Some(Schema::from_bytes(&buffer[4..4 + len])?);
+        let name_start = 4 + len;
+        let name_len = buffer
+            .get(name_start..name_start + 2)
+            .map_or(0, |b| u16::from_le_bytes([b[0], b[1]]) as usize);
+        self.table_name = match name_len {
+            0 => None,
+            n => {
+                let name = buffer.get(name_start + 2..name_start + 2 + n).ok_or_else(|| {
+                    io::Error::new(ErrorKind::InvalidData, "元数据块声明的表名长度超出块大小")
+                })?;
+                let name = std::str::from_utf8(name)
+                    .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+                Some(name.to_string())
+            }
+        };
+        Ok(())
+    }
+
+    // 空闲空间映射块的块号，尚未建立时为 None
+    fn free_space_map_block(&self) -> Option<BlockId> {
+        (self.header.free_space_map > 0).then_some(BlockId::from(self.header.free_space_map as u32))
+    }
+
+    // 映射块最多能容纳的条目数
+    fn free_space_capacity(&self) -> usize {
+        (self.block_size - FREE_SPACE_MAP_HEADER_BYTES) / FREE_SPACE_ENTRY_BYTES
+    }
+
+    // 打开文件时读取空闲空间映射；映射只是提示，指向越界块或元数据块的条目直接丢弃
+    pub(crate) fn load_free_space_map(&mut self) -> io::Result<()> {
+        let Some(map_block) = self.free_space_map_block() else {
+            return Ok(());
+        };
+        if map_block < self.first_data_block() || map_block.get() >= self.header.block_count {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("文件头中的空闲空间映射块号 {} 无效", map_block),
+            ));
+        }
+        let mut buffer = vec![0u8; self.block_size];
+        self.read_block(map_block, &mut buffer)?;
+        let count = u32::from_le_bytes(buffer[0..4].try_into().unwrap()) as usize;
+        if count > self.free_space_capacity() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("空闲空间映射块声明了 {} 个条目，超出块容量", count),
+            ));
+        }
+        let first_data_block = self.first_data_block();
+        let block_count = self.header.block_count;
+        self.free_space = buffer[FREE_SPACE_MAP_HEADER_BYTES..]
+            .chunks_exact(FREE_SPACE_ENTRY_BYTES)
+            .take(count)
+            .map(|entry| {
+                (
+                    BlockId::from(u32::from_le_bytes(entry[0..4].try_into().unwrap())),
+                    u32::from_le_bytes(entry[4..8].try_into().unwrap()),
+                )
+            })
+            .filter(|&(block, _)| {
+                block >= first_data_block && block.get() < block_count && block != map_block
+            })
+            .collect();
+        Ok(())
+    }
+
+    // 记录数据页当前的可用字节数，供 find_block_with_space 查找部分使用的页
+    // free_bytes 为 0 时从映射中移除该页；映射已满时，只有新页的可用空间多于
+    // 映射中最少的条目才替换它。首次记录时分配映射块，映射在 flush 时写回
+    pub fn record_free_space(&mut self, block: BlockId, free_bytes: u32) -> io::Result<()> {
+        self.ensure_writable()?;
+        self.ensure_valid_block(block)?;
+        if self.is_metadata_block(block) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("块 {} 是元数据块，不能记录可用空间", block),
+            ));
+        }
+        let existing = self.free_space.iter().position(|&(b, _)| b == block);
+        match (existing, free_bytes) {
+            (Some(index), 0) => {
+                self.free_space.remove(index);
+            }
+            (Some(index), _) => self.free_space[index].1 = free_bytes,
+            (None, 0) => return Ok(()),
+            (None, _) if self.free_space.len() < self.free_space_capacity() => {
+                self.free_space.push((block, free_bytes));
+            }
+            (None, _) => {
+                let (index, &(_, least)) = self
+                    .free_space
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, (_, bytes))| *bytes)
+                    .unwrap();
+                if free_bytes <= least {
+                    return Ok(());
+                }
+                self.free_space[index] = (block, free_bytes);
+            }
+        }
+        if self.free_space_map_block().is_none() {
+            let map_block = self.allocate_block()?;
+            self.header.free_space_map = map_block.get() as i32;
+            self.header_dirty = true;
+        }
+        self.free_space_dirty = true;
+        Ok(())
+    }
+
+    // 按空闲链表策略在空闲空间映射中查找可用字节数不少于 min_free_bytes 的部分使用页
+    // 映射只是提示：页的实际空间可能已经变化，调用方发现空间不足时应以实际值调用 record_free_space
+    pub fn find_block_with_space(&self, min_free_bytes: u32) -> Option<BlockId> {
+        self.free_list_policy.pick(&self.free_space, min_free_bytes)
+    }
+
+    // 空闲空间映射的当前内容：(块号, 可用字节数)，按加入顺序排列
+    pub fn free_space_entries(&self) -> &[(BlockId, u32)] {
+        &self.free_space
+    }
+
+    // 把空闲空间映射写回映射块
+    fn write_free_space_map(&mut self) -> io::Result<()> {
+        let Some(map_block) = self.free_space_map_block() else {
+            return Ok(());
+        };
+        let mut buffer = vec![0u8; self.block_size];
+        buffer[0..4].copy_from_slice(&(self.free_space.len() as u32).to_le_bytes());
+        let entries =
+            buffer[FREE_SPACE_MAP_HEADER_BYTES..].chunks_exact_mut(FREE_SPACE_ENTRY_BYTES);
+        for (entry, &(block, bytes)) in entries.zip(&self.free_space) {
+            entry[0..4].copy_from_slice(&block.get().to_le_bytes());
+            entry[4..8].copy_from_slice(&bytes.to_le_bytes());
+        }
+        self.write_block(map_block, &buffer)?;
+        self.free_space_dirty = false;
+        Ok(())
+    }
+
     // 读取内存中的文件头副本
     pub fn header(&self) -> FileHeader {
         self.header
     }
 
-    // 从指定块读取整个块数据到 buffer
+    // 沿文件头的空闲链表收集所有空闲块号（按链表顺序）
+    // 链表节点数不会超过 block_count；遇到环、文件头块或越界指针时报告文件损坏
+    pub fn free_blocks(&mut self) -> io::Result<Vec<BlockId>> {
+        Ok(self
+            .free_list_entries()?
+            .into_iter()
+            .map(|(block, _)| block)
+            .collect())
+    }
+
+    // 汇总文件级统计信息；只遍历一次空闲链表
+    pub fn stat(&mut self) -> io::Result<FileStat> {
+        let entries = self.free_list_entries()?;
+        let total_blocks = self.header.block_count;
+        let free_blocks = entries.len() as u32;
+        let total_free_bytes = entries.iter().map(|(_, h)| h.free_bytes as u64).sum();
+        Ok(FileStat {
+            total_blocks,
+            free_blocks,
+            // 块 0 为文件头块，既不算已用也不算空闲
+            used_blocks: total_blocks.saturating_sub(free_blocks + 1),
+            total_free_bytes,
+            file_size_bytes: self.extents.total_len()?,
+        })
+    }
+
+    // 空闲链表中的块数量
+    pub fn free_list_len(&mut self) -> io::Result<usize> {
+        Ok(self.free_blocks()?.len())
+    }
+
+    // 分配下一个自增记录 id（单调递增，不会重复使用），计数器随文件头持久化
+    pub fn take_auto_id(&mut self) -> io::Result<u64> {
+        self.ensure_writable()?;
+        let id = self.header.next_auto_id.max(1);
+        self.header.next_auto_id = id + 1;
+        self.header_dirty = true;
+        Ok(id)
+    }
+
+    // 从指定块读取整个块数据到 buffer；出错时在消息中附上文件路径和块号，ErrorKind 不变
     pub fn read_block(&mut self, block: BlockId, buffer: &mut [u8]) -> io::Result<()> {
+        self.read_block_inner(block, buffer)
+            .map_err(|err| with_block_context(err, "读取", &self.path, block))
+    }
+
+    fn read_block_inner(&mut self, block: BlockId, buffer: &mut [u8]) -> io::Result<()> {
         // 校验 buffer 长度是否和块大小一致
         if buffer.len() != self.block_size {
             return Err(io::Error::new(
@@ -63,12 +553,21 @@ impl FileHandle {
         }
 
         self.ensure_valid_block(block)?;
-        self.seek_to_block(block)?;
-        self.file.read_exact(buffer)
+        self.seek_to_block(block)?.read_exact(buffer)?;
+        if let Some(cipher) = &self.cipher {
+            cipher.apply(block, buffer);
+        }
+        Ok(())
     }
 
-    // 将 buffer 的整块数据写回指定块
+    // 将 buffer 的整块数据写回指定块；出错时在消息中附上文件路径和块号，ErrorKind 不变
     pub fn write_block(&mut self, block: BlockId, buffer: &[u8]) -> io::Result<()> {
+        self.write_block_inner(block, buffer)
+            .map_err(|err| with_block_context(err, "写入", &self.path, block))
+    }
+
+    fn write_block_inner(&mut self, block: BlockId, buffer: &[u8]) -> io::Result<()> {
+        self.ensure_writable()?;
         if buffer.len() != self.block_size {
             return Err(io::Error::new(
                 ErrorKind::InvalidInput,
@@ -89,102 +588,479 @@ impl FileHandle {
         }
 
         self.ensure_valid_block(block)?;
-        self.seek_to_block(block)?;
-        self.file.write_all(buffer)
+        match &self.cipher {
+            Some(cipher) => {
+                let mut encrypted = buffer.to_vec();
+                cipher.apply(block, &mut encrypted);
+                self.write_raw(block, &encrypted)
+            }
+            None => self.write_raw(block, buffer),
+        }
+    }
+
+    // 读取指定块并解析为 Page，供不经过缓冲池的工具使用
+    pub fn read_page(&mut self, block: BlockId) -> io::Result<Page> {
+        let mut buffer = vec![0u8; self.block_size];
+        self.read_block(block, &mut buffer)?;
+        Page::load(&buffer)
+    }
+
+    // 将 Page 序列化为整块后写回指定块（槽目录之外的空间写零）
+    pub fn write_page(&mut self, block: BlockId, page: &Page) -> io::Result<()> {
+        let mut buffer = vec![0u8; self.block_size];
+        page.flush(&mut buffer)?;
+        self.write_block(block, &buffer)
     }
 
     // 分配一个可用块：优先使用空闲链表，否则扩展文件
+    // 块 0 是文件头，任何情况下都不会被分配出去
     pub fn allocate_block(&mut self) -> io::Result<BlockId> {
-        if self.header.first_free_hole >= 0 {
-            // 从空闲链表头取出一个块
-            let block_num = self.header.first_free_hole as u32;
-            let mut page_header = self.read_page_header(block_num)?;
+        self.allocate_block_with_space(0)
+    }
 
-            // 更新文件头指向下一个空闲块
-            self.header.first_free_hole = page_header.next_free_page;
-            self.header_dirty = true;
+    // 分配一个页头 free_bytes 不少于 min_free_bytes 的块：按空闲链表策略选择空闲块，
+    // 没有满足要求的空闲块时扩展文件。只分配未使用的块，部分使用的数据页见 find_block_with_space
+    pub fn allocate_block_with_space(&mut self, min_free_bytes: u32) -> io::Result<BlockId> {
+        self.ensure_writable()?;
+        let block_num = match self.take_free_block(min_free_bytes)? {
+            Some(block_num) => block_num,
+            None => self.append_block()?,
+        };
+        if block_num == HEADER_BLOCK_NUMBER {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "文件损坏：分配到了文件头块",
+            ));
+        }
+        Ok(block_num)
+    }
+
+    // 按空闲链表策略取出一个 free_bytes >= min_free_bytes 的块；没有合适的块时返回 None
+    // 空闲链表损坏（环、文件头块或越界指针）时不修改任何状态并报错
+    fn take_free_block(&mut self, min_free_bytes: u32) -> io::Result<Option<BlockId>> {
+        if self.header.first_free_hole < 0 {
+            return Ok(None);
+        }
+        let entries = self.free_list_entries()?;
+        let fits = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, h))| h.free_bytes >= min_free_bytes);
+        let chosen = match self.free_list_policy {
+            FreeListPolicy::FirstFit => fits.map(|(i, _)| i).next(),
+            FreeListPolicy::BestFit => fits.min_by_key(|(_, (_, h))| h.free_bytes).map(|(i, _)| i),
+        };
+        let Some(index) = chosen else {
+            return Ok(None);
+        };
+        let (block_num, mut page_header) = entries[index];
 
-            // 如果有下一个空闲块，清除其 prev 指向
-            if page_header.next_free_page >= 0 {
-                let mut next_header = self.read_page_header(page_header.next_free_page as u32)?;
-                next_header.prev_free_page = -1;
-                self.write_page_header(page_header.next_free_page as u32, &next_header)?;
+        // 从链表中摘除：前驱（或文件头）指向后继，后继的 prev 指向前驱
+        let prev = match index.checked_sub(1) {
+            Some(prev_index) => {
+                let (prev_block, mut prev_header) = entries[prev_index];
+                prev_header.next_free_page = page_header.next_free_page;
+                self.write_page_header(prev_block, &prev_header)?;
+                prev_block.get() as i32
+            }
+            None => {
+                self.header.first_free_hole = page_header.next_free_page;
+                self.header_dirty = true;
+                -1
             }
+        };
+        if page_header.next_free_page >= 0 {
+            let next = BlockId::from(page_header.next_free_page as u32);
+            let mut next_header = self.read_page_header(next)?;
+            next_header.prev_free_page = prev;
+            self.write_page_header(next, &next_header)?;
+        }
 
-            // 清理分配后页头的链表指针，写回磁盘
-            page_header.next_free_page = -1;
-            page_header.prev_free_page = -1;
+        // 清理分配后页头的链表指针，写回磁盘
+        page_header.next_free_page = -1;
+        page_header.prev_free_page = -1;
+        if self.cipher.is_some() {
+            // 加密文件中已分配块必须以密文存放，重写整块
+            self.init_allocated_block(block_num, page_header)?;
+        } else {
             self.write_page_header(block_num, &page_header)?;
+        }
 
-            Ok(block_num)
+        Ok(Some(block_num))
+    }
+
+    // 扩展文件，在末尾追加一个新块
+    // 文件长度不足时按增长策略一次扩展多个块（只调用一次 set_len），多出的块放入空闲链表
+    fn append_block(&mut self) -> io::Result<BlockId> {
+        let block_num = BlockId::from(self.header.block_count);
+        let surplus = if self.has_capacity(block_num)? {
+            0
         } else {
-            // 否则扩展文件，增加一个新块
-            let block_num = self.header.block_count;
-            self.ensure_capacity(block_num)?;
+            self.growth_surplus(block_num)
+        };
+        self.ensure_capacity(BlockId::from(block_num.get() + surplus))?;
 
-            let page_header = PageHeader::clear(self.payload_capacity());
-            self.header.block_count += 1;
-            self.header_dirty = true;
+        let page_header = PageHeader::clear(self.payload_capacity());
+        self.header.block_count += 1;
+        self.header_dirty = true;
 
-            // 将新块初始化为零（包含页头），以保证确定性
-            self.zero_block(block_num, page_header)?;
+        // 将新块初始化为零（包含页头），以保证确定性
+        self.init_allocated_block(block_num, page_header)?;
 
-            Ok(block_num)
+        if surplus > 0 {
+            self.preallocate(surplus, true)?;
         }
+        Ok(block_num)
+    }
+
+    // 按增长策略，为追加块 block_num 扩展文件时额外多扩展的块数；
+    // 拆分为扩展段时不超出 block_num 所在段的剩余容量
+    fn growth_surplus(&self, block_num: BlockId) -> u32 {
+        let surplus = match self.growth {
+            GrowthPolicy::Fixed(n) => n.max(1) - 1,
+            GrowthPolicy::Doubling => block_num.get().saturating_sub(1),
+        };
+        let surplus = match self.extents.blocks_per_extent() {
+            0 => surplus,
+            n => surplus.min(n - 1 - self.extents.locate(block_num).1),
+        };
+        surplus.min(u32::MAX - 1 - block_num.get())
+    }
+
+    // 一次性把文件扩展 additional_blocks 个块（至多一次 set_len），初始化每个新块的页头，
+    // 返回新块的块号范围。into_free_list 为 true 时新块按块号顺序串到空闲链表头部，
+    // 之后由 allocate_block 依次取用；为 false 时新块视为已分配，由调用方直接使用
+    pub fn preallocate(
+        &mut self,
+        additional_blocks: u32,
+        into_free_list: bool,
+    ) -> io::Result<std::ops::Range<BlockId>> {
+        self.ensure_writable()?;
+        let start = self.header.block_count;
+        let end = start.checked_add(additional_blocks).ok_or_else(|| {
+            io::Error::new(ErrorKind::InvalidInput, "预分配后块数量超出 u32 范围")
+        })?;
+        let range = BlockId::from(start)..BlockId::from(end);
+        if additional_blocks == 0 {
+            return Ok(range);
+        }
+        self.ensure_capacity(BlockId::from(end - 1))?;
+        self.header.block_count = end;
+        self.header_dirty = true;
+        let capacity = self.payload_capacity();
+        if !into_free_list {
+            for block in start..end {
+                self.init_allocated_block(BlockId::from(block), PageHeader::clear(capacity))?;
+            }
+            return Ok(range);
+        }
+        // 空闲块的页头以明文写入；链表顺序为 start -> start+1 -> ... -> end-1 -> 原链表头
+        let old_head = self.header.first_free_hole;
+        for block in start..end {
+            let next = if block + 1 < end {
+                (block + 1) as i32
+            } else {
+                old_head
+            };
+            let mut page_header = PageHeader::new_free(capacity, next);
+            if block > start {
+                page_header.prev_free_page = (block - 1) as i32;
+            }
+            self.write_blank_block(BlockId::from(block), page_header)?;
+        }
+        if old_head >= 0 {
+            let old_head = BlockId::from(old_head as u32);
+            let mut next_header = self.read_page_header(old_head)?;
+            next_header.prev_free_page = (end - 1) as i32;
+            self.write_page_header(old_head, &next_header)?;
+        }
+        self.header.first_free_hole = start as i32;
+        Ok(range)
     }
 
     // 释放一个块并将其插入空闲链表头
     pub fn release_block(&mut self, block: BlockId) -> io::Result<()> {
+        self.ensure_writable()?;
         if block == HEADER_BLOCK_NUMBER {
             return Err(io::Error::new(ErrorKind::InvalidInput, "不能释放文件头块"));
         }
+        if block < self.first_data_block() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "不能释放表结构元数据块",
+            ));
+        }
+        if Some(block) == self.free_space_map_block() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "不能释放空闲空间映射块",
+            ));
+        }
         self.ensure_valid_block(block)?;
+        self.record_free_space(block, 0)?;
 
         // 构造空闲页头并写回磁盘（同时清空页内容）
         let page_header =
             PageHeader::new_free(self.payload_capacity(), self.header.first_free_hole);
-        self.zero_block(block, page_header)?;
+        self.write_blank_block(block, page_header)?;
 
         // 如果原先有空闲链表头，需要更新其 prev 指向
         if self.header.first_free_hole >= 0 {
-            let mut next_header = self.read_page_header(self.header.first_free_hole as u32)?;
-            next_header.prev_free_page = block as i32;
-            self.write_page_header(self.header.first_free_hole as u32, &next_header)?;
+            let old_head = BlockId::from(self.header.first_free_hole as u32);
+            let mut next_header = self.read_page_header(old_head)?;
+            next_header.prev_free_page = block.get() as i32;
+            self.write_page_header(old_head, &next_header)?;
         }
 
         // 将该释放块设置为新的空闲链表头
-        self.header.first_free_hole = block as i32;
+        self.header.first_free_hole = block.get() as i32;
         self.header_dirty = true;
         Ok(())
     }
 
-    // 将内存中脏的文件头写回并 flush 文件
+    // 安全擦除：保留块首的页头（空闲块的链表指针），把其余字节全部覆写为 0。
+    // release_block 释放块时已经整块清零，这里用于擦除调用方自行管理的块
+    pub fn zero_block(&mut self, block_id: BlockId) -> io::Result<()> {
+        self.ensure_writable()?;
+        self.ensure_valid_block(block_id)?;
+        if self.is_metadata_block(block_id) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("块 {} 是元数据块，不能擦除", block_id),
+            ));
+        }
+        let page_header = self.read_page_header(block_id)?;
+        self.write_blank_block(block_id, page_header)
+    }
+
+    // 按给定顺序物理重排数据块，返回发生移动的 (旧块号, 新块号) 映射
+    // order 中的块将依次放到这些块号升序排列后的位置上：order[i] 移动到第 i 小的块号，
+    // 因此只在 order 覆盖的块之间交换位置，不影响其他块。源和目标位置可能重叠，
+    // 按置换环逐个搬运，并用临时缓冲区保存环首块的内容。
+    // 空闲链表中的块和文件头块不能参与重排（会破坏链表指针）。
+    pub fn defragment(&mut self, order: &[BlockId]) -> io::Result<Vec<(BlockId, BlockId)>> {
+        self.ensure_writable()?;
+        let mut seen = HashSet::with_capacity(order.len());
+        for &block in order {
+            self.ensure_valid_block(block)?;
+            if block < self.first_data_block() {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "表结构元数据块不能参与重排",
+                ));
+            }
+            if Some(block) == self.free_space_map_block() {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "空闲空间映射块不能参与重排",
+                ));
+            }
+            if !seen.insert(block) {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("块 {} 在重排顺序中重复出现", block),
+                ));
+            }
+        }
+        for free in self.free_blocks()? {
+            if seen.contains(&free) {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("块 {} 位于空闲链表中，不能参与重排", free),
+                ));
+            }
+        }
+
+        let mut positions: Vec<BlockId> = order.to_vec();
+        positions.sort_unstable();
+        // 目标块号 -> 应搬到该位置的源块号
+        let source_of: HashMap<BlockId, BlockId> = positions
+            .iter()
+            .copied()
+            .zip(order.iter().copied())
+            .collect();
+
+        let mut temp = vec![0u8; self.block_size];
+        let mut buffer = vec![0u8; self.block_size];
+        let mut done = HashSet::with_capacity(order.len());
+        for &start in &positions {
+            if done.contains(&start) || source_of[&start] == start {
+                continue;
+            }
+            // 环首块会被第一个写入覆盖，先保存到临时缓冲区
+            self.read_block(start, &mut temp)?;
+            let mut target = start;
+            loop {
+                done.insert(target);
+                let source = source_of[&target];
+                if source == start {
+                    self.write_block(target, &temp)?;
+                    break;
+                }
+                self.read_block(source, &mut buffer)?;
+                self.write_block(target, &buffer)?;
+                target = source;
+            }
+        }
+
+        let moves: Vec<(BlockId, BlockId)> = positions
+            .iter()
+            .zip(order.iter())
+            .filter(|(new, old)| new != old)
+            .map(|(&new, &old)| (old, new))
+            .collect();
+        // 空闲空间映射中的条目随块一起移动
+        if !moves.is_empty() && !self.free_space.is_empty() {
+            let new_of: HashMap<BlockId, BlockId> = moves.iter().copied().collect();
+            for (block, _) in &mut self.free_space {
+                if let Some(&new) = new_of.get(block) {
+                    *block = new;
+                }
+            }
+            self.free_space_dirty = true;
+        }
+        Ok(moves)
+    }
+
+    // 按块号顺序逐块读取文件头块之外的所有块（1..block_count），产出 (块号, 块内容)，
+    // 用于完整性检查和全表扫描。块范围在调用时确定；空闲链表中的块和元数据块同样会产出，
+    // 需要区分时配合 free_blocks、first_data_block 使用。读取失败的块产出 Err，遍历可以继续
+    pub fn iter_blocks(&mut self) -> impl Iterator<Item = io::Result<(BlockId, Vec<u8>)>> + '_ {
+        let block_count = self.header.block_count;
+        (1..block_count).map(BlockId::from).map(move |block| {
+            let mut buffer = vec![0u8; self.block_size];
+            self.read_block(block, &mut buffer)?;
+            Ok((block, buffer))
+        })
+    }
+
+    // 读取指定块并生成诊断文本：数据页按 Page::debug_dump 输出，
+    // 其他块（空闲块、未初始化块）输出块头的十六进制内容
+    pub fn dump_block(&mut self, block_id: BlockId) -> io::Result<String> {
+        let mut buffer = vec![0u8; self.block_size];
+        self.read_block(block_id, &mut buffer)?;
+        match Page::load(&buffer) {
+            Ok(page) => Ok(format!("block {}\n{}", block_id, page.debug_dump())),
+            Err(err) if err.kind() == ErrorKind::InvalidData => {
+                let head: Vec<String> = buffer
+                    .iter()
+                    .take(32)
+                    .map(|b| format!("{:02x}", b))
+                    .collect();
+                Ok(format!(
+                    "block {} (not a data page: {})\n  head: {}\n",
+                    block_id,
+                    err,
+                    head.join(" ")
+                ))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    // 清空文件：截断到只剩文件头块（以及表结构元数据块），丢弃所有数据块和空闲链表，并立即持久化
+    pub fn truncate(&mut self) -> io::Result<()> {
+        self.ensure_writable()?;
+        let keep = self.first_data_block();
+        self.extents.truncate(keep.get(), self.block_size)?;
+        self.header.block_count = keep.get();
+        self.header.first_free_hole = -1;
+        self.header.free_space_map = 0;
+        self.header_dirty = true;
+        self.free_space.clear();
+        self.free_space_dirty = false;
+        self.flush()
+    }
+
+    // 将内存中脏的文件头写回并 flush 文件，随后按持久化模式 fsync
     pub fn flush(&mut self) -> io::Result<()> {
+        // 只读模式下不会产生任何修改，无需写回
+        if self.read_only {
+            return Ok(());
+        }
+        // 写屏障：先让之前写出的数据块落盘，再写空闲空间映射与文件头，
+        // 否则崩溃后文件头可能声明了尚未持久化的块
+        if self.free_space_dirty || self.header_dirty {
+            self.extents.flush()?;
+            self.sync()?;
+        }
+        if self.free_space_dirty {
+            self.write_free_space_map()?;
+        }
         if self.header_dirty {
             self.write_header()?;
             self.header_dirty = false;
         }
-        self.file.flush()
+        self.extents.flush()?;
+        self.sync()
+    }
+
+    // 按持久化模式将已写入的数据同步到磁盘
+    pub fn sync(&mut self) -> io::Result<()> {
+        if self.durability != DurabilityMode::None {
+            self.notify_sync();
+        }
+        match self.durability {
+            DurabilityMode::Full => self.extents.sync_all(),
+            DurabilityMode::DataOnly => self.extents.sync_data(),
+            DurabilityMode::None => Ok(()),
+        }
+    }
+
+    // 无视持久化模式，强制把数据与元数据同步到磁盘（检查点截断日志前必须保证数据页落盘）
+    pub fn force_sync(&mut self) -> io::Result<()> {
+        self.extents.flush()?;
+        self.notify_sync();
+        self.extents.sync_all()
     }
 
     // 将整个块清零并在块首写入 page header
-    fn zero_block(&mut self, block_number: u32, page_header: PageHeader) -> io::Result<()> {
+    fn write_blank_block(
+        &mut self,
+        block_number: BlockId,
+        page_header: PageHeader,
+    ) -> io::Result<()> {
         let mut buffer = vec![0u8; self.block_size];
         buffer[..PageHeader::BYTE_SIZE].copy_from_slice(&page_header.to_bytes());
-        self.seek_to_block(block_number)?;
-        self.file.write_all(&buffer)?;
+        self.write_raw(block_number, &buffer)
+    }
+
+    // 初始化刚分配的块：清零并写入页头，加密文件中按密文写入，
+    // 使 read_block 读到的内容与未加密文件一致
+    fn init_allocated_block(
+        &mut self,
+        block_number: BlockId,
+        page_header: PageHeader,
+    ) -> io::Result<()> {
+        let Some(cipher) = &self.cipher else {
+            return self.write_blank_block(block_number, page_header);
+        };
+        let mut buffer = vec![0u8; self.block_size];
+        buffer[..PageHeader::BYTE_SIZE].copy_from_slice(&page_header.to_bytes());
+        cipher.apply(block_number, &mut buffer);
+        self.write_raw(block_number, &buffer)
+    }
+
+    // 只读模式下拒绝一切修改操作
+    fn ensure_writable(&self) -> io::Result<()> {
+        if self.read_only {
+            return Err(io::Error::new(
+                ErrorKind::PermissionDenied,
+                format!("文件 {} 以只读模式打开，不能修改", self.path.display()),
+            ));
+        }
         Ok(())
     }
 
     // 验证块号是否在合理范围内（并排除文件头块）
-    fn ensure_valid_block(&self, block_number: u32) -> io::Result<()> {
+    fn ensure_valid_block(&self, block_number: BlockId) -> io::Result<()> {
         if block_number == HEADER_BLOCK_NUMBER {
             return Err(io::Error::new(
                 ErrorKind::InvalidInput,
                 "文件头块不能作为数据块访问",
             ));
         }
-        if block_number >= self.header.block_count {
+        if block_number.get() >= self.header.block_count {
             return Err(io::Error::new(
                 ErrorKind::InvalidInput,
                 format!(
@@ -201,51 +1077,122 @@ impl FileHandle {
         (self.block_size - PageHeader::BYTE_SIZE) as u32
     }
 
-    // 确保文件至少能容纳指定块号（按文件长度扩展）
-    fn ensure_capacity(&mut self, block_number: u32) -> io::Result<()> {
-        let required_len = (block_number as u64 + 1) * self.block_size as u64;
-        let current_len = self.file.metadata()?.len();
+    // 文件长度是否已经能容纳指定块号
+    fn has_capacity(&mut self, block_number: BlockId) -> io::Result<bool> {
+        let (extent, local) = self.extents.locate(block_number);
+        Ok(self.extents.len(extent)? >= (local as u64 + 1) * self.block_size as u64)
+    }
+
+    // 确保文件至少能容纳指定块号（按文件长度扩展，恰好扩展到该块末尾）
+    // 拆分为扩展段时只扩展块所在的段，之前的段补齐到满段
+    fn ensure_capacity(&mut self, block_number: BlockId) -> io::Result<()> {
+        let block_size = self.block_size as u64;
+        let (extent, local) = self.extents.locate(block_number);
+        let extent_len = self.extents.blocks_per_extent() as u64 * block_size;
+        for index in 0..extent {
+            if self.extents.len(index)? < extent_len {
+                self.extents.set_len(index, extent_len)?;
+            }
+        }
+        let required_len = (local as u64 + 1) * block_size;
+        let current_len = self.extents.len(extent)?;
         if current_len < required_len {
-            self.file.set_len(required_len)?;
+            self.extents.set_len(extent, required_len)?;
         }
         Ok(())
     }
 
+    // 沿空闲链表返回 (块号, 页头)，按链表顺序
+    fn free_list_entries(&mut self) -> io::Result<Vec<(BlockId, PageHeader)>> {
+        let mut entries = Vec::new();
+        let mut visited = HashSet::new();
+        let mut next = self.header.first_free_hole;
+        while next >= 0 {
+            let block = BlockId::from(next as u32);
+            if block == HEADER_BLOCK_NUMBER
+                || block.get() >= self.header.block_count
+                || !visited.insert(block)
+            {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("空闲链表在块 {} 处损坏", block),
+                ));
+            }
+            let page_header = self.read_page_header(block)?;
+            next = page_header.next_free_page;
+            entries.push((block, page_header));
+        }
+        Ok(entries)
+    }
+
     // 读取指定块的页头（块起始处的 PageHeader）
-    fn read_page_header(&mut self, block_number: u32) -> io::Result<PageHeader> {
-        self.seek_to_block(block_number)?;
+    fn read_page_header(&mut self, block_number: BlockId) -> io::Result<PageHeader> {
         let mut buf = [0u8; PageHeader::BYTE_SIZE];
-        self.file.read_exact(&mut buf)?;
+        self.seek_to_block(block_number)?.read_exact(&mut buf)?;
         PageHeader::from_bytes(&buf)
     }
 
     // 写入指定块的页头（覆盖块起始的字节）
-    fn write_page_header(&mut self, block_number: u32, header: &PageHeader) -> io::Result<()> {
-        self.seek_to_block(block_number)?;
-        self.file.write_all(&header.to_bytes())
+    fn write_page_header(&mut self, block_number: BlockId, header: &PageHeader) -> io::Result<()> {
+        self.write_raw(block_number, &header.to_bytes())
     }
 
     // 将内存中的文件头写回块 0
     fn write_header(&mut self) -> io::Result<()> {
-        self.seek_to_block(HEADER_BLOCK_NUMBER)?;
-        self.file.write_all(&self.header.to_bytes())
+        self.write_raw(HEADER_BLOCK_NUMBER, &self.header.to_bytes())
+    }
+
+    // 所有文件写入的唯一出口，从 block 起始处写入；写路径拦截器拒绝时丢弃本次写入
+    fn write_raw(&mut self, block: BlockId, buf: &[u8]) -> io::Result<()> {
+        if let Some(interceptor) = &mut self.write_interceptor {
+            if !interceptor.allow_write(block) {
+                return Ok(());
+            }
+        }
+        self.seek_to_block(block)?.write_all(buf)
     }
 
-    // 定位到指定块偏移
-    fn seek_to_block(&mut self, block_number: u32) -> io::Result<()> {
-        let offset = block_number as u64 * self.block_size as u64;
-        self.file.seek(SeekFrom::Start(offset)).map(|_| ())
+    // 通知写路径拦截器即将 fsync
+    fn notify_sync(&mut self) {
+        if let Some(interceptor) = &mut self.write_interceptor {
+            interceptor.on_sync();
+        }
+    }
+
+    // 定位到指定块的起始偏移，返回块所在的（段）文件
+    fn seek_to_block(&mut self, block_number: BlockId) -> io::Result<&mut File> {
+        self.extents.seek_to_block(block_number, self.block_size)
     }
 }
 
+// 为块读写错误补充上下文（操作、文件路径、块号），保留原始 ErrorKind
+fn with_block_context(err: io::Error, op: &str, path: &Path, block: BlockId) -> io::Error {
+    io::Error::new(
+        err.kind(),
+        format!("{} {} 的块 {} 失败：{}", op, path.display(), block, err),
+    )
+}
+
 // 当 FileHandle 被 Drop 时，如果文件头脏则尝试持久化
 impl Drop for FileHandle {
     fn drop(&mut self) {
+        if self.read_only {
+            return;
+        }
+        if self.free_space_dirty {
+            if let Err(err) = self.write_free_space_map() {
+                eprintln!(
+                    "警告: 无法持久化空闲空间映射到 {}: {}",
+                    self.path.display(),
+                    err
+                );
+            }
+        }
         if self.header_dirty {
             if let Err(err) = self.write_header() {
                 eprintln!("警告: 无法持久化文件头到 {}: {}", self.path.display(), err);
             }
         }
-        let _ = self.file.flush();
+        let _ = self.extents.flush();
     }
 }
@@ -1,22 +1,48 @@
 use std::convert::TryInto;
 use std::io::{self, ErrorKind};
 
+// 文件标志位：数据块经过加密，需要提供密钥才能打开
+pub const FLAG_ENCRYPTED: u32 = 0x0001;
+
+// 文件标志位：块 1 为元数据块，保存序列化的表结构（Schema），数据块从块 2 开始
+pub const FLAG_SCHEMA: u32 = 0x0002;
+
+// 文件标志位：文件按 pre_f 记录的块数拆分为多个扩展段文件（<path>.1、<path>.2 ...）
+pub const FLAG_EXTENTS: u32 = 0x0004;
+
 // 持久化的文件头，存放在文件的第一个块（块号 0）
 // 字段：
 // - block_count: 已分配的块数量（下一个可分配块号）
 // - first_free_hole: 空闲块链表头（-1 表示无空闲）
-// - pre_f / next_f: 预留字段，可用于索引根或双向链表等用途
+// - pre_f: 预留字段；设置 FLAG_EXTENTS 时为每个扩展段（含主文件）的块数
+// - free_space_map: 空闲空间映射块号（记录部分使用的数据页及其可用字节数），
+//   0 表示尚未建立（旧文件中该位置为预留字段 next_f，值为 0）
+// - block_size: 创建文件时使用的块大小（0 表示旧文件未记录，按配置处理）
+// - flags: 文件级标志位（FLAG_*），旧文件中该字段为 0
+// - next_auto_id: 下一个自增记录 id（旧文件中为 0，按 1 处理）
+// 磁盘布局末尾还有 4 字节 CRC32 校验和，覆盖前面所有字段，读取时校验。
+// 旧格式的文件头只有前 16 字节，其后（含校验和位置）全为 0；只有这种文件头允许不带校验和，
+// 当前格式写出的 block_size 与 next_auto_id 不会为 0，不会被误认为旧格式
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct FileHeader {
     pub block_count: u32,
     pub first_free_hole: i32,
     pub pre_f: i32,
-    pub next_f: i32,
+    pub free_space_map: i32,
+    pub block_size: u32,
+    pub flags: u32,
+    pub next_auto_id: u64,
 }
 
 impl FileHeader {
-    // 文件头在磁盘上占用的字节数（固定为 16 字节）
-    pub const BYTE_SIZE: usize = 16;
+    // 文件头在磁盘上占用的字节数（固定为 36 字节，含末尾校验和）
+    pub const BYTE_SIZE: usize = 36;
+
+    // 校验和在磁盘布局中的偏移
+    const CHECKSUM_OFFSET: usize = 32;
+
+    // 旧格式文件头（block_count、first_free_hole、pre_f、next_f）的字节数
+    const LEGACY_BYTE_SIZE: usize = 16;
 
     // 创建一个默认文件头：block_count 从 1 开始（0 用于文件头）
     pub fn new() -> Self {
@@ -24,26 +50,67 @@ impl FileHeader {
             block_count: 1,
             first_free_hole: -1,
             pre_f: 0,
-            next_f: 0,
+            free_space_map: 0,
+            block_size: 0,
+            flags: 0,
+            next_auto_id: 1,
         }
     }
 
-    // 从小端字节序反序列化
+    // 从小端字节序反序列化，并校验末尾的校验和
     pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
         if bytes.len() < Self::BYTE_SIZE {
             return Err(io::Error::new(ErrorKind::UnexpectedEof, "文件头缓冲区太小"));
         }
 
+        let stored = u32::from_le_bytes(
+            bytes[Self::CHECKSUM_OFFSET..Self::BYTE_SIZE]
+                .try_into()
+                .unwrap(),
+        );
+        let computed = crc32fast::hash(&bytes[..Self::CHECKSUM_OFFSET]);
+        let legacy = stored == 0 && Self::is_legacy_layout(bytes);
+        if !legacy && stored != computed {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "文件头校验和不匹配（记录 {:#010x}，实际 {:#010x}），文件头可能已损坏",
+                    stored, computed
+                ),
+            ));
+        }
+        Self::from_bytes_unverified(bytes)
+    }
+
+    // 是否为加入校验和之前的旧格式文件头：旧格式只写前 16 字节，之后的字段全为 0
+    fn is_legacy_layout(bytes: &[u8]) -> bool {
+        bytes[Self::LEGACY_BYTE_SIZE..Self::CHECKSUM_OFFSET]
+            .iter()
+            .all(|&b| b == 0)
+    }
+
+    // 从小端字节序反序列化，不校验校验和（调用方信任文件内容时使用）
+    pub fn from_bytes_unverified(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < Self::BYTE_SIZE {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "文件头缓冲区太小"));
+        }
+
         let block_count = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
         let first_free_hole = i32::from_le_bytes(bytes[4..8].try_into().unwrap());
         let pre_f = i32::from_le_bytes(bytes[8..12].try_into().unwrap());
-        let next_f = i32::from_le_bytes(bytes[12..16].try_into().unwrap());
+        let free_space_map = i32::from_le_bytes(bytes[12..16].try_into().unwrap());
+        let block_size = u32::from_le_bytes(bytes[16..20].try_into().unwrap());
+        let flags = u32::from_le_bytes(bytes[20..24].try_into().unwrap());
+        let next_auto_id = u64::from_le_bytes(bytes[24..32].try_into().unwrap());
 
         Ok(Self {
             block_count,
             first_free_hole,
             pre_f,
-            next_f,
+            free_space_map,
+            block_size,
+            flags,
+            next_auto_id,
         })
     }
 
@@ -53,9 +120,33 @@ impl FileHeader {
         buf[0..4].copy_from_slice(&self.block_count.to_le_bytes());
         buf[4..8].copy_from_slice(&self.first_free_hole.to_le_bytes());
         buf[8..12].copy_from_slice(&self.pre_f.to_le_bytes());
-        buf[12..16].copy_from_slice(&self.next_f.to_le_bytes());
+        buf[12..16].copy_from_slice(&self.free_space_map.to_le_bytes());
+        buf[16..20].copy_from_slice(&self.block_size.to_le_bytes());
+        buf[20..24].copy_from_slice(&self.flags.to_le_bytes());
+        buf[24..32].copy_from_slice(&self.next_auto_id.to_le_bytes());
+        let checksum = crc32fast::hash(&buf[..Self::CHECKSUM_OFFSET]);
+        buf[Self::CHECKSUM_OFFSET..].copy_from_slice(&checksum.to_le_bytes());
         buf
     }
+
+    // 文件数据块是否加密
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & FLAG_ENCRYPTED != 0
+    }
+
+    // 文件是否带有表结构元数据块
+    pub fn has_schema(&self) -> bool {
+        self.flags & FLAG_SCHEMA != 0
+    }
+
+    // 每个扩展段的块数；文件未拆分时为 0
+    pub fn extent_blocks(&self) -> u32 {
+        if self.flags & FLAG_EXTENTS != 0 {
+            self.pre_f as u32
+        } else {
+            0
+        }
+    }
 }
 
 impl Default for FileHeader {
@@ -1,15 +1,27 @@
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use super::fm_file_handler::FileHandle;
-use super::fm_file_header::FileHeader;
+use super::fm_cipher::{BlockCipher, ChaCha20Cipher};
+use super::fm_extent::{existing_extent_paths, extent_path};
+use super::fm_file_handler::{BlockId, DurabilityMode, FileHandle, GrowthPolicy};
+use super::fm_file_header::{FileHeader, FLAG_ENCRYPTED, FLAG_EXTENTS};
+use super::fm_page_header::PageHeader;
+use crate::rm::schema::Schema;
 
-// FileManager 配置：块大小与预分配字节数
+// FileManager 配置：块大小、预分配字节数、打开文件时使用的持久化模式与文件增长策略
+// validate 为 false 时打开文件跳过文件头校验和检查（用于可信文件的快速打开），
+// 文件长度与块数等结构检查仍然执行；数据页的结构校验（Page::load）在访问时照常进行，不受此选项影响
+// extent_size_bytes 非 0 时新建的表文件按该大小拆分为扩展段：主文件写满后，
+// 后续块依次放到 <path>.1、<path>.2 ... 中。段大小在创建时写入文件头，打开已有文件时以文件头为准
 #[derive(Clone, Copy, Debug)]
 pub struct FileManagerConfig {
     pub block_size: usize,
     pub preallocate_bytes: u64,
+    pub durability: DurabilityMode,
+    pub validate: bool,
+    pub growth: GrowthPolicy,
+    pub extent_size_bytes: u64,
 }
 
 impl Default for FileManagerConfig {
@@ -20,6 +32,10 @@ impl Default for FileManagerConfig {
             block_size: DEFAULT_BLOCK_SIZE,
             // 默认预分配若干块以减少小文件增长时的开销
             preallocate_bytes: DEFAULT_BLOCK_SIZE as u64 * DEFAULT_PREALLOC_BLOCKS,
+            durability: DurabilityMode::Full,
+            validate: true,
+            growth: GrowthPolicy::Fixed(1),
+            extent_size_bytes: 0,
         }
     }
 }
@@ -62,7 +78,21 @@ impl FileManager {
         fs::remove_dir_all(path)
     }
 
-    // 删除文件
+    // 列出目录下的所有表文件（扩展名为 .tbl 的普通文件），按路径排序；子目录和其他文件被忽略
+    pub fn list_tables<P: AsRef<Path>>(&self, dir: P) -> io::Result<Vec<PathBuf>> {
+        let mut tables = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_file() && path.extension().is_some_and(|ext| ext == "tbl") {
+                tables.push(path);
+            }
+        }
+        tables.sort();
+        Ok(tables)
+    }
+
+    // 删除文件（连同其扩展段文件）
     pub fn delete_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
         let path = path.as_ref();
         if !path.exists() {
@@ -74,12 +104,16 @@ impl FileManager {
                 format!("{} 是一个目录", path.display()),
             ));
         }
+        for extent in existing_extent_paths(path) {
+            fs::remove_file(extent)?;
+        }
         fs::remove_file(path)
     }
 
     // 创建表文件：创建上级目录、按预分配大小扩展文件并写入初始文件头
     pub fn create_table_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
         let path = path.as_ref();
+        let extent_blocks = self.extent_blocks()?;
         if let Some(parent) = path.parent() {
             if !parent.exists() {
                 fs::create_dir_all(parent)?;
@@ -89,47 +123,340 @@ impl FileManager {
             .read(true)
             .write(true)
             .create_new(true)
-            .open(path)?;
+            .open(path)
+            .map_err(|err| {
+                if err.kind() == ErrorKind::AlreadyExists {
+                    io::Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("表文件 {} 已存在", path.display()),
+                    )
+                } else {
+                    err
+                }
+            })?;
 
-        let aligned_size = self.align_prealloc();
+        // 预分配不超出主文件所在的段
+        let mut aligned_size = self.align_prealloc();
+        if extent_blocks > 0 {
+            aligned_size = aligned_size.min(extent_blocks as u64 * self.config.block_size as u64);
+        }
         file.set_len(aligned_size)?;
-        self.initialize_file(&mut file)
+        self.initialize_file(&mut file, extent_blocks)
+    }
+
+    // 创建表文件；force 为 true 时先删除已存在的同名文件再重新创建
+    // 已打开该文件的句柄需由调用方先行 flush，删除后其内容不再可见
+    pub fn create_table_file_force<P: AsRef<Path>>(&self, path: P, force: bool) -> io::Result<()> {
+        let path = path.as_ref();
+        if force {
+            self.delete_file(path)?;
+        }
+        self.create_table_file(path)
+    }
+
+    // 创建带表结构的表文件：块 1 写入序列化的 Schema 和表名（文件名去掉扩展名），
+    // 之后打开时可通过 FileHandle::schema、FileHandle::table_name 取回
+    pub fn create_table_file_with_schema<P: AsRef<Path>>(
+        &self,
+        path: P,
+        schema: &Schema,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        let table_name = table_name_of(path)?;
+        self.create_table_file(path)?;
+        let mut handle = self.open_file(path)?;
+        handle.write_schema(schema, &table_name)?;
+        handle.flush()
+    }
+
+    // 打开表文件，不存在时先创建（写入新的文件头）
+    // 检查与创建之间文件可能被其他进程创建：此时 create_new 返回 AlreadyExists，改为打开已有文件；
+    // 对方可能尚未写完文件头，打开失败时短暂等待后重试
+    pub fn open_or_create_table<P: AsRef<Path>>(&self, path: P) -> io::Result<FileHandle> {
+        const OPEN_RETRIES: u32 = 5;
+        let path = path.as_ref();
+        if !path.exists() {
+            match self.create_table_file(path) {
+                Ok(()) => return self.open_file(path),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {}
+                Err(err) => return Err(err),
+            }
+        }
+        let mut attempt = 0;
+        loop {
+            match self.open_file(path) {
+                Err(err) if err.kind() == ErrorKind::InvalidData && attempt < OPEN_RETRIES => {
+                    attempt += 1;
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                }
+                result => return result,
+            }
+        }
     }
 
     // 打开已有文件并读取文件头，返回 FileHandle
     pub fn open_file<P: AsRef<Path>>(&self, path: P) -> io::Result<FileHandle> {
-        let path = path.as_ref();
-        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        self.open_with_mode(path.as_ref(), false, None)
+    }
+
+    // 以只读模式打开已有文件（备份/分析进程使用），返回的 FileHandle 拒绝任何修改
+    pub fn open_file_readonly<P: AsRef<Path>>(&self, path: P) -> io::Result<FileHandle> {
+        self.open_with_mode(path.as_ref(), true, None)
+    }
+
+    // 以加密模式打开文件：数据块使用 32 字节密钥经 ChaCha20 加解密
+    // 尚未写入任何数据块的新文件会在此时被标记为加密文件；
+    // 已有明文数据的文件不能直接转为加密文件
+    pub fn open_file_encrypted<P: AsRef<Path>>(
+        &self,
+        path: P,
+        key: [u8; 32],
+    ) -> io::Result<FileHandle> {
+        self.open_with_mode(
+            path.as_ref(),
+            false,
+            Some(Box::new(ChaCha20Cipher::new(key))),
+        )
+    }
+
+    // 打开文件的公共流程：校验文件头并载入表结构与空闲空间映射
+    fn open_with_mode(
+        &self,
+        path: &Path,
+        read_only: bool,
+        cipher: Option<Box<dyn BlockCipher>>,
+    ) -> io::Result<FileHandle> {
+        let mut handle = self.open_without_metadata(path, read_only, cipher)?;
+        handle.load_schema()?;
+        handle.load_free_space_map()?;
+        Ok(handle)
+    }
+
+    // 校验文件头并建立 FileHandle，但不读取元数据块（表结构、空闲空间映射）；
+    // 复制表文件时目标的元数据块尚未写入，只能这样打开
+    // 块大小以文件头中记录的值为准（覆盖配置），旧文件未记录时才使用配置值
+    fn open_without_metadata(
+        &self,
+        path: &Path,
+        read_only: bool,
+        cipher: Option<Box<dyn BlockCipher>>,
+    ) -> io::Result<FileHandle> {
+        let mut file = OpenOptions::new().read(true).write(!read_only).open(path)?;
         let metadata = file.metadata()?;
-        if metadata.len() < self.config.block_size as u64 {
+        if metadata.len() < FileHeader::BYTE_SIZE as u64 {
             return Err(io::Error::new(
                 ErrorKind::InvalidData,
                 format!(
-                    "文件 {} 小于一个块（{} 字节）",
+                    "文件 {} 小于文件头（{} 字节）",
                     path.display(),
-                    self.config.block_size
+                    FileHeader::BYTE_SIZE
                 ),
             ));
         }
-        let header = self.read_header(&mut file)?;
-        if self.config.block_size < FileHeader::BYTE_SIZE {
+        let mut header = self.read_header(&mut file)?;
+        let block_size = self.resolve_block_size(&header)?;
+        if metadata.len() < block_size as u64 {
             return Err(io::Error::new(
-                ErrorKind::InvalidInput,
-                "块大小小于文件头字节数",
+                ErrorKind::InvalidData,
+                format!("文件 {} 小于一个块（{} 字节）", path.display(), block_size),
             ));
         }
-        Ok(FileHandle::new(
+        // 文件头声明的块必须全部完整存在；崩溃可能留下不完整的末尾块，
+        // 此时读取会在记录中途遇到 EOF，因此在打开时就报告。
+        // 拆分为扩展段时这里只检查主文件，扩展段在访问到时才打开
+        let primary_blocks = match header.extent_blocks() {
+            0 => header.block_count,
+            n => header.block_count.min(n),
+        };
+        let required_len = primary_blocks as u64 * block_size as u64;
+        if header.block_count == 0 || metadata.len() < required_len {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "文件 {} 长度 {} 字节，不足文件头声明的 {} 个块（需要 {} 字节），末尾块可能写入不完整",
+                    path.display(),
+                    metadata.len(),
+                    primary_blocks,
+                    required_len
+                ),
+            ));
+        }
+        // 文件头块始终为明文，据此判断是否需要密钥
+        match (header.is_encrypted(), cipher.is_some()) {
+            (true, false) => {
+                return Err(io::Error::new(
+                    ErrorKind::PermissionDenied,
+                    format!("文件 {} 已加密，需要提供密钥打开", path.display()),
+                ));
+            }
+            (false, true) if header.block_count > 1 => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "文件 {} 已包含明文数据块，不能以加密模式打开",
+                        path.display()
+                    ),
+                ));
+            }
+            (false, true) => {
+                header.flags |= FLAG_ENCRYPTED;
+                Self::write_header_block(&mut file, header, block_size)?;
+            }
+            _ => {}
+        }
+        let mut handle = FileHandle::new(
             file,
             path.to_path_buf(),
-            self.config.block_size,
+            block_size,
             header,
-        ))
+            self.config.durability,
+            read_only,
+            cipher,
+        );
+        handle.set_growth_policy(self.config.growth);
+        Ok(handle)
+    }
+
+    // 复制表文件（用于备份）：只逐块拷贝已分配的块，跳过尾部未使用的预分配空间，
+    // 并为目标文件写入新的文件头。源文件以只读方式打开，不会被修改；目标已存在时拒绝覆盖
+    pub fn copy_table_file<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        src: P,
+        dst: Q,
+    ) -> io::Result<()> {
+        let dst = dst.as_ref();
+        // 只读打开源文件，同时完成文件头校验
+        let mut source = self.open_file_readonly(src)?;
+        let block_size = source.block_size();
+        let header = FileHeader {
+            block_size: block_size as u32,
+            ..source.header()
+        };
+
+        if let Some(parent) = dst.parent() {
+            if !parent.exists() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(dst)
+            .map_err(|err| {
+                if err.kind() == ErrorKind::AlreadyExists {
+                    io::Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("目标文件 {} 已存在，拒绝覆盖", dst.display()),
+                    )
+                } else {
+                    err
+                }
+            })?;
+
+        // 拷贝失败时删除不完整的目标文件（扩展段由 write_block 按需创建）
+        let result = (|| {
+            let primary_blocks = match header.extent_blocks() {
+                0 => header.block_count,
+                n => header.block_count.min(n),
+            };
+            file.set_len(primary_blocks as u64 * block_size as u64)?;
+            Self::write_header_block(&mut file, header, block_size)?;
+            drop(file);
+
+            // 文件头沿用源文件的表结构标记与映射块号，而这些块此时还是全零，
+            // 因此先不读元数据打开目标，块全部拷贝完之后元数据随之就位
+            let mut target = self.open_without_metadata(dst, false, None)?;
+            let mut buffer = vec![0u8; block_size];
+            for block in (1..header.block_count).map(BlockId::from) {
+                source.read_block(block, &mut buffer)?;
+                target.write_block(block, &buffer)?;
+            }
+            target.flush()
+        })();
+        if result.is_err() {
+            let _ = self.delete_file(dst);
+        }
+        result
+    }
+
+    // 重命名（移动）表文件及其扩展段文件，目标已存在时拒绝覆盖；带元数据块的表同时把
+    // 元数据块中的表名改为新文件名。
+    // 先为每个文件建立指向目标路径的硬链接：hard_link 在目标已存在时原子地失败，
+    // 不会出现“检查时不存在、移动时已被别人创建”而覆盖目标的竞争；全部链接成功后改写表名，再删除源路径。
+    // 任一链接或改写表名失败时删除已建立的链接，源路径保持可用。源与目标须位于同一文件系统
+    pub fn rename_table<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q) -> io::Result<()> {
+        let src = src.as_ref();
+        let dst = dst.as_ref();
+        let table_name = table_name_of(dst)?;
+        // 只读打开一次，确认源文件是合法的表文件
+        let has_metadata = self.open_file_readonly(src)?.schema().is_some();
+        let sources: Vec<PathBuf> = std::iter::once(src.to_path_buf())
+            .chain(existing_extent_paths(src))
+            .collect();
+        if let Some(parent) = dst.parent() {
+            if !parent.exists() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let mut linked = Vec::with_capacity(sources.len());
+        for (index, source) in sources.iter().enumerate() {
+            let target = extent_path(dst, index);
+            if let Err(err) = fs::hard_link(source, &target) {
+                for target in &linked {
+                    let _ = fs::remove_file(target);
+                }
+                return Err(if err.kind() == ErrorKind::AlreadyExists {
+                    io::Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("目标文件 {} 已存在，拒绝覆盖", target.display()),
+                    )
+                } else {
+                    err
+                });
+            }
+            linked.push(target);
+        }
+        // 硬链接与源文件共享内容，经目标路径改写表名
+        if has_metadata {
+            let renamed = self.open_file(dst).and_then(|mut handle| {
+                handle.write_table_name(&table_name)?;
+                handle.flush()
+            });
+            if let Err(err) = renamed {
+                for target in &linked {
+                    let _ = fs::remove_file(target);
+                }
+                return Err(err);
+            }
+        }
+        // 主文件最先删除：之后源路径不再能作为表打开，目标路径已经完整
+        for source in &sources {
+            fs::remove_file(source)?;
+        }
+        Ok(())
     }
 
     // 初始化新文件，写入默认文件头并填充首个块
-    fn initialize_file(&self, file: &mut File) -> io::Result<()> {
-        let header = FileHeader::new();
-        let mut buffer = vec![0u8; self.config.block_size];
+    fn initialize_file(&self, file: &mut File, extent_blocks: u32) -> io::Result<()> {
+        let mut header = FileHeader {
+            block_size: self.config.block_size as u32,
+            ..FileHeader::new()
+        };
+        if extent_blocks > 0 {
+            header.flags |= FLAG_EXTENTS;
+            header.pre_f = extent_blocks as i32;
+        }
+        Self::write_header_block(file, header, self.config.block_size)
+    }
+
+    // 将文件头写入块 0，块内其余字节填零
+    fn write_header_block(
+        file: &mut File,
+        header: FileHeader,
+        block_size: usize,
+    ) -> io::Result<()> {
+        let mut buffer = vec![0u8; block_size];
         buffer[..FileHeader::BYTE_SIZE].copy_from_slice(&header.to_bytes());
         file.seek(SeekFrom::Start(0))?;
         file.write_all(&buffer)?;
@@ -141,7 +468,47 @@ impl FileManager {
         file.seek(SeekFrom::Start(0))?;
         let mut buf = [0u8; FileHeader::BYTE_SIZE];
         file.read_exact(&mut buf)?;
-        FileHeader::from_bytes(&buf)
+        if self.config.validate {
+            FileHeader::from_bytes(&buf)
+        } else {
+            FileHeader::from_bytes_unverified(&buf)
+        }
+    }
+
+    // 确定文件实际使用的块大小：优先使用文件头记录值，并校验其合法性
+    fn resolve_block_size(&self, header: &FileHeader) -> io::Result<usize> {
+        let block_size = if header.block_size == 0 {
+            self.config.block_size
+        } else {
+            header.block_size as usize
+        };
+        if block_size < FileHeader::BYTE_SIZE.max(PageHeader::BYTE_SIZE) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("块大小 {} 小于文件头/页头字节数", block_size),
+            ));
+        }
+        Ok(block_size)
+    }
+
+    // 按配置计算新文件每个扩展段的块数（0 表示不拆分）；
+    // 主文件至少要放下文件头块和表结构元数据块
+    fn extent_blocks(&self) -> io::Result<u32> {
+        if self.config.extent_size_bytes == 0 {
+            return Ok(0);
+        }
+        let blocks = self.config.extent_size_bytes / self.config.block_size as u64;
+        if blocks < 2 || blocks > i32::MAX as u64 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "扩展段大小 {} 字节无效：至少需要 2 个块（{} 字节）",
+                    self.config.extent_size_bytes,
+                    2 * self.config.block_size
+                ),
+            ));
+        }
+        Ok(blocks as u32)
     }
 
     // 计算并对齐预分配的字节数到块大小的整数倍
@@ -156,3 +523,16 @@ impl FileManager {
         prealloc
     }
 }
+
+// 由表文件路径得到表名：文件名去掉扩展名
+fn table_name_of(path: &Path) -> io::Result<String> {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(str::to_string)
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("无法从路径 {} 得到表名", path.display()),
+            )
+        })
+}
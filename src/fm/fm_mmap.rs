@@ -0,0 +1,113 @@
+use std::io::{self, ErrorKind};
+
+use memmap2::Mmap;
+
+use super::fm_file_handler::{BlockId, FileHandle};
+
+// 文件头块编号常量（块 0）
+const HEADER_BLOCK_NUMBER: BlockId = BlockId::new(0);
+
+// 基于内存映射的文件句柄：读取直接从映射区拷贝，省去每块一次的 seek + read 系统调用，
+// 适合随机读多的场景；写入、分配和释放仍交给内部的 FileHandle 走系统调用，
+// 持久化语义与 FileHandle 相同。映射为共享映射，写入后立即可见；
+// 文件增长超出映射范围时在下一次读取前重新映射
+pub struct MmapFileHandle {
+    handle: FileHandle,
+    map: Mmap,
+}
+
+impl MmapFileHandle {
+    // 基于已打开的 FileHandle 建立映射；加密文件的块需要逐块解密，不支持映射读取；
+    // 映射只覆盖主文件，拆分为扩展段的文件同样不支持
+    pub fn new(handle: FileHandle) -> io::Result<Self> {
+        if handle.is_encrypted() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "加密文件不支持内存映射读取",
+            ));
+        }
+        if handle.extent_blocks() > 0 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "拆分为扩展段的文件不支持内存映射读取",
+            ));
+        }
+        let map = Self::map_file(&handle)?;
+        Ok(Self { handle, map })
+    }
+
+    // 返回块大小（字节）
+    pub fn block_size(&self) -> usize {
+        self.handle.block_size()
+    }
+
+    // 访问内部 FileHandle（分配、释放、flush 等操作）
+    pub fn handle(&mut self) -> &mut FileHandle {
+        &mut self.handle
+    }
+
+    // 取回内部 FileHandle，释放映射
+    pub fn into_inner(self) -> FileHandle {
+        self.handle
+    }
+
+    // 从映射区读取整个块到 buffer，校验规则与 FileHandle::read_block 一致
+    pub fn read_block(&mut self, block: BlockId, buffer: &mut [u8]) -> io::Result<()> {
+        let block_size = self.handle.block_size();
+        if buffer.len() != block_size {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "buffer 长度 {} 与块大小 {} 不匹配",
+                    buffer.len(),
+                    block_size
+                ),
+            ));
+        }
+        if block == HEADER_BLOCK_NUMBER {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "不能把文件头块作为数据块读取",
+            ));
+        }
+        let block_count = self.handle.header().block_count;
+        if block.get() >= block_count {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("块 {} 超出范围（当前块数量 {}）", block, block_count),
+            ));
+        }
+        let start = block.get() as usize * block_size;
+        let end = start + block_size;
+        if end > self.map.len() {
+            self.remap()?;
+        }
+        match self.map.get(start..end) {
+            Some(bytes) => {
+                buffer.copy_from_slice(bytes);
+                Ok(())
+            }
+            None => Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("块 {} 超出文件末尾", block),
+            )),
+        }
+    }
+
+    // 写入整块：走 FileHandle 的系统调用路径，映射区随之可见
+    pub fn write_block(&mut self, block: BlockId, buffer: &[u8]) -> io::Result<()> {
+        self.handle.write_block(block, buffer)
+    }
+
+    // 按当前文件长度重新建立映射
+    pub fn remap(&mut self) -> io::Result<()> {
+        self.map = Self::map_file(&self.handle)?;
+        Ok(())
+    }
+
+    fn map_file(handle: &FileHandle) -> io::Result<Mmap> {
+        // 安全性：映射只读；文件只通过内部的 FileHandle 修改，read_block 先按 block_count
+        // 校验块号（truncate 会同步减小 block_count），因此不会访问到被截断的区域
+        unsafe { Mmap::map(handle.file()) }
+    }
+}
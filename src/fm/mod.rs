@@ -1,9 +1,17 @@
 // fm 模块的子模块导出（文件管理相关的子组件）
+pub mod fm_cipher; // 块级加密
+pub mod fm_crash_sim; // 写路径拦截与断电模拟
+pub mod fm_extent; // 扩展段文件（大表拆分为多个物理文件）
 pub mod fm_file_handler; // 文件句柄与块级读写、分配/回收
 pub mod fm_file_header; // 文件头结构和序列化
 pub mod fm_manager; // 高级文件管理（创建/删除/打开/预分配）
+#[cfg(feature = "mmap")]
+pub mod fm_mmap; // 内存映射读取后端
 pub mod fm_page_header; // 每页页头
 
-pub use fm_file_handler::FileHandle;
+pub use fm_crash_sim::{CrashSim, WriteInterceptor};
+pub use fm_file_handler::{BlockId, DurabilityMode, FileHandle, FreeListPolicy, GrowthPolicy};
 pub use fm_file_header::FileHeader;
 pub use fm_manager::{FileManager, FileManagerConfig};
+#[cfg(feature = "mmap")]
+pub use fm_mmap::MmapFileHandle;
@@ -2,6 +2,7 @@ mod fm;
 mod mm;
 mod rm;
 mod test;
+mod wal;
 
 use std::convert::TryInto;
 use std::error::Error;
@@ -10,21 +11,19 @@ use mm::page_compact::PageCompact;
 use mm::page_header::PageHeader;
 use mm::page_ops::PageOps;
 use mm::BufferManager;
-use test::test1;
+use rm::TableManager;
+use test::{new_table_file, test1, test_fm, test_mm, test_rm, test_wal};
 
 // 测试页面级操作：PageHeader、插入/读取/删除、compact、序列化/反序列化
 fn test_page_ops(page_size: usize) -> Result<(), Box<dyn Error>> {
     println!("== 开始 Page 层测试 ==");
     // 构造空页面（内存表示）
-    let header = PageHeader {
-        slot_count: 0,
-        free_offset: PageHeader::SIZE as u16,
-        free_bytes: (page_size as u16) - (PageHeader::SIZE as u16),
-    };
+    let header = PageHeader::new(page_size);
     let mut page = Page {
         header,
         data: Vec::new(),
         slots: Vec::new(),
+        page_size,
     };
 
     // 插入若干记录
@@ -56,7 +55,7 @@ fn test_page_ops(page_size: usize) -> Result<(), Box<dyn Error>> {
 
     // 测试紧缩：把剩余记录紧缩到一起并重写槽目录
     let before_free = page.header.free_bytes;
-    page.compact(page_size)?;
+    page.compact()?;
     let after_free = page.header.free_bytes;
     println!("紧缩完成，free_bytes: {} -> {}", before_free, after_free);
 
@@ -72,7 +71,7 @@ fn test_page_ops(page_size: usize) -> Result<(), Box<dyn Error>> {
     // 序列化到 frame 并从 frame 反序列化，验证 round-trip
     let mut frame = vec![0u8; page_size];
     page.flush(&mut frame)?;
-    let page2 = Page::load(&mut frame)?;
+    let page2 = Page::load(&frame)?;
     // 校验 slot 数与某些记录
     assert_eq!(page2.header.slot_count, page.header.slot_count);
     if page.header.slot_count > 0 {
@@ -133,9 +132,49 @@ fn test_buffer_manager(handle: fm::FileHandle) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+// 测试 TableManager 的 pin 计数：外部先 pin 住一个数据页，再在同一页上 insert/get/delete，
+// 每次操作只释放自己的 pin，外部持有的 pin 必须保留
+fn test_table_pins() -> Result<(), Box<dyn Error>> {
+    println!("== 开始 TableManager pin 计数测试 ==");
+    let mut table_mgr = TableManager::new(new_table_file("table_pins")?, 4);
+    let (block, _) = table_mgr.insert(b"first")?;
+
+    table_mgr.buffer_manager().pin(block)?;
+    assert_eq!(table_mgr.pin_count(block), Some(1));
+
+    let rid = table_mgr.insert(b"second")?;
+    assert_eq!(rid.0, block, "第二条记录应写入同一个数据页");
+    assert_eq!(table_mgr.pin_count(block), Some(1));
+    assert_eq!(table_mgr.get(rid)?, b"second");
+    assert_eq!(table_mgr.pin_count(block), Some(1));
+    table_mgr.delete(rid)?;
+    assert_eq!(table_mgr.pin_count(block), Some(1));
+
+    table_mgr.buffer_manager().unpin(block);
+    assert_eq!(table_mgr.pin_count(block), Some(0));
+    println!("外部 pin 在 insert/get/delete 之后保持不变，验证通过");
+    println!("== TableManager pin 计数测试结束 ==\n");
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
 
     println!(">>> 开始 Record Manager 初始化测试");
     test1()?;
+
+    println!(">>> 开始 File Manager 测试");
+    test_fm()?;
+
+    println!(">>> 开始 Memory Manager 测试");
+    test_mm()?;
+
+    println!(">>> 开始 Record Manager 测试");
+    test_rm()?;
+
+    println!(">>> 开始 WAL 测试");
+    test_wal()?;
+
+    println!(">>> 开始 TableManager pin 计数测试");
+    test_table_pins()?;
     Ok(())
 }
@@ -1,11 +1,13 @@
 use std::collections::{HashMap, VecDeque};
 use std::io;
 
-use crate::fm::FileHandle;
+use crate::fm::{BlockId, FileHandle};
 use crate::mm::page_guard::PageGuard;
 use crate::mm::page_header::PageHeader;
+use crate::wal::WalWriter;
 
-type BlockId = u32;
+// 快照标识，由 BufferManager::begin_snapshot 分配
+pub type SnapshotId = u64;
 
 // 缓冲区管理器：维护固定容量的内存帧，支持加载/缓存/替换/写回等功能
 pub struct BufferManager {
@@ -16,6 +18,11 @@ pub struct BufferManager {
     lru_list: VecDeque<usize>,    // LRU 队列：存储帧索引，队首为最近最少使用
     free_list: VecDeque<BlockId>, // 空闲数据页列表
     map: HashMap<BlockId, usize>, // BlockId -> frames 索引的快速映射
+    dirty_page_threshold: Option<usize>, // 脏页数超过该值时提前写回最旧的脏页
+    snapshots: HashMap<SnapshotId, HashMap<BlockId, Vec<u8>>>, // 快照 -> 修改前的页面副本
+    next_snapshot_id: SnapshotId, // 下一个分配的快照 id
+    wal: Option<WalWriter>,       // 关联的预写日志，checkpoint 时截断
+    fetch_count: u64,             // 累计 fetch 次数（命中与未命中都计入）
 }
 
 // 缓冲帧：记录块信息、数据、脏标记和 pin 计数
@@ -39,14 +46,36 @@ impl BufferManager {
             lru_list: VecDeque::new(),
             free_list: VecDeque::new(),
             map: HashMap::new(),
+            dirty_page_threshold: None,
+            snapshots: HashMap::new(),
+            next_snapshot_id: 1,
+            wal: None,
+            fetch_count: 0,
         }
     }
 
+    // 关联（或用 None 取消）预写日志；日志中记录的修改在 checkpoint 写回数据页后即可丢弃
+    pub fn set_wal(&mut self, wal: Option<WalWriter>) {
+        self.wal = wal;
+    }
+
+    // 访问关联的预写日志，用于追加记录
+    pub fn wal(&mut self) -> Option<&mut WalWriter> {
+        self.wal.as_mut()
+    }
+
+    // 设置脏页阈值：脏页数超过阈值时 mark_dirty 会按 LRU 顺序提前写回最旧的脏页，
+    // 减少崩溃时丢失的修改；None 表示只在替换或 flush_all 时写回
+    pub fn set_dirty_page_threshold(&mut self, threshold: Option<usize>) {
+        self.dirty_page_threshold = threshold;
+    }
+
     // 获取指定块的数据引用
     // - 如果已在缓冲区中命中，则直接返回并 pin
     // - 否则加载块到一个空闲帧或替换最久未使用且未被 pin 的帧
     // fetch 返回带自动 unpin 的 PageGuard
     pub fn fetch(&mut self, block_id: BlockId) -> io::Result<PageGuard> {
+        self.fetch_count += 1;
         // 1. 查找命中
         if let Some(idx) = self.find_frame(block_id) {
             // 增加 pin 计数
@@ -56,11 +85,8 @@ impl BufferManager {
             // 更新 LRU：标记为最近使用
             self.touch(idx);
             // 构造 PageGuard 并返回
-            let data_slice = &mut self.frames[idx].as_mut().unwrap().data[..];
-            let ptr = data_slice.as_mut_ptr();
-            let len = data_slice.len();
             let mgr_ptr = self as *mut Self;
-            return Ok(PageGuard::new(mgr_ptr, block_id, ptr, len));
+            return Ok(PageGuard::new(mgr_ptr, block_id, idx));
         }
         // 1. 查找命中（使用 map 做 O(1) 查找）
         if let Some(&idx) = self.map.get(&block_id) {
@@ -71,11 +97,8 @@ impl BufferManager {
             // 更新 LRU：标记为最近使用
             self.touch(idx);
             // 构造 PageGuard 并返回
-            let data_slice = &mut self.frames[idx].as_mut().unwrap().data[..];
-            let ptr = data_slice.as_mut_ptr();
-            let len = data_slice.len();
             let mgr_ptr = self as *mut Self;
-            return Ok(PageGuard::new(mgr_ptr, block_id, ptr, len));
+            return Ok(PageGuard::new(mgr_ptr, block_id, idx));
         }
         // 2. 未命中：选择空闲帧或替换
         let idx = if let Some(free_idx) = self.frames.iter().position(|f| f.is_none()) {
@@ -127,17 +150,37 @@ impl BufferManager {
         // 将该帧标记为最近使用
         self.lru_list.push_back(idx);
         // 构造 PageGuard
-        let data_slice = &mut self.frames[idx].as_mut().unwrap().data[..];
-        let ptr = data_slice.as_mut_ptr();
-        let len = data_slice.len();
         let mgr_ptr = self as *mut Self;
-        Ok(PageGuard {
-            mgr: mgr_ptr,
-            block_id,
-            data_ptr: ptr,
-            len,
-            _marker: std::marker::PhantomData,
-        })
+        Ok(PageGuard::new(mgr_ptr, block_id, idx))
+    }
+
+    // PageGuard 解引用时经由帧下标取得页面数据；帧在 pin 期间不会被替换，因此一定存在
+    pub(crate) fn frame_data(&self, idx: usize) -> &[u8] {
+        &self.frames[idx].as_ref().expect("被 pin 的帧不应为空").data
+    }
+
+    pub(crate) fn frame_data_mut(&mut self, idx: usize) -> &mut [u8] {
+        &mut self.frames[idx].as_mut().expect("被 pin 的帧不应为空").data
+    }
+
+    // 调整缓冲区容量（帧数）：只能扩容，已有帧的下标保持不变，持有中的 PageGuard 仍然有效
+    pub fn resize(&mut self, capacity: usize) -> io::Result<()> {
+        if capacity < self.capacity {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("缓冲区只能扩容：当前 {} 帧，请求 {} 帧", self.capacity, capacity),
+            ));
+        }
+        self.frames.resize(capacity, None);
+        self.capacity = capacity;
+        Ok(())
+    }
+
+    // 加载并 pin 住块但不返回 PageGuard：块在 pin 期间不会被替换，调用方用完后必须配对调用 unpin
+    pub fn pin(&mut self, block_id: BlockId) -> io::Result<()> {
+        let guard = self.fetch(block_id)?;
+        std::mem::forget(guard);
+        Ok(())
     }
 
     // 解除 pin，允许块被替换
@@ -158,33 +201,267 @@ impl BufferManager {
                 frame.dirty = true;
             }
         }
+        // 提前写回失败时页面保持脏状态，之后替换或 flush_all 时会重试
+        let _ = self.maybe_flush();
+    }
+
+    // 开始一个快照：此后页面第一次被修改前，其原始内容会为该快照保留一份副本（写时复制），
+    // 通过 fetch_snapshot 读到的始终是快照开始时的页面内容
+    pub fn begin_snapshot(&mut self) -> SnapshotId {
+        let id = self.next_snapshot_id;
+        self.next_snapshot_id += 1;
+        self.snapshots.insert(id, HashMap::new());
+        id
+    }
+
+    // 结束快照并释放其保留的页面副本
+    pub fn end_snapshot(&mut self, snapshot: SnapshotId) {
+        self.snapshots.remove(&snapshot);
+    }
+
+    // 按快照视图读取页面内容：快照开始后被修改过的页面返回保留的旧副本，否则返回当前内容
+    pub fn fetch_snapshot(
+        &mut self,
+        snapshot: SnapshotId,
+        block_id: BlockId,
+    ) -> io::Result<Vec<u8>> {
+        let Some(images) = self.snapshots.get(&snapshot) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("快照 {} 不存在或已结束", snapshot),
+            ));
+        };
+        if let Some(image) = images.get(&block_id) {
+            return Ok(image.clone());
+        }
+        let frame = self.fetch(block_id)?;
+        Ok(frame.to_vec())
+    }
+
+    // 页面即将被修改：为尚未保存该页面的活动快照保留当前内容（由 PageGuard 在第一次写入前调用）
+    pub(crate) fn preserve_for_snapshots(&mut self, block_id: BlockId) {
+        if self.snapshots.is_empty() {
+            return;
+        }
+        let Some(frame) = self
+            .find_frame(block_id)
+            .and_then(|idx| self.frames[idx].as_ref())
+        else {
+            return;
+        };
+        for images in self.snapshots.values_mut() {
+            images.entry(block_id).or_insert_with(|| frame.data.clone());
+        }
+    }
+
+    // 块即将被整块覆写或释放：为尚未保存该块的活动快照保留当前内容，
+    // 块不在缓冲区中时先从磁盘读出
+    fn preserve_block_for_snapshots(&mut self, block_id: BlockId) -> io::Result<()> {
+        if self
+            .snapshots
+            .values()
+            .all(|images| images.contains_key(&block_id))
+        {
+            return Ok(());
+        }
+        if self.contains(block_id) {
+            self.preserve_for_snapshots(block_id);
+            return Ok(());
+        }
+        let mut image = vec![0u8; self.block_size];
+        self.handle.read_block(block_id, &mut image)?;
+        for images in self.snapshots.values_mut() {
+            images.entry(block_id).or_insert_with(|| image.clone());
+        }
+        Ok(())
+    }
+
+    // 累计调用 fetch 的次数，用于观察上层操作访问页面的次数
+    pub fn fetch_count(&self) -> u64 {
+        self.fetch_count
+    }
+
+    // 块当前是否驻留在缓冲区中
+    pub fn contains(&self, block_id: BlockId) -> bool {
+        self.map.contains_key(&block_id)
+    }
+
+    // 块的 pin 计数；不在缓冲区中时返回 None
+    pub fn pin_count(&self, block_id: BlockId) -> Option<usize> {
+        self.find_frame(block_id)
+            .and_then(|idx| self.frames[idx].as_ref())
+            .map(|frame| frame.pin_count)
+    }
+
+    // 块是否在缓冲区中且为脏页
+    pub fn is_dirty(&self, block_id: BlockId) -> bool {
+        self.find_frame(block_id)
+            .and_then(|idx| self.frames[idx].as_ref())
+            .is_some_and(|frame| frame.dirty)
+    }
+
+    // 当前所有脏页的块号（按块号升序），检查点可以先记下这份快照，再逐页写回
+    pub fn dirty_page_ids(&self) -> Vec<BlockId> {
+        let mut ids: Vec<BlockId> = self
+            .frames
+            .iter()
+            .flatten()
+            .filter(|frame| frame.dirty)
+            .map(|frame| frame.block_id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    // 脏页数超过阈值时，按 LRU 顺序（最久未使用优先）写回脏页直到不超过阈值
+    // 只清除脏标记，不改变 pin 计数
+    pub fn maybe_flush(&mut self) -> io::Result<()> {
+        let Some(threshold) = self.dirty_page_threshold else {
+            return Ok(());
+        };
+        let mut dirty = self.frames.iter().flatten().filter(|f| f.dirty).count();
+        if dirty <= threshold {
+            return Ok(());
+        }
+        for pos in 0..self.lru_list.len() {
+            if dirty <= threshold {
+                break;
+            }
+            let idx = self.lru_list[pos];
+            if let Some(frame) = &mut self.frames[idx] {
+                if frame.dirty {
+                    self.handle.write_block(frame.block_id, &frame.data)?;
+                    frame.dirty = false;
+                    dirty -= 1;
+                }
+            }
+        }
+        Ok(())
     }
 
     // 刷写所有脏页到磁盘，并调用底层 FileHandle flush
     pub fn flush_all(&mut self) -> io::Result<()> {
-        for opt in &mut self.frames {
-            if let Some(frame) = opt {
+        self.flush_all_with_progress(|_, _| {})
+    }
+
+    // 同 flush_all，每写回一个脏页后以 (已写回页数, 脏页总数) 调用 progress，
+    // 便于在检查点等长时间刷写时展示进度；没有脏页时不调用
+    pub fn flush_all_with_progress<F: FnMut(usize, usize)>(
+        &mut self,
+        mut progress: F,
+    ) -> io::Result<()> {
+        let total = self.frames.iter().flatten().filter(|frame| frame.dirty).count();
+        let mut flushed = 0;
+        for frame in self.frames.iter_mut().flatten() {
+            if frame.dirty {
+                self.handle.write_block(frame.block_id, &frame.data)?;
+                frame.dirty = false;
+                flushed += 1;
+                progress(flushed, total);
+            }
+        }
+        // 刷新文件头元数据；FileHandle::flush 会先 fsync 上面写出的数据页，再写文件头
+        self.handle.flush()?;
+        Ok(())
+    }
+
+    // 检查点：写回所有脏页，强制 fsync 数据文件，最后截断预写日志
+    // 顺序不能颠倒：截断前数据页必须已经落盘，中途崩溃时日志仍完整，可以据此恢复
+    pub fn checkpoint(&mut self) -> io::Result<()> {
+        self.flush_all()?;
+        self.handle.force_sync()?;
+        if let Some(wal) = &mut self.wal {
+            wal.truncate_to(0)?;
+        }
+        Ok(())
+    }
+
+    // 若指定块在缓冲区中且为脏页，则写回磁盘（不调用 FileHandle flush）
+    pub fn flush_page(&mut self, block_id: BlockId) -> io::Result<()> {
+        if let Some(idx) = self.find_frame(block_id) {
+            if let Some(frame) = &mut self.frames[idx] {
                 if frame.dirty {
                     self.handle.write_block(frame.block_id, &frame.data)?;
                     frame.dirty = false;
                 }
             }
         }
-        // 刷新文件头元数据
-        self.handle.flush()?;
         Ok(())
     }
+
+    // 写回（若为脏页）并立即丢弃指定块的帧，释放一个帧位；块仍被 pin 时报错且不做任何修改，
+    // 块不在缓冲区中时直接返回
+    pub fn flush_and_evict(&mut self, block_id: BlockId) -> io::Result<()> {
+        let Some(idx) = self.find_frame(block_id) else {
+            return Ok(());
+        };
+        if let Some(frame) = &self.frames[idx] {
+            if frame.pin_count > 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "块 {} 仍被 pin（pin 计数 {}），不能逐出",
+                        block_id, frame.pin_count
+                    ),
+                ));
+            }
+        }
+        self.flush_page(block_id)?;
+        self.discard_frame(block_id);
+        Ok(())
+    }
+
+    // 写回所有脏页后丢弃全部未被 pin 的帧，释放缓冲区内存（被 pin 的帧保留）
+    pub fn evict_all(&mut self) -> io::Result<()> {
+        self.flush_all()?;
+        for idx in 0..self.frames.len() {
+            let evictable = matches!(&self.frames[idx], Some(frame) if frame.pin_count == 0);
+            if evictable {
+                if let Some(frame) = self.frames[idx].take() {
+                    self.map.remove(&frame.block_id);
+                }
+                if let Some(pos) = self.lru_list.iter().position(|&x| x == idx) {
+                    self.lru_list.remove(pos);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // 丢弃缓冲区中的所有帧，脏页不写回（用于表被清空等场景）
+    // 仍有帧被 pin 时拒绝执行，避免使持有 PageGuard 的调用方访问失效内存
+    pub fn discard_all(&mut self) -> io::Result<()> {
+        if self.frames.iter().flatten().any(|f| f.pin_count > 0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "缓冲区中仍有被 pin 的页面，不能丢弃",
+            ));
+        }
+        self.frames.iter_mut().for_each(|f| *f = None);
+        self.map.clear();
+        self.lru_list.clear();
+        self.free_list.clear();
+        Ok(())
+    }
+
+    // 估算缓冲区当前占用的内存（驻留帧数 * 块大小，加上快照保留的页面副本）
+    pub fn approx_memory_bytes(&self) -> usize {
+        let snapshot_pages: usize = self.snapshots.values().map(|images| images.len()).sum();
+        (self.map.len() + snapshot_pages) * self.block_size
+    }
+
     // 分配新数据页，初始化页头并写入磁盘，返回 BlockId
     pub fn allocate_data_page(&mut self) -> io::Result<BlockId> {
         let fm_bid = self.handle.allocate_block()?;
         let bid = fm_bid;
+        // 快照开始时该块还不是数据页（空闲块或尚未分配），保留写入新页头之前的内容，
+        // 快照视图中它仍不是数据页
+        self.preserve_block_for_snapshots(bid)?;
+        // 复用的块可能仍以旧内容缓存在缓冲区中（例如释放前被读取过），丢弃旧帧
+        self.discard_frame(bid);
         // 初始化页面内容：写入空白 header
         let mut buf = vec![0u8; self.block_size];
-        let header = PageHeader {
-            slot_count: 0,
-            free_offset: PageHeader::SIZE as u16,
-            free_bytes: (self.block_size - PageHeader::SIZE) as u16,
-        };
+        let header = PageHeader::new(self.block_size);
         header.to_bytes(&mut buf[..PageHeader::SIZE])?;
         self.handle.write_block(bid, &buf)?;
         self.free_list.push_back(bid);
@@ -192,23 +469,22 @@ impl BufferManager {
     }
     // 释放数据页，将 BlockId 加入空闲列表
     pub fn free_page(&mut self, block_id: BlockId) -> io::Result<()> {
+        // 活动快照仍需读到释放前的页面内容
+        self.preserve_block_for_snapshots(block_id)?;
         // 如果在缓冲区中，移除缓存
-        if let Some(idx) = self.find_frame(block_id) {
-            self.frames[idx] = None;
-            if let Some(pos) = self.lru_list.iter().position(|&x| x == idx) {
-                self.lru_list.remove(pos);
-            }
-        }
-        if let Some(&idx) = self.map.get(&block_id) {
+        self.discard_frame(block_id);
+        self.free_list.push_back(block_id);
+        Ok(())
+    }
+
+    // 内部：丢弃指定块的缓存帧（不写回）
+    fn discard_frame(&mut self, block_id: BlockId) {
+        if let Some(idx) = self.map.remove(&block_id) {
             self.frames[idx] = None;
             if let Some(pos) = self.lru_list.iter().position(|&x| x == idx) {
                 self.lru_list.remove(pos);
             }
-            // 从 map 中移除映射
-            self.map.remove(&block_id);
         }
-        self.free_list.push_back(block_id);
-        Ok(())
     }
 
     // 内部：查找指定块对应的帧索引
@@ -248,6 +524,10 @@ pub struct CacheEntry<T> {
 // 通用缓存，支持 LRU 与 CLOCK 替换算法
 pub struct Cache<T> {
     capacity: usize,
+    // 低水位：批量淘汰后保留的条目数
+    low_watermark: usize,
+    // 高水位：条目数达到该值时触发批量淘汰
+    high_watermark: usize,
     policy: ReplacementPolicy,
     map: HashMap<String, CacheEntry<T>>,
     // LRU 队列：队头为最久未使用
@@ -259,8 +539,29 @@ pub struct Cache<T> {
 
 impl<T> Cache<T> {
     pub fn new(capacity: usize, policy: ReplacementPolicy) -> Self {
+        // 默认水位：满容量时淘汰一个条目，与逐条替换行为一致
+        Cache::with_watermarks(capacity, capacity.saturating_sub(1), capacity, policy)
+    }
+
+    // 创建带水位线的缓存：条目数达到 high 时一次性淘汰到 low，减少边界处的频繁替换
+    // 要求 low < high <= capacity（capacity 为 0 时允许 low == high == 0）
+    pub fn with_watermarks(
+        capacity: usize,
+        low: usize,
+        high: usize,
+        policy: ReplacementPolicy,
+    ) -> Self {
+        assert!(
+            high <= capacity && (low < high || high == 0),
+            "invalid cache watermarks: low={} high={} capacity={}",
+            low,
+            high,
+            capacity
+        );
         Cache {
             capacity,
+            low_watermark: low,
+            high_watermark: high,
             policy,
             map: HashMap::new(),
             lru: VecDeque::new(),
@@ -277,11 +578,8 @@ impl<T> Cache<T> {
             }
             return;
         }
-        if self.map.len() >= self.capacity {
-            match self.policy {
-                ReplacementPolicy::LRU => self.evict_lru(),
-                ReplacementPolicy::CLOCK => self.evict_clock(),
-            }
+        if self.map.len() >= self.high_watermark {
+            self.evict_to_low_watermark();
         }
         let entry = CacheEntry {
             key: key.clone(),
@@ -305,6 +603,84 @@ impl<T> Cache<T> {
         None
     }
 
+    // 查找 key，未命中时调用 f 计算并插入；只在未命中时调用 f，命中与插入都会更新访问记录
+    pub fn get_or_insert_with<F: FnOnce() -> T>(&mut self, key: String, f: F) -> &T {
+        if let Some(entry) = self.map.get_mut(&key) {
+            entry.used = true;
+            self.update_usage(&key);
+        } else {
+            let value = f();
+            self.insert(key.clone(), value);
+        }
+        &self.map[&key].value
+    }
+
+    // 移除指定条目并返回其值，同时维护 LRU 队列与 CLOCK 环
+    pub fn remove(&mut self, key: &str) -> Option<T> {
+        let entry = self.map.remove(key)?;
+        if let Some(pos) = self.lru.iter().position(|k| k == key) {
+            self.lru.remove(pos);
+        }
+        if let Some(pos) = self.clock.iter().position(|k| k == key) {
+            self.remove_clock_at(pos);
+        }
+        Some(entry.value)
+    }
+
+    // 当前缓存条目数
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    // 清空缓存及替换算法的辅助结构
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.lru.clear();
+        self.clock.clear();
+        self.clock_hand = 0;
+    }
+
+    // 按 LRU 顺序（最久未使用在前）列出缓存中的 key，不影响访问记录
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.lru.iter()
+    }
+
+    // 取出全部条目（按 LRU 顺序）并清空缓存，可用于关闭前持久化热点集合
+    pub fn drain(&mut self) -> Vec<(String, T)> {
+        let order: Vec<String> = self.lru.drain(..).collect();
+        let mut entries = Vec::with_capacity(order.len());
+        for key in order {
+            if let Some(entry) = self.map.remove(&key) {
+                entries.push((key, entry.value));
+            }
+        }
+        self.clear();
+        entries
+    }
+
+    // 缓存容量上限
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // 按替换策略连续淘汰，直到条目数不超过低水位
+    fn evict_to_low_watermark(&mut self) {
+        while self.map.len() > self.low_watermark {
+            let before = self.map.len();
+            match self.policy {
+                ReplacementPolicy::LRU => self.evict_lru(),
+                ReplacementPolicy::CLOCK => self.evict_clock(),
+            }
+            if self.map.len() == before {
+                break;
+            }
+        }
+    }
+
     fn update_usage(&mut self, key: &str) {
         if let Some(pos) = self.lru.iter().position(|k| k == key) {
             self.lru.remove(pos);
@@ -316,14 +692,25 @@ impl<T> Cache<T> {
         if let Some(evict_key) = self.lru.pop_front() {
             self.map.remove(&evict_key);
             if let Some(pos) = self.clock.iter().position(|k| k == &evict_key) {
-                self.clock.remove(pos);
-                if self.clock_hand >= self.clock.len() && !self.clock.is_empty() {
-                    self.clock_hand = 0;
-                }
+                self.remove_clock_at(pos);
             }
         }
     }
 
+    // 从 CLOCK 环中移除 pos 处的 key 并维护指针：
+    // - 移除指针之前的元素时，后续元素整体左移，指针减一以仍指向原条目
+    // - 移除指针所在元素时，指针保持下标不变，即指向原来的下一个条目
+    // - 指针越过末尾（或环为空）时回到 0
+    fn remove_clock_at(&mut self, pos: usize) {
+        self.clock.remove(pos);
+        if pos < self.clock_hand {
+            self.clock_hand -= 1;
+        }
+        if self.clock_hand >= self.clock.len() {
+            self.clock_hand = 0;
+        }
+    }
+
     fn evict_clock(&mut self) {
         if self.clock.is_empty() {
             return;
@@ -336,10 +723,7 @@ impl<T> Cache<T> {
                 } else {
                     let evict_key = key.clone();
                     self.map.remove(&evict_key);
-                    self.clock.remove(self.clock_hand);
-                    if self.clock_hand >= self.clock.len() && !self.clock.is_empty() {
-                        self.clock_hand = 0;
-                    }
+                    self.remove_clock_at(self.clock_hand);
                     if let Some(pos) = self.lru.iter().position(|k| k == &evict_key) {
                         self.lru.remove(pos);
                     }
@@ -351,6 +735,16 @@ impl<T> Cache<T> {
     }
 }
 
+impl<T: AsRef<[u8]>> Cache<T> {
+    // 估算缓存占用的内存：每个条目按 key 与 value 的字节数计
+    pub fn approx_bytes(&self) -> usize {
+        self.map
+            .values()
+            .map(|entry| entry.key.len() + entry.value.as_ref().len())
+            .sum()
+    }
+}
+
 // 定义专用缓存类型：
 // 查询计划缓存，保存 SQL（或计划）字符串
 pub type QueryPlanCache = Cache<String>;
@@ -391,19 +785,53 @@ impl MemoryManager {
     }
 
     // 访问存储在缓冲池中的页面
-    pub fn fetch_page(&mut self, block_id: u32) -> io::Result<PageGuard> {
+    pub fn fetch_page(&mut self, block_id: BlockId) -> io::Result<PageGuard> {
         self.data_buffer.fetch(block_id)
     }
 
     // 从磁盘加载一个页面到空槽（若存在空槽则自动加载）
-    pub fn load_page_to_empty_slot(&mut self, block_id: u32) -> io::Result<PageGuard> {
+    pub fn load_page_to_empty_slot(&mut self, block_id: BlockId) -> io::Result<PageGuard> {
         // BufferManager.fetch 内部会优先使用空闲帧加载页面
         self.data_buffer.fetch(block_id)
     }
 
     // 将页面从磁盘加载到牺牲者缓冲池插槽（触发替换算法）
-    pub fn load_page_to_victim_slot(&mut self, block_id: u32) -> io::Result<PageGuard> {
+    pub fn load_page_to_victim_slot(&mut self, block_id: BlockId) -> io::Result<PageGuard> {
         // 当不存在空闲帧时，BufferManager.fetch 会通过 LRU（或 CLOCK）选择牺牲者插槽
         self.data_buffer.fetch(block_id)
     }
+    // 估算各缓存当前占用的内存总量（字节）
+    pub fn approx_memory_bytes(&self) -> usize {
+        self.data_buffer.approx_memory_bytes()
+            + self.query_cache.approx_bytes()
+            + self.dict_cache.approx_bytes()
+            + self.log_buffer.approx_bytes()
+    }
+
+    // 表结构变化后使相关查询计划失效：移除所有 key 中包含该表名的计划
+    // 按子串匹配，宁可多删（名称相互包含的表）也不保留过期计划
+    pub fn invalidate_plans_for(&mut self, table: &str) {
+        if table.is_empty() {
+            return;
+        }
+        let stale: Vec<String> = self
+            .query_cache
+            .map
+            .keys()
+            .filter(|key| key.contains(table))
+            .cloned()
+            .collect();
+        for key in stale {
+            self.query_cache.remove(&key);
+        }
+    }
+
+    // 内存压力下释放所有缓存：数据缓冲区先写回脏页再丢弃帧，三个字符串缓存直接清空
+    pub fn evict_all(&mut self) -> io::Result<()> {
+        self.data_buffer.evict_all()?;
+        self.query_cache.clear();
+        self.dict_cache.clear();
+        self.log_buffer.clear();
+        Ok(())
+    }
 }
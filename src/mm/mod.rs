@@ -4,5 +4,7 @@ pub mod page_compact;
 pub mod page_guard;
 pub mod page_header;
 pub mod page_ops;
+pub mod shared_buffer_manager;
 
 pub use buffer_manager::BufferManager;
+pub use shared_buffer_manager::SharedBufferManager;
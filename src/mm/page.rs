@@ -1,39 +1,221 @@
-use crate::mm::page_header::PageHeader;
+use crate::mm::page_compact::PageCompact;
+use crate::mm::page_header::{
+    PageHeader, FLAG_BLOOM, FLAG_FRAMED, FLAG_WIDE_SLOTS, FRAME_PREFIX_BYTES, NO_FREE_SLOT,
+    PAGE_FORMAT_VERSION,
+};
+use crate::mm::page_ops::{unframe, PageOps};
+use std::fmt::{self, Write};
 use std::io::{self, ErrorKind};
 
+/// Page::repair 的修复报告：记录被修正的字段（修正前, 修正后）以及被清除的损坏槽
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    pub slot_count: Option<(u16, u16)>,
+    pub free_offset: Option<(u32, u32)>,
+    pub free_bytes: Option<(u32, u32)>,
+    /// 记录范围超出数据区、被置为空槽的槽 ID
+    pub dropped_slots: Vec<u16>,
+    /// 修复后的有效记录数
+    pub live_slots: usize,
+}
+
+impl RepairReport {
+    /// 页面是否本来就是一致的（未做任何修改）
+    pub fn is_clean(&self) -> bool {
+        self.slot_count.is_none()
+            && self.free_offset.is_none()
+            && self.free_bytes.is_none()
+            && self.dropped_slots.is_empty()
+    }
+}
+
+/// Page::verify 发现的第一处不一致
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageInconsistency {
+    /// 页头 slot_count 与槽目录项数不一致
+    SlotCountMismatch { header: u16, actual: usize },
+    /// 页头 free_offset 与数据区末尾（页头 + 数据区长度）不一致
+    FreeOffsetMismatch { header: u32, expected: usize },
+    /// 有效槽的记录范围不在数据区内
+    SlotOutOfRange { slot: u16, offset: u32, len: u32 },
+    /// 两条有效记录的字节范围重叠
+    OverlappingRecords { first: u16, second: u16 },
+    /// 页头 free_bytes 与按布局计算的值不一致
+    FreeBytesMismatch { header: u32, expected: usize },
+}
+
+impl fmt::Display for PageInconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageInconsistency::SlotCountMismatch { header, actual } => {
+                write!(
+                    f,
+                    "页头 slot_count={} 与槽目录项数 {} 不一致",
+                    header, actual
+                )
+            }
+            PageInconsistency::FreeOffsetMismatch { header, expected } => {
+                write!(
+                    f,
+                    "页头 free_offset={} 与数据区末尾 {} 不一致",
+                    header, expected
+                )
+            }
+            PageInconsistency::SlotOutOfRange { slot, offset, len } => {
+                write!(f, "槽 {} 的记录 [{}, +{}) 超出数据区", slot, offset, len)
+            }
+            PageInconsistency::OverlappingRecords { first, second } => {
+                write!(f, "槽 {} 与槽 {} 的记录重叠", first, second)
+            }
+            PageInconsistency::FreeBytesMismatch { header, expected } => {
+                write!(f, "页头 free_bytes={} 与计算值 {} 不一致", header, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PageInconsistency {}
+
+/// Page::try_insert 放不下记录时的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertError {
+    /// 连续空闲空间不够，但紧缩回收已删除记录的空洞后可以放下
+    NeedsCompaction,
+    /// 即使紧缩也放不下（或槽目录已达上限）
+    Full,
+}
+
+impl fmt::Display for InsertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InsertError::NeedsCompaction => write!(f, "页面空间不足，紧缩后可以插入"),
+            InsertError::Full => write!(f, "页面空间不足，紧缩后仍无法插入"),
+        }
+    }
+}
+
+impl std::error::Error for InsertError {}
+
 /// 内存页结构，包含页头、数据区和槽目录
 pub struct Page {
     pub header: PageHeader,
     /// 记录数据区（不包含页头）
     pub data: Vec<u8>,
-    /// 槽目录：每个槽存 (offset, length)
-    pub slots: Vec<(u16, u16)>,
+    /// 槽目录：每个槽存 (offset, length)，持久化宽度由 FLAG_WIDE_SLOTS 决定；已删除的槽 length 为 0，
+    /// offset 存空闲槽链表中下一个空闲槽号（见 PageHeader::free_slot_head）
+    pub slots: Vec<(u32, u32)>,
+    /// 页面（帧）总字节数，插入时据此校验数据区不会与槽目录重叠
+    pub page_size: usize,
 }
 
 impl Page {
-    /// 从 frame 读取并解析成 Page
-    pub fn load(frame: &mut [u8]) -> io::Result<Page> {
+    /// 构造大小为 page_size 的空页面
+    pub fn new(page_size: usize) -> Page {
+        Page {
+            header: PageHeader::new(page_size),
+            data: Vec::new(),
+            slots: Vec::new(),
+            page_size,
+        }
+    }
+
+    /// 从 frame 读取并解析成 Page（只读取 frame，不会修改它）
+    pub fn load(frame: &[u8]) -> io::Result<Page> {
+        let (header, slots) = Page::load_slots(frame)?;
+        // 解析数据区（free_offset 已在 load_slots 中校验）
+        let data_end = header.free_offset as usize;
+        let data_len = data_end - PageHeader::SIZE;
+        let mut data = vec![0u8; data_len];
+        data.copy_from_slice(&frame[PageHeader::SIZE..data_end]);
+        Ok(Page {
+            header,
+            data,
+            slots,
+            page_size: frame.len(),
+        })
+    }
+
+    /// 直接从 frame 中借用指定槽的记录，不拷贝数据区；校验规则与 get_record 相同
+    pub fn record_in_frame(frame: &[u8], slot_id: u16) -> io::Result<&[u8]> {
+        let (header, slots) = Page::load_slots(frame)?;
+        let Some(&(off, len)) = slots.get(slot_id as usize) else {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "无效的槽 ID"));
+        };
+        if len == 0 {
+            return Err(io::Error::new(ErrorKind::NotFound, "指定槽无记录或已删除"));
+        }
+        // load_slots 已校验有效槽的记录完整落在数据区内
+        let stored = &frame[off as usize..off as usize + len as usize];
+        if !header.has_framing() {
+            return Ok(stored);
+        }
+        unframe(slot_id, stored)
+    }
+
+    /// 只解析页头和槽目录（不拷贝记录数据），供统计类操作使用
+    /// 页面格式版本必须与 PAGE_FORMAT_VERSION 一致，槽目录必须与页头中的校验和相符；
+    /// 页头中的 slot_count、free_offset 以及每个槽的 (offset, length) 都会做越界校验，
+    /// 损坏的帧返回 InvalidData 而不是在索引时 panic
+    pub fn load_slots(frame: &[u8]) -> io::Result<(PageHeader, Vec<(u32, u32)>)> {
         // 解析页头
-        let header = PageHeader::from_bytes(&frame[0..PageHeader::SIZE])?;
+        let header = PageHeader::from_bytes(frame)?;
+        // 未知版本的页面布局可能不同，拒绝解析以免误读
+        if header.version != PAGE_FORMAT_VERSION {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported page format version {}", header.version),
+            ));
+        }
         let page_size = frame.len();
         let slot_count = header.slot_count as usize;
-        let slot_dir_size = slot_count * 4;
+        let entry_size = header.slot_entry_size();
+        let slot_dir_size = slot_count * entry_size;
         if page_size < PageHeader::SIZE + slot_dir_size {
             return Err(io::Error::new(
                 ErrorKind::InvalidData,
                 "frame too small for slots",
             ));
         }
+        // 槽目录校验和：单个槽项被破坏时在这里定位到槽目录区域
+        let slot_dir = &frame[page_size - slot_dir_size..];
+        let checksum = PageHeader::slot_dir_checksum_of(slot_dir);
+        if checksum != header.slot_dir_checksum {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "slot directory corrupted: checksum {:#06x} does not match header {:#06x}",
+                    checksum, header.slot_dir_checksum
+                ),
+            ));
+        }
         // 解析槽目录（位于页末）
         let mut slots = Vec::with_capacity(slot_count);
         let mut slot_base = page_size - slot_dir_size;
         for _ in 0..slot_count {
-            let off = u16::from_le_bytes([frame[slot_base], frame[slot_base + 1]]);
-            let len = u16::from_le_bytes([frame[slot_base + 2], frame[slot_base + 3]]);
+            let entry = &frame[slot_base..slot_base + entry_size];
+            let (off, len) = if header.has_wide_slots() {
+                (
+                    u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]),
+                    u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]),
+                )
+            } else {
+                (
+                    u16::from_le_bytes([entry[0], entry[1]]) as u32,
+                    u16::from_le_bytes([entry[2], entry[3]]) as u32,
+                )
+            };
             slots.push((off, len));
-            slot_base += 4;
+            slot_base += entry_size;
+        }
+        // 空闲槽链表头必须指向目录内的槽（或为空）
+        let head = header.free_slot_head;
+        if head != NO_FREE_SLOT && head as usize >= slot_count {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("free slot list head {} out of slot directory", head),
+            ));
         }
-        // 解析数据区
+        // 数据区位于页头之后、槽目录之前
         let data_end = header.free_offset as usize;
         if data_end < PageHeader::SIZE || data_end > page_size - slot_dir_size {
             return Err(io::Error::new(
@@ -41,21 +223,29 @@ impl Page {
                 "invalid free_offset",
             ));
         }
-        let data_len = data_end - PageHeader::SIZE;
-        let mut data = vec![0u8; data_len];
-        data.copy_from_slice(&frame[PageHeader::SIZE..data_end]);
-        Ok(Page {
-            header,
-            data,
-            slots,
-        })
+        // 有效槽的记录必须完整落在数据区内
+        for (slot_id, &(off, len)) in slots.iter().enumerate() {
+            if len == 0 {
+                continue;
+            }
+            let start = off as usize;
+            let end = start + len as usize;
+            if start < PageHeader::SIZE || end > data_end {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("slot {} out of data region", slot_id),
+                ));
+            }
+        }
+        Ok((header, slots))
     }
 
     /// 将 Page 序列化并写入 frame
     pub fn flush(&self, frame: &mut [u8]) -> io::Result<()> {
         let page_size = frame.len();
         let slot_count = self.slots.len();
-        let slot_dir_size = slot_count * 4;
+        let entry_size = self.header.slot_entry_size();
+        let slot_dir_size = slot_count * entry_size;
         // 检查 frame 空间
         if page_size < PageHeader::SIZE + self.data.len() + slot_dir_size {
             return Err(io::Error::new(
@@ -63,22 +253,453 @@ impl Page {
                 "frame too small to flush page",
             ));
         }
-        // 更新并写入页头
-        let mut hdr = self.header.clone();
-        hdr.slot_count = slot_count as u16;
-        hdr.free_offset = (PageHeader::SIZE + self.data.len()) as u16;
-        // free_bytes 保持在内存结构中管理
-        hdr.to_bytes(&mut frame[0..PageHeader::SIZE])?;
         // 写入数据区
         let data_end = PageHeader::SIZE + self.data.len();
         frame[PageHeader::SIZE..data_end].copy_from_slice(&self.data);
         // 写入槽目录
-        let mut slot_base = page_size - slot_dir_size;
+        let dir_base = page_size - slot_dir_size;
+        let mut slot_base = dir_base;
         for &(off, len) in &self.slots {
-            frame[slot_base..slot_base + 2].copy_from_slice(&off.to_le_bytes());
-            frame[slot_base + 2..slot_base + 4].copy_from_slice(&len.to_le_bytes());
-            slot_base += 4;
+            let entry = &mut frame[slot_base..slot_base + entry_size];
+            if self.header.has_wide_slots() {
+                entry[0..4].copy_from_slice(&off.to_le_bytes());
+                entry[4..8].copy_from_slice(&len.to_le_bytes());
+            } else {
+                // 普通槽目录项只有 2 字节，insert_record_at 已拒绝超出范围的偏移和长度
+                entry[0..2].copy_from_slice(&(off as u16).to_le_bytes());
+                entry[2..4].copy_from_slice(&(len as u16).to_le_bytes());
+            }
+            slot_base += entry_size;
         }
+        // 更新并写入页头（槽目录校验和按刚写入的目录计算）
+        let mut hdr = self.header.clone();
+        hdr.slot_count = slot_count as u16;
+        hdr.free_offset = (PageHeader::SIZE + self.data.len()) as u32;
+        // free_bytes 按实际布局重算后写入，避免增量维护的偏差被持久化
+        hdr.free_bytes = (page_size - PageHeader::SIZE - self.data.len() - slot_dir_size) as u32;
+        hdr.slot_dir_checksum = PageHeader::slot_dir_checksum_of(&frame[dir_base..]);
+        hdr.to_bytes(&mut frame[0..PageHeader::SIZE])?;
         Ok(())
     }
+
+    /// 检查页面自身是否一致，返回发现的第一处不一致：
+    /// slot_count 与槽目录项数、free_offset 与数据区长度、有效记录是否落在数据区内且互不重叠、
+    /// free_bytes 与按布局计算的值。只读取内存结构，不修改页面
+    pub fn verify(&self) -> Result<(), PageInconsistency> {
+        if self.header.slot_count as usize != self.slots.len() {
+            return Err(PageInconsistency::SlotCountMismatch {
+                header: self.header.slot_count,
+                actual: self.slots.len(),
+            });
+        }
+        let data_end = PageHeader::SIZE + self.data.len();
+        if self.header.free_offset as usize != data_end {
+            return Err(PageInconsistency::FreeOffsetMismatch {
+                header: self.header.free_offset,
+                expected: data_end,
+            });
+        }
+        let mut live = Vec::new();
+        for (slot, &(offset, len)) in self.slots.iter().enumerate() {
+            if len == 0 {
+                continue;
+            }
+            let slot = slot as u16;
+            if (offset as usize) < PageHeader::SIZE || offset as usize + len as usize > data_end {
+                return Err(PageInconsistency::SlotOutOfRange { slot, offset, len });
+            }
+            live.push((offset, len, slot));
+        }
+        // 按起始偏移排序后只需比较相邻的记录
+        live.sort_unstable();
+        for pair in live.windows(2) {
+            let (offset, len, first) = pair[0];
+            let (next_offset, _, second) = pair[1];
+            if offset as usize + len as usize > next_offset as usize {
+                return Err(PageInconsistency::OverlappingRecords { first, second });
+            }
+        }
+        let expected = self
+            .page_size
+            .saturating_sub(data_end + self.slots.len() * self.header.slot_entry_size());
+        if self.header.free_bytes as usize != expected {
+            return Err(PageInconsistency::FreeBytesMismatch {
+                header: self.header.free_bytes,
+                expected,
+            });
+        }
+        Ok(())
+    }
+
+    /// 按数据区和槽目录的实际大小重新计算 free_bytes（数据区末尾到槽目录之间的连续空间），
+    /// 修正增量维护可能产生的偏差；也可单独作为修复步骤调用
+    pub fn recompute_free_bytes(&mut self) {
+        let used =
+            PageHeader::SIZE + self.data.len() + self.slots.len() * self.header.slot_entry_size();
+        self.header.free_bytes = self.page_size.saturating_sub(used) as u32;
+    }
+
+    /// 按槽目录和数据区的实际内容修复页头：清除记录范围越界的槽，
+    /// 重新计算 slot_count、free_offset 与 free_bytes，并报告修改内容；健康页面上调用不做任何修改
+    pub fn repair(&mut self) -> RepairReport {
+        let mut report = RepairReport::default();
+        let data_end = PageHeader::SIZE + self.data.len();
+        for (slot_id, slot) in self.slots.iter_mut().enumerate() {
+            let (off, len) = *slot;
+            if len == 0 {
+                continue;
+            }
+            let start = off as usize;
+            if start < PageHeader::SIZE || start + len as usize > data_end {
+                *slot = (0, 0);
+                report.dropped_slots.push(slot_id as u16);
+            }
+        }
+        // 链表指针同样可能损坏，按修复后的空槽重新串起空闲槽链表
+        self.rebuild_free_slots();
+        report.live_slots = self.slots.iter().filter(|&&(_, len)| len != 0).count();
+
+        let slot_count = self.slots.len() as u16;
+        if self.header.slot_count != slot_count {
+            report.slot_count = Some((self.header.slot_count, slot_count));
+            self.header.slot_count = slot_count;
+        }
+        let free_offset = data_end as u32;
+        if self.header.free_offset != free_offset {
+            report.free_offset = Some((self.header.free_offset, free_offset));
+            self.header.free_offset = free_offset;
+        }
+        let old_free_bytes = self.header.free_bytes;
+        self.recompute_free_bytes();
+        if self.header.free_bytes != old_free_bytes {
+            report.free_bytes = Some((old_free_bytes, self.header.free_bytes));
+        }
+        if !report.dropped_slots.is_empty() {
+            self.rebuild_bloom();
+        }
+        report
+    }
+
+    /// 裁剪槽目录末尾的空槽并把其占用的目录空间计入 free_bytes，返回裁剪的槽数；
+    /// 中间的空槽保持不变，以保证其余记录的槽号稳定
+    pub fn shrink_slot_dir(&mut self) -> usize {
+        let mut trimmed = 0;
+        while let Some(&(_, 0)) = self.slots.last() {
+            self.slots.pop();
+            trimmed += 1;
+        }
+        self.header.slot_count = self.slots.len() as u16;
+        self.header.free_bytes += (trimmed * self.header.slot_entry_size()) as u32;
+        if trimmed > 0 {
+            self.rebuild_free_slots();
+        }
+        trimmed
+    }
+
+    /// 按槽目录中的空槽重建空闲槽链表，槽号小的在链表前部
+    pub fn rebuild_free_slots(&mut self) {
+        let mut head = NO_FREE_SLOT;
+        for (slot_id, slot) in self.slots.iter_mut().enumerate().rev() {
+            if slot.1 == 0 {
+                slot.0 = head as u32;
+                head = slot_id as u16;
+            }
+        }
+        self.header.free_slot_head = head;
+    }
+
+    /// 把空槽从空闲槽链表中摘下：位于表头时 O(1)，否则沿链表查找前驱；
+    /// 链表最多走 slot_count 步，损坏成环时不会死循环
+    pub(crate) fn unlink_free_slot(&mut self, slot_id: u16) {
+        let next = self.slots[slot_id as usize].0 as u16;
+        if self.header.free_slot_head == slot_id {
+            self.header.free_slot_head = next;
+            return;
+        }
+        let mut prev = self.header.free_slot_head;
+        for _ in 0..self.slots.len() {
+            let Some(&(prev_next, 0)) = self.slots.get(prev as usize) else {
+                return;
+            };
+            if prev_next == slot_id as u32 {
+                self.slots[prev as usize].0 = next as u32;
+                return;
+            }
+            prev = prev_next as u16;
+        }
+    }
+
+    /// 按记录字节数把约一半的记录移到 other，返回 (原槽 ID, other 中的新槽 ID) 列表
+    /// 按槽号顺序保留前半部分、移动后半部分，便于 B+ 树叶子分裂保持键序；
+    /// 本页随后紧缩并裁剪末尾空槽，other 继承本页的布隆过滤器和长度前缀设置。
+    /// other 空间不足时返回错误且两个页面都不被修改
+    pub fn split(&mut self, other: &mut Page) -> io::Result<Vec<(u16, u16)>> {
+        let live: Vec<(u16, u32)> = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(_, len))| len != 0)
+            .map(|(slot, &(_, len))| (slot as u16, len))
+            .collect();
+        let total: usize = live.iter().map(|&(_, len)| len as usize).sum();
+        // 找到分裂点：前半部分累计字节数不超过一半（至少保留一条）
+        let mut kept = 0;
+        let mut acc = 0;
+        for &(_, len) in &live {
+            if kept > 0 && (acc + len as usize) * 2 > total {
+                break;
+            }
+            acc += len as usize;
+            kept += 1;
+        }
+        // 有两条以上记录时至少移动一条
+        if kept == live.len() && live.len() > 1 {
+            kept -= 1;
+        }
+        let moving = &live[kept..];
+        if moving.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let entry_size = other.header.slot_entry_size();
+        let needed: usize = moving
+            .iter()
+            .map(|&(_, len)| len as usize + entry_size)
+            .sum();
+        if needed > other.header.free_bytes as usize {
+            return Err(io::Error::other("目标页面空间不足，无法完成分裂"));
+        }
+        if self.header.has_framing() != other.header.has_framing() {
+            if !other.slots.is_empty() {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "目标页面的记录长度前缀设置与源页面不一致",
+                ));
+            }
+            other.header.flags =
+                (other.header.flags & !FLAG_FRAMED) | (self.header.flags & FLAG_FRAMED);
+        }
+        if self.header.has_bloom() && !other.header.has_bloom() {
+            other.enable_bloom();
+        }
+
+        let mut moved = Vec::with_capacity(moving.len());
+        for &(slot, _) in moving {
+            let new_slot = other.insert_record(self.get_record(slot)?)?;
+            moved.push((slot, new_slot));
+        }
+        for &(slot, _) in &moved {
+            self.slots[slot as usize] = (0, 0);
+        }
+        self.rebuild_free_slots();
+        self.compact()?;
+        self.shrink_slot_dir();
+        self.recompute_free_bytes();
+        self.rebuild_bloom();
+        Ok(moved)
+    }
+
+    /// 计算布隆过滤器使用的键哈希（FNV-1a），键为记录的完整字节
+    pub fn key_hash(key: &[u8]) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for &b in key {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        hash
+    }
+
+    /// 为页面启用布隆过滤器，并按现有记录构建
+    pub fn enable_bloom(&mut self) {
+        self.header.flags |= FLAG_BLOOM;
+        self.rebuild_bloom();
+    }
+
+    /// 为空页面启用宽槽目录项（4 字节偏移 + 4 字节长度），使记录偏移和长度可以超过 65 535；
+    /// 槽目录宽度影响已有布局，已有记录的页面不能切换格式
+    pub fn enable_wide_slots(&mut self) -> io::Result<()> {
+        if self.header.has_wide_slots() {
+            return Ok(());
+        }
+        if !self.slots.is_empty() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "只能在空页面上启用宽槽目录项",
+            ));
+        }
+        self.header.flags |= FLAG_WIDE_SLOTS;
+        Ok(())
+    }
+
+    /// 为空页面启用记录长度前缀：此后每条记录前写入 2 字节长度，
+    /// get_record 会用它校验槽目录中的长度。已有记录的页面不能切换格式
+    pub fn enable_framing(&mut self) -> io::Result<()> {
+        if self.header.has_framing() {
+            return Ok(());
+        }
+        if !self.slots.is_empty() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "只能在空页面上启用记录长度前缀",
+            ));
+        }
+        self.header.flags |= FLAG_FRAMED;
+        Ok(())
+    }
+
+    /// 按槽号顺序遍历页内的有效记录，产出 (槽 ID, 记录字节)；跳过已删除的空槽以及无法读取的槽
+    pub fn iter_records(&self) -> impl Iterator<Item = (u16, &[u8])> {
+        (0..self.slots.len() as u16)
+            .filter_map(move |slot| self.get_record(slot).ok().map(|data| (slot, data)))
+    }
+
+    /// 当前连续空闲空间（不紧缩）能否放下长度为 data_len 的记录：
+    /// 计入长度前缀，以及空闲槽链表为空时需要新增的槽目录项
+    pub fn can_fit(&self, data_len: usize) -> bool {
+        self.space_needed(data_len)
+            .is_some_and(|needed| needed <= self.header.free_bytes as usize)
+    }
+
+    /// 紧缩之后能否放下长度为 data_len 的记录（只预测，不修改页面）
+    pub fn can_fit_after_compact(&self, data_len: usize) -> bool {
+        self.space_needed(data_len)
+            .is_some_and(|needed| needed <= self.free_space_after_compact() as usize)
+    }
+
+    /// 尝试插入一条记录，放不下时不修改页面，并区分紧缩后能否放下，
+    /// 由调用方决定是紧缩本页还是换一页；能放下时与 insert_record 相同，返回槽 ID
+    pub fn try_insert(&mut self, data: &[u8]) -> Result<u16, InsertError> {
+        if !self.can_fit(data.len()) {
+            return Err(if self.can_fit_after_compact(data.len()) {
+                InsertError::NeedsCompaction
+            } else {
+                InsertError::Full
+            });
+        }
+        self.insert_record(data).map_err(|_| InsertError::Full)
+    }
+
+    /// 插入长度为 data_len 的记录需要的字节数（记录 + 长度前缀 + 新增的槽目录项）；
+    /// 需要新槽但槽目录已达 u16 上限时返回 None
+    fn space_needed(&self, data_len: usize) -> Option<usize> {
+        let prefix = if self.header.has_framing() {
+            FRAME_PREFIX_BYTES
+        } else {
+            0
+        };
+        // 空闲槽链表非空时复用已删除的槽，不需要新的槽目录项
+        let reuses_slot = self
+            .slots
+            .get(self.header.free_slot_head as usize)
+            .is_some_and(|&(_, len)| len == 0);
+        let dir_growth = if reuses_slot {
+            0
+        } else if self.slots.len() >= u16::MAX as usize {
+            return None;
+        } else {
+            self.header.slot_entry_size()
+        };
+        Some(data_len + prefix + dir_growth)
+    }
+
+    /// 有效槽的记录在页面中的物理位置 (相对页面起始的偏移, 长度)，与 get_record 返回的字节一致
+    /// （启用长度前缀时不含前缀）；空槽、无效槽号返回 None。紧缩会移动记录，之前取得的位置随之失效
+    pub fn slot_location(&self, slot_id: u16) -> Option<(u32, u32)> {
+        let record = self.get_record(slot_id).ok()?;
+        let (off, len) = self.slots[slot_id as usize];
+        let prefix = len - record.len() as u32;
+        Some((off + prefix, len - prefix))
+    }
+
+    /// 按 slot_location 给出的物理位置直接读取记录字节，不经过槽目录；
+    /// 位置超出数据区时 panic（与切片越界相同）
+    pub fn record_at(&self, offset: u32, len: u32) -> &[u8] {
+        let start = offset as usize - PageHeader::SIZE;
+        &self.data[start..start + len as usize]
+    }
+
+    /// 有效记录占用的字节数（所有非空槽的长度之和，含长度前缀）
+    pub fn live_bytes(&self) -> usize {
+        self.slots.iter().map(|&(_, len)| len as usize).sum()
+    }
+
+    /// 紧缩并收缩槽目录后可以回收的字节数：已删除记录留在数据区的空洞，
+    /// 加上槽目录末尾的空槽项。页头 free_bytes 一致时满足
+    /// live_bytes + dead_bytes + free_bytes + 页头 + 保留的槽目录 == page_size
+    pub fn dead_bytes(&self) -> usize {
+        // 最后一个有效槽之前的槽（含中间的空槽）在紧缩后保留，以保持槽号不变
+        let kept_slots = self
+            .slots
+            .iter()
+            .rposition(|&(_, len)| len != 0)
+            .map_or(0, |last| last + 1);
+        self.page_size.saturating_sub(
+            PageHeader::SIZE
+                + kept_slots * self.header.slot_entry_size()
+                + self.live_bytes()
+                + self.header.free_bytes as usize,
+        )
+    }
+
+    /// 按页内现存记录重建布隆过滤器（删除记录后调用，过滤器本身不支持删除）
+    pub fn rebuild_bloom(&mut self) {
+        if !self.header.has_bloom() {
+            return;
+        }
+        let hashes: Vec<u64> = self
+            .iter_records()
+            .map(|(_, data)| Page::key_hash(data))
+            .collect();
+        self.header.bloom = Default::default();
+        for hash in hashes {
+            self.header.bloom_insert(hash);
+        }
+    }
+
+    /// 页面是否可能包含该键；返回 false 时一定不包含
+    pub fn may_contain(&self, key_hash: u64) -> bool {
+        self.header.may_contain(key_hash)
+    }
+
+    /// 生成便于诊断的页面文本：页头字段、每个槽的偏移/长度/是否有效，以及记录的十六进制预览
+    pub fn debug_dump(&self) -> String {
+        // 每条记录最多预览的字节数
+        const PREVIEW_BYTES: usize = 16;
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "page: slot_count={} free_offset={} free_bytes={} flags={:#06x} data_len={}",
+            self.header.slot_count,
+            self.header.free_offset,
+            self.header.free_bytes,
+            self.header.flags,
+            self.data.len()
+        );
+        for (slot, &(off, len)) in self.slots.iter().enumerate() {
+            if len == 0 {
+                let _ = writeln!(out, "  slot {}: next_free={} len=0 deleted", slot, off);
+                continue;
+            }
+            let _ = write!(out, "  slot {}: offset={} len={} live ", slot, off, len);
+            match self.get_record(slot as u16) {
+                Ok(record) => {
+                    let preview: Vec<String> = record
+                        .iter()
+                        .take(PREVIEW_BYTES)
+                        .map(|b| format!("{:02x}", b))
+                        .collect();
+                    let more = if record.len() > PREVIEW_BYTES {
+                        " .."
+                    } else {
+                        ""
+                    };
+                    let _ = writeln!(out, "[{}{}]", preview.join(" "), more);
+                }
+                Err(err) => {
+                    let _ = writeln!(out, "<error: {}>", err);
+                }
+            }
+        }
+        out
+    }
 }
@@ -4,24 +4,27 @@ use std::io;
 
 // 页面紧缩，将有效记录移动到数据区前部，重写槽目录，释放连续空间
 pub trait PageCompact {
-    fn compact(&mut self, page_size: usize) -> io::Result<()>;
+    fn compact(&mut self) -> io::Result<()>;
+    // 预测紧缩后可获得的连续空闲字节数，不修改页面
+    fn free_space_after_compact(&self) -> u32;
 }
 
 impl PageCompact for Page {
-    fn compact(&mut self, page_size: usize) -> io::Result<()> {
+    fn compact(&mut self) -> io::Result<()> {
         // 新数据区和槽目录
         let mut new_data = Vec::new();
-        let mut new_slots = Vec::new();
-        // 遍历旧 slot
+        let mut new_slots = Vec::with_capacity(self.slots.len());
+        // 遍历旧 slot：空槽原样保留在原位置，保证外部保存的 Rid 在紧缩后仍然有效
         for &(off, len) in &self.slots {
             if len == 0 {
+                new_slots.push((off, len));
                 continue;
-            } // 跳过空槽
-              // 计算旧数据区相对于 data Vec 的偏移
+            }
+            // 计算旧数据区相对于 data Vec 的偏移
             let start = (off as usize).saturating_sub(PageHeader::SIZE);
             let end = start + len as usize;
             // 新槽偏移 = header 后 + new_data 长度
-            let new_off = PageHeader::SIZE as u16 + new_data.len() as u16;
+            let new_off = (PageHeader::SIZE + new_data.len()) as u32;
             new_data.extend_from_slice(&self.data[start..end]);
             new_slots.push((new_off, len));
         }
@@ -30,12 +33,22 @@ impl PageCompact for Page {
         self.slots = new_slots;
         // 更新页头
         let slot_count = self.slots.len() as u16;
-        let free_offset = PageHeader::SIZE as u16 + self.data.len() as u16;
-        let slot_dir_size = (self.slots.len() * 4) as u16;
-        let free_bytes = page_size as u16 - free_offset - slot_dir_size;
+        let free_offset = (PageHeader::SIZE + self.data.len()) as u32;
         self.header.slot_count = slot_count;
         self.header.free_offset = free_offset;
-        self.header.free_bytes = free_bytes;
+        self.recompute_free_bytes();
         Ok(())
     }
+
+    fn free_space_after_compact(&self) -> u32 {
+        // 紧缩只保留有效记录的数据，槽目录（含空槽）保持原大小
+        let live_bytes: usize = self
+            .slots
+            .iter()
+            .filter(|&&(_, len)| len != 0)
+            .map(|&(_, len)| len as usize)
+            .sum();
+        let used = PageHeader::SIZE + live_bytes + self.slots.len() * self.header.slot_entry_size();
+        self.page_size.saturating_sub(used) as u32
+    }
 }
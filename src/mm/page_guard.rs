@@ -1,32 +1,69 @@
+use crate::fm::BlockId;
 use crate::mm::BufferManager;
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
-type BlockId = u32;
-// PageGuard 在构造时 pin 一个页面，Drop 时自动 unpin
+// PageGuard 在构造时 pin 一个页面，Drop 时自动 unpin；
+// 通过 DerefMut 写过页面时 Drop 还会自动标记脏页，只读访问（Deref）不会
+// guard 只保存帧下标，每次解引用都经由 BufferManager 取得帧数据，
+// 因此 frames 这个外层 Vec 重新分配（如 BufferManager::resize 扩容）后 guard 仍然有效。
+// 仍需满足的不变量：
+// - guard 存活期间 BufferManager 本身不能移动或释放（由生命周期 'a 借用保证）
+// - 帧被 pin 期间不会被替换，下标始终指向同一个块；discard_frame/discard_all
+//   会无视 pin 直接丢弃帧，不能在该块仍有 guard 时调用（否则解引用时 panic）
+// - 同一块可以同时存在多个 guard，调用方需自行避免通过它们交错读写
 pub struct PageGuard<'a> {
     pub(crate) mgr: *mut BufferManager,
     pub block_id: BlockId,
-    pub data_ptr: *mut u8,
-    pub len: usize,
+    // 帧在 BufferManager::frames 中的下标
+    frame_idx: usize,
+    // 是否通过 DerefMut 获取过可写引用；为 true 时 Drop 自动把帧标记为脏页
+    written: bool,
     pub _marker: PhantomData<&'a mut [u8]>,
 }
 
 impl<'a> PageGuard<'a> {
     // 从 BufferManager 的 fetch 构造 PageGuard
-    pub(crate) fn new(
-        mgr: *mut BufferManager,
-        block_id: BlockId,
-        data_ptr: *mut u8,
-        len: usize,
-    ) -> Self {
+    pub(crate) fn new(mgr: *mut BufferManager, block_id: BlockId, frame_idx: usize) -> Self {
         PageGuard {
             mgr,
             block_id,
-            data_ptr,
-            len,
+            frame_idx,
+            written: false,
             _marker: PhantomData,
         }
     }
+
+    // 交出帧下标而不 unpin：pin 的所有权转交给调用方（如 SharedPageGuard），
+    // 之后由调用方负责 unpin；只能在写入之前交出
+    pub(crate) fn into_frame_idx(self) -> usize {
+        debug_assert!(!self.written);
+        let idx = self.frame_idx;
+        std::mem::forget(self);
+        idx
+    }
+
+    // 该 guard 对应的块号
+    pub fn block_id(&self) -> BlockId {
+        self.block_id
+    }
+
+    // 读取帧的脏标记
+    pub fn is_dirty(&self) -> bool {
+        unsafe {
+            self.mgr
+                .as_ref()
+                .is_some_and(|mgr| mgr.is_dirty(self.block_id))
+        }
+    }
+
+    // 通过 guard 把帧标记为脏页，避免另外调用 buf_mgr.mark_dirty(bid)
+    pub fn mark_dirty(&mut self) {
+        unsafe {
+            if let Some(mgr) = self.mgr.as_mut() {
+                mgr.mark_dirty(self.block_id);
+            }
+        }
+    }
 }
 
 unsafe impl<'a> Send for PageGuard<'a> {}
@@ -35,22 +72,34 @@ unsafe impl<'a> Sync for PageGuard<'a> {}
 impl<'a> Deref for PageGuard<'a> {
     type Target = [u8];
     fn deref(&self) -> &Self::Target {
-        unsafe { std::slice::from_raw_parts(self.data_ptr, self.len) }
+        unsafe { (*self.mgr).frame_data(self.frame_idx) }
     }
 }
 
 impl<'a> DerefMut for PageGuard<'a> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { std::slice::from_raw_parts_mut(self.data_ptr, self.len) }
+        // 第一次写入前为活动快照保留页面原始内容
+        if !self.written {
+            unsafe {
+                if let Some(mgr) = self.mgr.as_mut() {
+                    mgr.preserve_for_snapshots(self.block_id);
+                }
+            }
+        }
+        self.written = true;
+        unsafe { (*self.mgr).frame_data_mut(self.frame_idx) }
     }
 }
 
 impl<'a> Drop for PageGuard<'a> {
     fn drop(&mut self) {
-        // 自动 unpin
+        // 写过的页面自动标记为脏页，然后 unpin
         unsafe {
             if let Some(mgr) = self.mgr.as_mut() {
-                mgr.unpin(self.block_id as u32);
+                if self.written {
+                    mgr.mark_dirty(self.block_id);
+                }
+                mgr.unpin(self.block_id);
             }
         }
     }
@@ -1,19 +1,84 @@
 use std::io::{self, ErrorKind};
 
+/// 页头标志位：页面维护布隆过滤器
+pub const FLAG_BLOOM: u16 = 0x0001;
+
+/// 页头标志位：每条记录带 2 字节长度前缀，读取时与槽长度交叉校验
+pub const FLAG_FRAMED: u16 = 0x0002;
+
+/// 页头标志位：槽目录项使用 4 字节偏移 + 4 字节长度，用于超过 64 KB、2 字节偏移无法寻址的页面
+pub const FLAG_WIDE_SLOTS: u16 = 0x0004;
+
+/// 普通槽目录项的字节数（2 字节偏移 + 2 字节长度）
+pub const SLOT_ENTRY_BYTES: usize = 4;
+
+/// 宽槽目录项的字节数（4 字节偏移 + 4 字节长度，设置 FLAG_WIDE_SLOTS 时使用）
+pub const WIDE_SLOT_ENTRY_BYTES: usize = 8;
+
+/// 记录长度前缀的字节数（仅在设置 FLAG_FRAMED 时存在）
+pub const FRAME_PREFIX_BYTES: usize = 2;
+
+/// 当前页面格式版本；页面布局（页头、槽目录）变化时递增
+/// 版本 2：页头增加槽目录校验和
+/// 版本 3：页头增加空闲槽链表头
+/// 版本 4：free_offset、free_bytes 扩展为 4 字节，槽目录项宽度由 FLAG_WIDE_SLOTS 选择
+pub const PAGE_FORMAT_VERSION: u8 = 4;
+
+/// 空闲槽链表的结束标记（链表为空时的表头）
+pub const NO_FREE_SLOT: u16 = u16::MAX;
+
+/// 布隆过滤器位图的字节数（256 位）
+pub const BLOOM_BYTES: usize = 32;
+
+/// 每个键在布隆过滤器中设置的位数
+const BLOOM_HASHES: u64 = 3;
+
 /// 页面头元数据，存储槽目录计数、数据区偏移和剩余空闲字节数
 #[derive(Debug, Clone)]
 pub struct PageHeader {
     /// 当前有效槽数
     pub slot_count: u16,
     /// 下一个可写记录的起始偏移
-    pub free_offset: u16,
+    pub free_offset: u32,
     /// 页内剩余的空闲字节数
-    pub free_bytes: u16,
+    pub free_bytes: u32,
+    /// 页面标志位（FLAG_*）
+    pub flags: u16,
+    /// 页面格式版本（PAGE_FORMAT_VERSION）
+    pub version: u8,
+    /// 槽目录区域（页末 slot_count * 槽目录项字节数）的 16 位校验和，由 Page::flush 写入、Page::load 校验
+    pub slot_dir_checksum: u16,
+    /// 空闲槽链表头：已删除的槽在其 offset 字段中保存下一个空闲槽号，NO_FREE_SLOT 表示链表结束
+    pub free_slot_head: u16,
+    /// 页内记录的布隆过滤器位图，仅在设置 FLAG_BLOOM 时有效
+    pub bloom: [u8; BLOOM_BYTES],
 }
 
 impl PageHeader {
     /// 页头在帧中的字节长度
-    pub const SIZE: usize = 6;
+    /// 布局：slot_count 2 字节，free_offset、free_bytes 各 4 字节，flags 2 字节，version 1 字节，
+    /// 保留 1 字节，slot_dir_checksum、free_slot_head 各 2 字节，随后为布隆过滤器位图
+    pub const SIZE: usize = 18 + BLOOM_BYTES;
+
+    /// 为大小为 page_size 的空页面构造页头；超过 64 KB 的页面自动使用宽槽目录项
+    pub fn new(page_size: usize) -> PageHeader {
+        let flags = if page_size > u16::MAX as usize + 1 {
+            FLAG_WIDE_SLOTS
+        } else {
+            0
+        };
+        PageHeader {
+            slot_count: 0,
+            free_offset: PageHeader::SIZE as u32,
+            free_bytes: (page_size - PageHeader::SIZE) as u32,
+            flags,
+            version: PAGE_FORMAT_VERSION,
+            // 空槽目录的校验和
+            slot_dir_checksum: PageHeader::slot_dir_checksum_of(&[]),
+            free_slot_head: NO_FREE_SLOT,
+            bloom: [0u8; BLOOM_BYTES],
+        }
+    }
 
     /// 从字节缓冲区解析出 PageHeader，要求 buf.len() >= SIZE
     pub fn from_bytes(buf: &[u8]) -> io::Result<PageHeader> {
@@ -24,12 +89,23 @@ impl PageHeader {
             ));
         }
         let slot_count = u16::from_le_bytes([buf[0], buf[1]]);
-        let free_offset = u16::from_le_bytes([buf[2], buf[3]]);
-        let free_bytes = u16::from_le_bytes([buf[4], buf[5]]);
+        let free_offset = u32::from_le_bytes([buf[2], buf[3], buf[4], buf[5]]);
+        let free_bytes = u32::from_le_bytes([buf[6], buf[7], buf[8], buf[9]]);
+        let flags = u16::from_le_bytes([buf[10], buf[11]]);
+        let version = buf[12];
+        let slot_dir_checksum = u16::from_le_bytes([buf[14], buf[15]]);
+        let free_slot_head = u16::from_le_bytes([buf[16], buf[17]]);
+        let mut bloom = [0u8; BLOOM_BYTES];
+        bloom.copy_from_slice(&buf[18..18 + BLOOM_BYTES]);
         Ok(PageHeader {
             slot_count,
             free_offset,
             free_bytes,
+            flags,
+            version,
+            slot_dir_checksum,
+            free_slot_head,
+            bloom,
         })
     }
 
@@ -42,8 +118,67 @@ impl PageHeader {
             ));
         }
         buf[0..2].copy_from_slice(&self.slot_count.to_le_bytes());
-        buf[2..4].copy_from_slice(&self.free_offset.to_le_bytes());
-        buf[4..6].copy_from_slice(&self.free_bytes.to_le_bytes());
+        buf[2..6].copy_from_slice(&self.free_offset.to_le_bytes());
+        buf[6..10].copy_from_slice(&self.free_bytes.to_le_bytes());
+        buf[10..12].copy_from_slice(&self.flags.to_le_bytes());
+        buf[12] = self.version;
+        buf[13] = 0;
+        buf[14..16].copy_from_slice(&self.slot_dir_checksum.to_le_bytes());
+        buf[16..18].copy_from_slice(&self.free_slot_head.to_le_bytes());
+        buf[18..18 + BLOOM_BYTES].copy_from_slice(&self.bloom);
         Ok(())
     }
+
+    /// 计算槽目录字节的 16 位校验和（CRC32 高低 16 位异或折叠）
+    pub fn slot_dir_checksum_of(slot_dir: &[u8]) -> u16 {
+        let crc = crc32fast::hash(slot_dir);
+        ((crc >> 16) ^ (crc & 0xFFFF)) as u16
+    }
+
+    /// 页面是否维护布隆过滤器
+    pub fn has_bloom(&self) -> bool {
+        self.flags & FLAG_BLOOM != 0
+    }
+
+    /// 页面记录是否带长度前缀
+    pub fn has_framing(&self) -> bool {
+        self.flags & FLAG_FRAMED != 0
+    }
+
+    /// 页面是否使用宽槽目录项
+    pub fn has_wide_slots(&self) -> bool {
+        self.flags & FLAG_WIDE_SLOTS != 0
+    }
+
+    /// 每个槽目录项占用的字节数
+    pub fn slot_entry_size(&self) -> usize {
+        if self.has_wide_slots() {
+            WIDE_SLOT_ENTRY_BYTES
+        } else {
+            SLOT_ENTRY_BYTES
+        }
+    }
+
+    /// 将键哈希加入布隆过滤器
+    pub fn bloom_insert(&mut self, key_hash: u64) {
+        for bit in Self::bloom_bits(key_hash) {
+            self.bloom[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    /// 布隆过滤器判定：false 表示页面一定不包含该键；未启用过滤器时总是返回 true
+    pub fn may_contain(&self, key_hash: u64) -> bool {
+        if !self.has_bloom() {
+            return true;
+        }
+        Self::bloom_bits(key_hash).all(|bit| self.bloom[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    /// 双重哈希：由一个 64 位哈希派生出 BLOOM_HASHES 个位下标
+    fn bloom_bits(key_hash: u64) -> impl Iterator<Item = usize> {
+        let h1 = key_hash & 0xFFFF_FFFF;
+        let h2 = (key_hash >> 32) | 1;
+        let bits = (BLOOM_BYTES * 8) as u64;
+        (0..BLOOM_HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bits) as usize)
+    }
 }
@@ -1,40 +1,162 @@
 use crate::mm::page::Page;
+use crate::mm::page_header::{PageHeader, FRAME_PREFIX_BYTES};
 use std::io::{self, ErrorKind};
 
+/// 去掉带长度前缀记录的 2 字节前缀；校验前缀中的长度与槽目录一致，发现槽长度被破坏
+pub(crate) fn unframe(slot_id: u16, stored: &[u8]) -> io::Result<&[u8]> {
+    let prefix = stored
+        .get(..FRAME_PREFIX_BYTES)
+        .map(|prefix| u16::from_le_bytes([prefix[0], prefix[1]]));
+    match prefix {
+        Some(prefix) if prefix as usize + FRAME_PREFIX_BYTES == stored.len() => {
+            Ok(&stored[FRAME_PREFIX_BYTES..])
+        }
+        _ => Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "槽 {} 的长度 {} 与记录长度前缀不一致",
+                slot_id,
+                stored.len()
+            ),
+        )),
+    }
+}
+
+/// 槽目录已达 u16 上限时的错误，与空间不足同为 Other，调用方按页满处理
+fn slot_limit_error() -> io::Error {
+    io::Error::other(format!("页面槽数量已达上限 {}，无法插入记录", u16::MAX))
+}
+
 /// 在页面上操作记录的接口
 pub trait PageOps {
     /// 插入一条记录，返回槽 ID
     fn insert_record(&mut self, data: &[u8]) -> io::Result<u16>;
+    /// 把记录插入到指定槽 ID（恢复、重建索引时使用）；必要时在槽目录末尾补齐空槽，
+    /// 目标槽已有记录时返回 AlreadyExists
+    fn insert_record_at(&mut self, slot_id: u16, data: &[u8]) -> io::Result<()>;
     /// 根据槽 ID 获取记录数据切片
     fn get_record(&self, slot_id: u16) -> io::Result<&[u8]>;
     /// 删除指定槽 ID 的记录
     fn delete_record(&mut self, slot_id: u16) -> io::Result<()>;
+    /// 原位覆盖指定槽的记录；新数据不能比旧记录长，变短时多出的字节留在数据区直到紧缩
+    fn update_record(&mut self, slot_id: u16, data: &[u8]) -> io::Result<()>;
 }
 
 impl PageOps for Page {
     fn insert_record(&mut self, data: &[u8]) -> io::Result<u16> {
-        let data_len = data.len() as u16;
-        // 每个槽目录项占 4 字节
-        let slot_entry_size = 4u16;
+        // 优先复用空闲槽链表头部的已删除槽（O(1)），链表为空时才追加新的槽目录项
+        let head = self.header.free_slot_head;
+        let slot_id = if self
+            .slots
+            .get(head as usize)
+            .is_some_and(|&(_, len)| len == 0)
+        {
+            head
+        } else {
+            // slot_count 为 u16，槽已满时不能再追加（否则槽 ID 会回绕）
+            if self.slots.len() >= u16::MAX as usize {
+                return Err(slot_limit_error());
+            }
+            self.slots.len() as u16
+        };
+        self.insert_record_at(slot_id, data)?;
+        // 返回新插入的槽 ID
+        Ok(slot_id)
+    }
+
+    fn insert_record_at(&mut self, slot_id: u16, data: &[u8]) -> io::Result<()> {
+        let idx = slot_id as usize;
+        // 槽 u16::MAX 会使 slot_count 变为 65536，超出页头 u16 的表示范围
+        if idx >= u16::MAX as usize {
+            return Err(slot_limit_error());
+        }
+        if self.slots.get(idx).is_some_and(|&(_, len)| len != 0) {
+            return Err(io::Error::new(
+                ErrorKind::AlreadyExists,
+                format!("槽 {} 已有记录", slot_id),
+            ));
+        }
+        // 启用长度前缀时，槽长度包含 2 字节前缀；前缀只能表示 u16 范围内的长度
+        let framed = self.header.has_framing();
+        if framed && data.len() > u16::MAX as usize {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "记录长度 {} 超过长度前缀可表示的上限 {}",
+                    data.len(),
+                    u16::MAX
+                ),
+            ));
+        }
+        let data_len = if framed {
+            (data.len() + FRAME_PREFIX_BYTES) as u32
+        } else {
+            data.len() as u32
+        };
+        // 目标槽超出目录时需要新增的目录项（含中间补齐的空槽）
+        // 在 usize 下计算，避免槽号很大时目录增长量溢出
+        let slot_entry_size = self.header.slot_entry_size();
+        let new_entries = (idx + 1).saturating_sub(self.slots.len());
+        let dir_growth = new_entries * slot_entry_size;
         // 检查剩余空间
-        if self.header.free_bytes < data_len + slot_entry_size {
+        if (self.header.free_bytes as usize) < data_len as usize + dir_growth {
             return Err(io::Error::new(
                 ErrorKind::Other,
                 "页面空间不足，无法插入记录",
             ));
         }
+        // 与 free_bytes 无关的防御性检查：数据区（向后增长）的新末尾不能越过
+        // 槽目录（从页尾向前增长）的新起点，防止 free_bytes 不一致时写出重叠的页面
+        let slot_count = self.slots.len().max(idx + 1);
+        let data_end = self.header.free_offset as usize + data_len as usize;
+        if data_end + slot_count * slot_entry_size > self.page_size {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "插入后数据区末尾 {} 将与槽目录（{} 个槽）重叠，页头 free_bytes 可能已损坏",
+                    data_end, slot_count
+                ),
+            ));
+        }
         // 计算记录写入偏移，相对于页面起始
         let off = self.header.free_offset;
+        // 普通槽目录项只有 2 字节偏移和长度，超出时需要宽槽目录项
+        if !self.header.has_wide_slots() && (off > u16::MAX as u32 || data_len > u16::MAX as u32) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "记录偏移 {} 或长度 {} 超出普通槽目录项的范围，需要启用宽槽目录项",
+                    off, data_len
+                ),
+            ));
+        }
         // 写入 data 到内存 data 区
+        if framed {
+            self.data
+                .extend_from_slice(&(data.len() as u16).to_le_bytes());
+        }
         self.data.extend_from_slice(data);
-        // 增加槽目录
-        self.slots.push((off, data_len));
+        // 目标槽原本是空槽时先从空闲槽链表中摘下；超出目录时补齐槽目录，补出的空槽加入链表
+        if idx < self.slots.len() {
+            self.unlink_free_slot(slot_id);
+        } else {
+            let old_len = self.slots.len();
+            self.slots.resize(idx + 1, (0, 0));
+            for pad in old_len..idx {
+                self.slots[pad] = (self.header.free_slot_head as u32, 0);
+                self.header.free_slot_head = pad as u16;
+            }
+        }
+        self.slots[idx] = (off, data_len);
         // 更新页头元数据
-        self.header.slot_count += 1;
+        self.header.slot_count = self.slots.len() as u16;
         self.header.free_offset += data_len;
-        self.header.free_bytes = self.header.free_bytes - data_len - slot_entry_size;
-        // 返回新插入的槽 ID
-        Ok((self.slots.len() - 1) as u16)
+        // 空间检查已保证 dir_growth 不超过 free_bytes，转换为 u32 不会截断
+        self.header.free_bytes = self.header.free_bytes - data_len - dir_growth as u32;
+        if self.header.has_bloom() {
+            self.header.bloom_insert(Page::key_hash(data));
+        }
+        Ok(())
     }
 
     fn get_record(&self, slot_id: u16) -> io::Result<&[u8]> {
@@ -47,12 +169,15 @@ impl PageOps for Page {
             return Err(io::Error::new(ErrorKind::NotFound, "指定槽无记录或已删除"));
         }
         // data Vec 从页头之后开始，因此偏移应减去页头长度
-        let start = (off as usize).saturating_sub(crate::mm::page_header::PageHeader::SIZE);
+        let start = (off as usize).saturating_sub(PageHeader::SIZE);
         let end = start + len as usize;
         if end > self.data.len() {
             return Err(io::Error::new(ErrorKind::UnexpectedEof, "记录数据超出范围"));
         }
-        Ok(&self.data[start..end])
+        if !self.header.has_framing() {
+            return Ok(&self.data[start..end]);
+        }
+        unframe(slot_id, &self.data[start..end])
     }
 
     fn delete_record(&mut self, slot_id: u16) -> io::Result<()> {
@@ -60,16 +185,45 @@ impl PageOps for Page {
         if idx >= self.slots.len() {
             return Err(io::Error::new(ErrorKind::InvalidInput, "无效的槽 ID"));
         }
-        let (off, len) = self.slots[idx];
+        let (_, len) = self.slots[idx];
         if len == 0 {
             return Err(io::Error::new(ErrorKind::NotFound, "指定槽无记录或已删除"));
         }
-        // 释放空间：增加 free_bytes，简单不做紧缩
-        let slot_entry_size = 4u16;
-        self.header.free_bytes += len + slot_entry_size;
-        // 标记为空槽
-        self.slots[idx] = (0, 0);
-        self.header.slot_count -= 1;
+        // free_bytes 表示数据区末尾与槽目录之间的连续空间，记录字节留在数据区中间，
+        // 不计入 free_bytes，需紧缩后才能复用；槽目录项保留为空槽，保证其他槽号不变
+        // 标记为空槽并压入空闲槽链表头，之后的 insert_record 优先复用
+        self.slots[idx] = (self.header.free_slot_head as u32, 0);
+        self.header.free_slot_head = slot_id;
+        self.rebuild_bloom();
+        Ok(())
+    }
+
+    fn update_record(&mut self, slot_id: u16, data: &[u8]) -> io::Result<()> {
+        let old_len = self.get_record(slot_id)?.len();
+        if data.len() > old_len {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "新记录长度 {} 超过槽 {} 的原记录长度 {}，无法原位更新",
+                    data.len(),
+                    slot_id,
+                    old_len
+                ),
+            ));
+        }
+        let idx = slot_id as usize;
+        let (off, _) = self.slots[idx];
+        let mut start = (off as usize).saturating_sub(PageHeader::SIZE);
+        let mut slot_len = data.len();
+        if self.header.has_framing() {
+            self.data[start..start + FRAME_PREFIX_BYTES]
+                .copy_from_slice(&(data.len() as u16).to_le_bytes());
+            start += FRAME_PREFIX_BYTES;
+            slot_len += FRAME_PREFIX_BYTES;
+        }
+        self.data[start..start + data.len()].copy_from_slice(data);
+        self.slots[idx] = (off, slot_len as u32);
+        self.rebuild_bloom();
         Ok(())
     }
 }
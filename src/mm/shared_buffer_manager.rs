@@ -0,0 +1,103 @@
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::fm::BlockId;
+use crate::mm::BufferManager;
+
+// 线程安全的缓冲区管理器：用 Mutex 包住 BufferManager，句柄可 Clone 后交给其他线程。
+// 每个操作只在调用期间持有互斥量；fetch 返回的 SharedPageGuard 本身不持锁，
+// 读写页面以及 Drop 时的 unpin 都会重新加锁
+#[derive(Clone)]
+pub struct SharedBufferManager {
+    inner: Arc<Mutex<BufferManager>>,
+}
+
+impl SharedBufferManager {
+    pub fn new(buf_mgr: BufferManager) -> Self {
+        SharedBufferManager {
+            inner: Arc::new(Mutex::new(buf_mgr)),
+        }
+    }
+
+    // 加锁后直接操作内部的 BufferManager（allocate_data_page、checkpoint 等）
+    pub fn lock(&self) -> io::Result<MutexGuard<'_, BufferManager>> {
+        lock_pool(&self.inner)
+    }
+
+    // pin 一个块并返回不持锁的 guard；guard 存活期间该帧不会被替换
+    pub fn fetch(&self, block_id: BlockId) -> io::Result<SharedPageGuard> {
+        let frame_idx = self.lock()?.fetch(block_id)?.into_frame_idx();
+        Ok(SharedPageGuard {
+            pool: self.clone(),
+            block_id,
+            frame_idx,
+            written: false,
+        })
+    }
+
+    // 释放一次通过 lock() 手动加上的 pin（guard 的 pin 由其 Drop 释放）
+    pub fn unpin(&self, block_id: BlockId) -> io::Result<()> {
+        self.lock()?.unpin(block_id);
+        Ok(())
+    }
+
+    // 把已在缓冲区中的块标记为脏页
+    pub fn mark_dirty(&self, block_id: BlockId) -> io::Result<()> {
+        self.lock()?.mark_dirty(block_id);
+        Ok(())
+    }
+
+    pub fn flush_all(&self) -> io::Result<()> {
+        self.lock()?.flush_all()
+    }
+}
+
+// SharedBufferManager::fetch 返回的 guard：持有一次 pin，Drop 时重新加锁 unpin，
+// 通过 write 修改过页面时还会一并标记脏页。
+// 同一块的多个 guard 之间不做互斥，调用方需自行（如借助记录锁）避免并发写同一页
+pub struct SharedPageGuard {
+    pool: SharedBufferManager,
+    block_id: BlockId,
+    // 帧在 BufferManager::frames 中的下标，pin 期间保持不变
+    frame_idx: usize,
+    written: bool,
+}
+
+impl SharedPageGuard {
+    pub fn block_id(&self) -> BlockId {
+        self.block_id
+    }
+
+    // 加锁后以只读方式访问页面数据
+    pub fn read<R>(&self, f: impl FnOnce(&[u8]) -> R) -> io::Result<R> {
+        let mgr = self.pool.lock()?;
+        Ok(f(mgr.frame_data(self.frame_idx)))
+    }
+
+    // 加锁后修改页面数据；第一次写入前为活动快照保留原始内容
+    pub fn write<R>(&mut self, f: impl FnOnce(&mut [u8]) -> R) -> io::Result<R> {
+        let mut mgr = self.pool.lock()?;
+        if !self.written {
+            mgr.preserve_for_snapshots(self.block_id);
+            self.written = true;
+        }
+        Ok(f(mgr.frame_data_mut(self.frame_idx)))
+    }
+}
+
+impl Drop for SharedPageGuard {
+    fn drop(&mut self) {
+        if let Ok(mut mgr) = self.pool.lock() {
+            if self.written {
+                mgr.mark_dirty(self.block_id);
+            }
+            mgr.unpin(self.block_id);
+        }
+    }
+}
+
+fn lock_pool(inner: &Mutex<BufferManager>) -> io::Result<MutexGuard<'_, BufferManager>> {
+    inner
+        .lock()
+        .map_err(|_| io::Error::other("缓冲池互斥量已被毒化"))
+}
@@ -0,0 +1,46 @@
+use std::cmp::Ordering;
+use std::convert::TryInto;
+
+// 索引键类型：决定键字节（小端编码）如何解码和比较
+// 直接按字节比较小端编码会把负数排在正数之后、把浮点数排成乱序，索引必须按类型比较
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyType {
+    U32,
+    I32,
+    Str,
+    F64,
+}
+
+impl KeyType {
+    // 定长类型的编码字节数，变长类型返回 None
+    pub fn fixed_size(self) -> Option<usize> {
+        match self {
+            KeyType::U32 | KeyType::I32 => Some(4),
+            KeyType::F64 => Some(8),
+            KeyType::Str => None,
+        }
+    }
+}
+
+// 按键类型比较两个键
+// - 整数按数值比较（负数小于正数）
+// - F64 使用 total_cmp：-NaN < -inf < ... < -0.0 < 0.0 < ... < inf < NaN，保证全序
+// - Str 按 UTF-8 字节序比较（等价于 Unicode 码点序）
+// 长度与类型不符的键无法解码，退化为按原始字节比较，保证排序结果仍然确定
+pub fn compare(key_type: KeyType, a: &[u8], b: &[u8]) -> Ordering {
+    match key_type {
+        KeyType::U32 => match (a.try_into(), b.try_into()) {
+            (Ok(a), Ok(b)) => u32::from_le_bytes(a).cmp(&u32::from_le_bytes(b)),
+            _ => a.cmp(b),
+        },
+        KeyType::I32 => match (a.try_into(), b.try_into()) {
+            (Ok(a), Ok(b)) => i32::from_le_bytes(a).cmp(&i32::from_le_bytes(b)),
+            _ => a.cmp(b),
+        },
+        KeyType::F64 => match (a.try_into(), b.try_into()) {
+            (Ok(a), Ok(b)) => f64::from_le_bytes(a).total_cmp(&f64::from_le_bytes(b)),
+            _ => a.cmp(b),
+        },
+        KeyType::Str => a.cmp(b),
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::rm::types::Rid;
+
+// 锁冲突时 acquire 的重试间隔
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(1);
+
+// 单条记录的锁状态：多个读者共享，或一个写者独占
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockState {
+    Shared(usize),
+    Exclusive,
+}
+
+// 锁模式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+// 记录级锁表：按 Rid 授予共享/排他锁
+// 冲突时不排队等待：lock_* 立即返回 WouldBlock，由调用方在超时内重试（见 acquire），
+// 不存在等待环，因此不会死锁
+#[derive(Debug, Default)]
+pub struct LockManager {
+    locks: HashMap<Rid, LockState>,
+    // acquire 遇到冲突时的最长重试时间，0 表示不等待
+    timeout: Duration,
+}
+
+impl LockManager {
+    pub fn new() -> Self {
+        LockManager::default()
+    }
+
+    // 设置 acquire 的等待超时
+    pub fn with_timeout(timeout: Duration) -> Self {
+        LockManager {
+            locks: HashMap::new(),
+            timeout,
+        }
+    }
+
+    // 申请共享锁：记录被排他锁定时返回 WouldBlock
+    pub fn lock_shared(&mut self, rid: Rid) -> io::Result<()> {
+        match self.locks.get_mut(&rid) {
+            None => {
+                self.locks.insert(rid, LockState::Shared(1));
+                Ok(())
+            }
+            Some(LockState::Shared(holders)) => {
+                *holders += 1;
+                Ok(())
+            }
+            Some(LockState::Exclusive) => Err(conflict(rid, LockMode::Shared)),
+        }
+    }
+
+    // 申请排他锁：记录已被任何锁持有时返回 WouldBlock
+    pub fn lock_exclusive(&mut self, rid: Rid) -> io::Result<()> {
+        if self.locks.contains_key(&rid) {
+            return Err(conflict(rid, LockMode::Exclusive));
+        }
+        self.locks.insert(rid, LockState::Exclusive);
+        Ok(())
+    }
+
+    // 释放一把锁：排他锁直接释放，共享锁减少一个持有者；未加锁时返回 NotFound
+    pub fn unlock(&mut self, rid: Rid) -> io::Result<()> {
+        match self.locks.get_mut(&rid) {
+            None => Err(io::Error::new(
+                ErrorKind::NotFound,
+                format!("记录 {:?} 未加锁", rid),
+            )),
+            Some(LockState::Shared(holders)) if *holders > 1 => {
+                *holders -= 1;
+                Ok(())
+            }
+            Some(_) => {
+                self.locks.remove(&rid);
+                Ok(())
+            }
+        }
+    }
+
+    // 查询记录当前的锁状态
+    pub fn state(&self, rid: Rid) -> Option<LockState> {
+        self.locks.get(&rid).copied()
+    }
+
+    // 在共享的锁表上申请锁，冲突时在超时内重试；返回的 LockGuard 在 Drop 时释放锁
+    pub fn acquire(
+        manager: &Arc<Mutex<LockManager>>,
+        rid: Rid,
+        mode: LockMode,
+    ) -> io::Result<LockGuard> {
+        let start = Instant::now();
+        loop {
+            let mut locks = lock_table(manager)?;
+            let result = match mode {
+                LockMode::Shared => locks.lock_shared(rid),
+                LockMode::Exclusive => locks.lock_exclusive(rid),
+            };
+            match result {
+                Ok(()) => {
+                    return Ok(LockGuard {
+                        manager: Arc::clone(manager),
+                        rid,
+                    })
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    if start.elapsed() >= locks.timeout {
+                        return Err(err);
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+            // 释放锁表后再等待，让持有者有机会解锁
+            drop(locks);
+            thread::sleep(LOCK_RETRY_INTERVAL);
+        }
+    }
+}
+
+// 持有一把记录锁，Drop 时自动释放
+pub struct LockGuard {
+    manager: Arc<Mutex<LockManager>>,
+    rid: Rid,
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        if let Ok(mut locks) = lock_table(&self.manager) {
+            let _ = locks.unlock(self.rid);
+        }
+    }
+}
+
+fn lock_table(manager: &Mutex<LockManager>) -> io::Result<MutexGuard<'_, LockManager>> {
+    manager
+        .lock()
+        .map_err(|_| io::Error::other("锁表互斥量已被毒化"))
+}
+
+fn conflict(rid: Rid, mode: LockMode) -> io::Error {
+    io::Error::new(
+        ErrorKind::WouldBlock,
+        format!("记录 {:?} 的 {:?} 锁与已持有的锁冲突", rid, mode),
+    )
+}
@@ -1,6 +1,13 @@
 //! Record Manager 模块
 pub mod types;
+pub mod key;
+pub mod lock;
 pub mod rm_manager;
+pub mod schema;
+pub mod transaction;
 
 pub use types::Rid;
-pub use rm_manager::TableManager;
+pub use key::KeyType;
+pub use lock::{LockManager, LockMode};
+pub use rm_manager::{RecordTooLarge, TableManager, TableMode};
+pub use schema::{Column, ColumnType, Schema};
@@ -1,45 +1,339 @@
-use std::io;
-use crate::fm::FileHandle;
+use std::fmt;
+use std::io::{self, ErrorKind};
+use crate::fm::{BlockId, FileHandle};
 use crate::rm::types::Rid;
-use crate::mm::{BufferManager, page::Page, page_ops::PageOps};
+use crate::rm::transaction::{Transaction, UndoLog};
+use crate::rm::lock::{LockGuard, LockManager, LockMode};
+use std::sync::{Arc, Mutex};
+use crate::mm::{BufferManager, page::Page, page_header::PageHeader, page_ops::PageOps};
+use crate::mm::page_compact::PageCompact;
+
+// TableManager::plan_inserts 的预估结果
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlanReport {
+    // 会写入至少一条记录的已有部分使用页数量
+    pub pages_reused: usize,
+    // 需要新分配的数据页数量
+    pub new_pages: usize,
+    // 全部记录的字节数之和（不含槽目录项）
+    pub total_bytes: usize,
+}
+
+// 记录超过单条记录上限（TableManager::max_record_size）时的错误内容。
+// insert 与 plan_inserts 把它包装成 ErrorKind::InvalidInput 的 io::Error 返回，
+// 调用方用 RecordTooLarge::from_io_error 与其他参数错误区分
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecordTooLarge {
+    pub len: usize,
+    pub max: usize,
+}
+
+impl RecordTooLarge {
+    // 从 io::Error 中取出 RecordTooLarge；不是记录过大错误时返回 None
+    pub fn from_io_error(err: &io::Error) -> Option<&RecordTooLarge> {
+        err.get_ref()?.downcast_ref()
+    }
+}
+
+impl fmt::Display for RecordTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "记录长度 {} 超过单条记录上限 {}", self.len, self.max)
+    }
+}
+
+impl std::error::Error for RecordTooLarge {}
+
+impl From<RecordTooLarge> for io::Error {
+    fn from(err: RecordTooLarge) -> Self {
+        io::Error::new(ErrorKind::InvalidInput, err)
+    }
+}
+
+// 表的写入模式
+// - Normal: 插入优先复用空闲空间映射中的部分使用页和页内已删除的槽，支持删除与紧缩
+// - AppendOnly: 只追加（事件日志等），插入只写当前尾页，写满后分配新页，
+//   不查找可复用空间、不维护空闲空间映射；删除与紧缩返回 Unsupported
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TableMode {
+    #[default]
+    Normal,
+    AppendOnly,
+}
 
 // 表级管理器：提供插入/读取/删除/扫描函数
 pub struct TableManager {
     buf_mgr: BufferManager,
+    // 新数据页是否维护布隆过滤器（加速按内容查找时的否定判断）
+    bloom_filter: bool,
+    // 活动事务的撤销日志；不在事务中时为 None
+    undo: Option<UndoLog>,
+    // 记录级锁表；设置后读取加共享锁，修改加排他锁（操作结束即释放）
+    locks: Option<Arc<Mutex<LockManager>>>,
+    // flush 时自动紧缩的阈值：脏页可回收的死空间超过页面大小的该比例时先紧缩再写回；None 表示不自动紧缩
+    compact_on_flush: Option<f64>,
+    mode: TableMode,
+    // 追加模式下当前写入的尾页
+    append_tail: Option<BlockId>,
 }
 
 impl TableManager {
     // 使用给定的 FileHandle 和缓冲区容量创建表管理器
     pub fn new(handle: FileHandle, capacity: usize) -> Self {
         let buf_mgr = BufferManager::new(handle, capacity);
-        TableManager { buf_mgr }
+        TableManager {
+            buf_mgr,
+            bloom_filter: false,
+            undo: None,
+            locks: None,
+            compact_on_flush: None,
+            mode: TableMode::Normal,
+            append_tail: None,
+        }
+    }
+
+    // 设置之后写入记录的新数据页是否维护布隆过滤器
+    pub fn set_bloom_filter(&mut self, enabled: bool) {
+        self.bloom_filter = enabled;
+    }
+
+    // 设置 flush 时自动紧缩的阈值（0.0 ~ 1.0 的比例），None 关闭；紧缩保持槽号不变，已有 Rid 仍然有效
+    pub fn set_compact_on_flush(&mut self, threshold: Option<f64>) {
+        self.compact_on_flush = threshold;
+    }
+
+    // 设置表的写入模式；切换到追加模式后从新分配的页开始追加
+    pub fn set_mode(&mut self, mode: TableMode) {
+        self.mode = mode;
+        self.append_tail = None;
+    }
+
+    pub fn mode(&self) -> TableMode {
+        self.mode
+    }
+
+    // 挂接（或用 None 取消）记录级锁表，多个 TableManager 可共享同一个锁表
+    pub fn set_lock_manager(&mut self, locks: Option<Arc<Mutex<LockManager>>>) {
+        self.locks = locks;
+    }
+
+    // 开始一个事务，事务结束（提交、回滚或 Drop）前独占该表
+    pub fn begin(&mut self) -> Transaction<'_> {
+        Transaction::new(self)
+    }
+
+    // 单条记录的最大字节数：空白数据页扣除页头和一个槽目录项后剩余的空间。
+    // TableManager 写入的数据页不带长度前缀；窄槽目录项的长度字段只有 2 字节，另受 u16 上限限制
+    pub fn max_record_size(&self) -> usize {
+        let empty = PageHeader::new(self.buf_mgr.handle.block_size());
+        let page_room = (empty.free_bytes as usize).saturating_sub(empty.slot_entry_size());
+        if empty.has_wide_slots() {
+            page_room
+        } else {
+            page_room.min(u16::MAX as usize)
+        }
+    }
+
+    // 数据页每个槽目录项的字节数：超过 64 KB 的块使用宽槽目录项（见 PageHeader::new）
+    fn slot_entry_size(&self) -> usize {
+        PageHeader::new(self.buf_mgr.handle.block_size()).slot_entry_size()
     }
 
     // 插入一条记录，返回记录标识符 (block, slot)
     pub fn insert(&mut self, data: &[u8]) -> io::Result<Rid> {
-        // 分配新数据页（若已有空闲页，可扩展为先查找空闲页）
+        // 超出单页容量的记录任何页面都放不下，在访问页面之前直接拒绝
+        let max = self.max_record_size();
+        if data.len() > max {
+            return Err(RecordTooLarge {
+                len: data.len(),
+                max,
+            }
+            .into());
+        }
+        // 普通模式优先写入空闲空间映射中记录的部分使用页，追加模式只尝试当前尾页
+        let placed = match self.mode {
+            TableMode::Normal => self.insert_into_partial_page(data)?,
+            TableMode::AppendOnly => self.insert_into_tail_page(data)?,
+        };
+        if let Some(rid) = placed {
+            return Ok(rid);
+        }
+        // 没有合适的页时分配新数据页
         let block = self.buf_mgr.allocate_data_page()?;
+        if let Some(undo) = &mut self.undo {
+            undo.allocated.push(block);
+        }
         // 读取并 pin
         let mut frame = self.buf_mgr.fetch(block)?;
         // 加载页面结构
-        let mut page = Page::load(&mut *frame)?;
+        let mut page = Page::load(&frame)?;
+        if self.bloom_filter && !page.header.has_bloom() {
+            page.enable_bloom();
+        }
         // 插入记录到槽目录，获得 slot id
         let slot = page.insert_record(data)?;
         // 写回页面
         page.flush(&mut *frame)?;
-        // 解除 pin
+        // guard 被写过，Drop 时自动标记脏页并 unpin
         drop(frame);
-        self.buf_mgr.mark_dirty(block);
-        self.buf_mgr.unpin(block);
+        if self.mode == TableMode::AppendOnly {
+            self.append_tail = Some(block);
+            return Ok((block, slot));
+        }
+        // 新页剩余的空间登记到空闲空间映射，供之后的插入复用
+        self.buf_mgr
+            .handle
+            .record_free_space(block, page.header.free_bytes)?;
         Ok((block, slot))
     }
+
+    // 追加模式：把记录写入当前尾页，尾页放不下（或尚无尾页）时返回 None
+    fn insert_into_tail_page(&mut self, data: &[u8]) -> io::Result<Option<Rid>> {
+        let Some(block) = self.append_tail else {
+            return Ok(None);
+        };
+        let mut frame = self.buf_mgr.fetch(block)?;
+        let mut page = match Page::load(&frame) {
+            Ok(page) => page,
+            // 尾页已不是数据页（例如分配它的事务已回滚），改从新页开始追加
+            Err(err) if err.kind() == ErrorKind::InvalidData => {
+                self.append_tail = None;
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        };
+        if !page.can_fit(data.len()) {
+            return Ok(None);
+        }
+        if let Some(undo) = self.undo.as_mut().filter(|undo| undo.needs_image(block)) {
+            undo.before_images.insert(block, frame.to_vec());
+        }
+        let slot = page.insert_record(data)?;
+        // guard 被写过，Drop 时自动标记脏页并 unpin
+        page.flush(&mut frame)?;
+        Ok(Some((block, slot)))
+    }
+
+    // 预演按顺序插入给定大小的记录：沿用 insert 的放置规则（先按空闲空间映射找部分使用页，
+    // 放不下时分配新页），只在映射的副本上模拟，不读写任何页面。
+    // 映射只是提示，页面实际空间与映射不一致时结果可能与真实插入有出入
+    pub fn plan_inserts(&mut self, sizes: &[usize]) -> io::Result<PlanReport> {
+        let max = self.max_record_size();
+        let entry_size = self.slot_entry_size();
+        let handle = &self.buf_mgr.handle;
+        let policy = handle.free_list_policy();
+        let empty_page_bytes = (handle.block_size() - PageHeader::SIZE) as u32;
+        let mut entries = handle.free_space_entries().to_vec();
+        let existing = entries.len();
+        let mut reused = vec![false; existing];
+        let mut report = PlanReport::default();
+        for &size in sizes {
+            if size > max {
+                return Err(RecordTooLarge { len: size, max }.into());
+            }
+            report.total_bytes += size;
+            // 与 insert_into_partial_page 一致：每条记录消耗记录本身 + 一个槽目录项
+            let used = (size + entry_size) as u32;
+            let target = policy
+                .pick(&entries, used)
+                .and_then(|block| entries.iter().position(|&(b, _)| b == block));
+            match target {
+                Some(index) => {
+                    if index < existing {
+                        reused[index] = true;
+                    }
+                    entries[index].1 -= used;
+                }
+                None => {
+                    report.new_pages += 1;
+                    // 新页用虚拟块号登记，不会与已有块冲突
+                    let virtual_block = BlockId::from(u32::MAX - report.new_pages as u32);
+                    entries.push((virtual_block, empty_page_bytes - used));
+                }
+            }
+        }
+        report.pages_reused = reused.iter().filter(|&&r| r).count();
+        Ok(report)
+    }
+
+    // 尝试把记录写入空闲空间映射中的部分使用页，没有合适的页时返回 None
+    // 映射只是提示：页的实际空间不足或已不是数据页时，按实际情况更新映射后继续查找
+    fn insert_into_partial_page(&mut self, data: &[u8]) -> io::Result<Option<Rid>> {
+        // 记录本身 + 槽目录项（TableManager 写入的数据页不带长度前缀）
+        let needed = (data.len() + self.slot_entry_size()) as u32;
+        while let Some(block) = self.buf_mgr.handle.find_block_with_space(needed) {
+            let mut frame = self.buf_mgr.fetch(block)?;
+            let mut page = match Page::load(&frame) {
+                Ok(page) if page.header.free_bytes >= needed => page,
+                // 映射记录已过时：按页的实际可用空间更新，不是数据页时移除
+                loaded => {
+                    drop(frame);
+                    let free_bytes = match loaded {
+                        Ok(page) => page.header.free_bytes,
+                        Err(err) if err.kind() == ErrorKind::InvalidData => 0,
+                        Err(err) => return Err(err),
+                    };
+                    self.buf_mgr.handle.record_free_space(block, free_bytes)?;
+                    continue;
+                }
+            };
+            // 事务中首次修改该页时保存前像
+            if let Some(undo) = self.undo.as_mut().filter(|undo| undo.needs_image(block)) {
+                undo.before_images.insert(block, frame.to_vec());
+            }
+            let slot = page.insert_record(data)?;
+            page.flush(&mut frame)?;
+            // guard 被写过，Drop 时自动标记脏页并 unpin
+            drop(frame);
+            self.buf_mgr
+                .handle
+                .record_free_space(block, page.header.free_bytes)?;
+            return Ok(Some((block, slot)));
+        }
+        Ok(None)
+    }
     
+    // 分配下一个自增 id 并插入记录：记录内容为 8 字节小端 id 加上 data，返回 (id, Rid)
+    pub fn insert_with_autoid(&mut self, data: &[u8]) -> io::Result<(u64, Rid)> {
+        let id = self.buf_mgr.handle.take_auto_id()?;
+        let mut record = Vec::with_capacity(8 + data.len());
+        record.extend_from_slice(&id.to_le_bytes());
+        record.extend_from_slice(data);
+        let rid = self.insert(&record)?;
+        Ok((id, rid))
+    }
+
+    // 定长记录的快速更新路径：新数据长度必须与原记录相同，直接原位覆盖，不涉及转发指针
+    pub fn update_in_place(&mut self, rid: Rid, new_data: &[u8]) -> io::Result<()> {
+        let _lock = self.lock_record(rid, LockMode::Exclusive)?;
+        let (block, slot) = rid;
+        self.save_before_image(block)?;
+        let mut frame = self.buf_mgr.fetch(block)?;
+        let mut page = Page::load(&frame)?;
+        let old_len = page.get_record(slot)?.len();
+        if new_data.len() != old_len {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "原位更新要求长度不变：记录 {:?} 长度为 {}，新数据长度为 {}",
+                    rid,
+                    old_len,
+                    new_data.len()
+                ),
+            ));
+        }
+        page.update_record(slot, new_data)?;
+        page.flush(&mut frame)?;
+        // guard 被写过，Drop 时自动标记脏页并 unpin
+        drop(frame);
+        Ok(())
+    }
+
     // 更新指定记录内容：如果新数据长度小于等于旧数据长度，则原位更新；否则，插入新记录并在原位置写入转发指针
     // pub fn update(&mut self, rid: Rid, new_data: &[u8]) -> io::Result<Rid> {
     //     let (block, slot) = rid;
     //     let mut frame = self.buf_mgr.fetch(block)?;
     //     // 加载页面结构
-    //     let mut page = Page::load(&mut *frame)?;
+    //     let mut page = Page::load(&frame)?;
     //     // 获取旧记录数据
     //     let old_data = page.get_record(slot)?;
     //     if new_data.len() <= old_data.len() {
@@ -79,42 +373,403 @@ impl TableManager {
 
     // 根据 Rid 读取记录内容
     pub fn get(&mut self, rid: Rid) -> io::Result<Vec<u8>> {
+        self.with_record(rid, |data| data.to_vec())
+    }
+
+    // 在页面 pin 住期间直接对借用的记录字节调用 f 并返回其结果，不拷贝记录
+    pub fn with_record<R, F: FnOnce(&[u8]) -> R>(&mut self, rid: Rid, f: F) -> io::Result<R> {
+        let _lock = self.lock_record(rid, LockMode::Shared)?;
         let (block, slot) = rid;
-        let mut frame = self.buf_mgr.fetch(block)?;
-        let page = Page::load(&mut *frame)?;
-        let data = page.get_record(slot)?.to_vec();
-        drop(frame);
-        self.buf_mgr.unpin(block);
-        Ok(data)
+        let frame = self.buf_mgr.fetch(block)?;
+        Page::record_in_frame(&frame, slot).map(f)
+    }
+
+    // 根据 Rid 读取记录内容；块号或槽号越界、块不是数据页（如已释放）、槽已删除时返回 Ok(None)，
+    // 只有 IO 错误或页面损坏才返回 Err
+    pub fn get_optional(&mut self, rid: Rid) -> io::Result<Option<Vec<u8>>> {
+        let (block, slot) = rid;
+        if block < self.buf_mgr.handle.first_data_block()
+            || block.get() >= self.buf_mgr.handle.header().block_count
+        {
+            return Ok(None);
+        }
+        let live = match self.load_page_slots(block)? {
+            Some(slots) => slots.get(slot as usize).is_some_and(|&(_, len)| len != 0),
+            None => false,
+        };
+        if !live {
+            return Ok(None);
+        }
+        self.get(rid).map(Some)
+    }
+
+    // 批量读取记录：按块分组，每个块只 fetch 一次并读出其中所有请求的槽
+    // 返回顺序与 rids 一致；任一记录不存在时返回错误
+    pub fn get_many(&mut self, rids: &[Rid]) -> io::Result<Vec<Vec<u8>>> {
+        let _locks = rids
+            .iter()
+            .map(|&rid| self.lock_record(rid, LockMode::Shared))
+            .collect::<io::Result<Vec<_>>>()?;
+        // 按 Rid 排序的输入下标，同一块的请求相邻
+        let mut order: Vec<usize> = (0..rids.len()).collect();
+        order.sort_unstable_by_key(|&i| rids[i]);
+        let mut result = vec![Vec::new(); rids.len()];
+        for group in order.chunk_by(|&a, &b| rids[a].0 == rids[b].0) {
+            let block = rids[group[0]].0;
+            let frame = self.buf_mgr.fetch(block)?;
+            let page = Page::load(&frame)?;
+            for &i in group {
+                result[i] = page.get_record(rids[i].1)?.to_vec();
+            }
+        }
+        Ok(result)
+    }
+
+    // 判断 Rid 是否指向一条有效记录
+    pub fn exists(&mut self, rid: Rid) -> io::Result<bool> {
+        Ok(self.get_optional(rid)?.is_some())
     }
 
     // 删除指定 Rid 的记录
     pub fn delete(&mut self, rid: Rid) -> io::Result<()> {
+        self.reject_in_append_mode("删除")?;
+        let _lock = self.lock_record(rid, LockMode::Exclusive)?;
         let (block, slot) = rid;
+        self.save_before_image(block)?;
         let mut frame = self.buf_mgr.fetch(block)?;
-        let mut page = Page::load(&mut *frame)?;
+        let mut page = Page::load(&frame)?;
         page.delete_record(slot)?;
-        page.flush(&mut *frame)?;
+        page.flush(&mut frame)?;
+        // guard 被写过，Drop 时自动标记脏页并 unpin
         drop(frame);
-        self.buf_mgr.mark_dirty(block);
-        self.buf_mgr.unpin(block);
         Ok(())
     }
 
+    // 紧缩指定数据页（槽号不变，已有 Rid 仍然有效），返回紧缩后的 free_bytes；
+    // 页面没有可回收的死空间时不做修改，直接返回当前的 free_bytes
+    pub fn compact_page(&mut self, block_id: BlockId) -> io::Result<u32> {
+        self.reject_in_append_mode("紧缩")?;
+        let frame = self.buf_mgr.fetch(block_id)?;
+        let mut page = Page::load(&frame)?;
+        drop(frame);
+        if page.free_space_after_compact() <= page.header.free_bytes {
+            return Ok(page.header.free_bytes);
+        }
+        self.save_before_image(block_id)?;
+        page.compact()?;
+        // guard 被写过，Drop 时自动标记脏页并 unpin
+        let mut frame = self.buf_mgr.fetch(block_id)?;
+        page.flush(&mut frame)?;
+        drop(frame);
+        self.buf_mgr
+            .handle
+            .record_free_space(block_id, page.header.free_bytes)?;
+        Ok(page.header.free_bytes)
+    }
+
     // 简单扫描给定块列表，返回所有有效 Rid
-    pub fn scan(&mut self, blocks: &[u32]) -> io::Result<Vec<Rid>> {
+    pub fn scan(&mut self, blocks: &[BlockId]) -> io::Result<Vec<Rid>> {
         let mut result = Vec::new();
         for &block in blocks {
-            let mut frame = self.buf_mgr.fetch(block)?;
-            let page = Page::load(&mut *frame)?;
-            for slot in 0..page.header.slot_count {
-                if page.get_record(slot).is_ok() {
-                    result.push((block, slot));
+            let frame = self.buf_mgr.fetch(block)?;
+            let page = Page::load(&frame)?;
+            result.extend(page.iter_records().map(|(slot, _)| (block, slot)));
+        }
+        Ok(result)
+    }
+
+    // 按完整记录内容查找记录；布隆过滤器判定“一定不存在”的页面直接跳过，不解析记录
+    pub fn find_record(&mut self, record: &[u8]) -> io::Result<Option<Rid>> {
+        let key_hash = Page::key_hash(record);
+        for block in self.buf_mgr.handle.data_blocks() {
+            let frame = self.buf_mgr.fetch(block)?;
+            let header = PageHeader::from_bytes(&frame)?;
+            let mut found = None;
+            if header.may_contain(key_hash) {
+                match Page::load(&frame) {
+                    Ok(page) => {
+                        found = page
+                            .iter_records()
+                            .find(|&(_, data)| data == record)
+                            .map(|(slot, _)| slot);
+                    }
+                    // 未格式化为数据页的块
+                    Err(err) if err.kind() == ErrorKind::InvalidData => {}
+                    Err(err) => return Err(err),
                 }
             }
             drop(frame);
-            self.buf_mgr.unpin(block);
+            if let Some(slot) = found {
+                return Ok(Some((block, slot)));
+            }
+        }
+        Ok(None)
+    }
+
+    // 扫描全表，返回所有有效记录的 Rid
+    pub fn scan_all(&mut self) -> io::Result<Vec<Rid>> {
+        self.scan_filter(|_| true)
+    }
+
+    // 扫描全表，对每条有效记录在页面 pin 住时调用谓词，只收集满足条件的 Rid
+    // 谓词直接作用于页内记录字节，不为每条记录单独分配内存
+    pub fn scan_filter<F: Fn(&[u8]) -> bool>(&mut self, pred: F) -> io::Result<Vec<Rid>> {
+        let mut result = Vec::new();
+        for block in self.buf_mgr.handle.data_blocks() {
+            let frame = self.buf_mgr.fetch(block)?;
+            match Page::load(&frame) {
+                Ok(page) => {
+                    result.extend(
+                        page.iter_records()
+                            .filter(|&(_, data)| pred(data))
+                            .map(|(slot, _)| (block, slot)),
+                    );
+                }
+                // 未格式化为数据页的块
+                Err(err) if err.kind() == ErrorKind::InvalidData => {}
+                Err(err) => return Err(err),
+            }
         }
         Ok(result)
     }
+
+    // 删除所有满足 pred 的记录，返回删除条数
+    // 单遍扫描：每页只 pin 一次，在页内删除全部匹配记录后统一 flush 一次
+    pub fn delete_where<F: Fn(&[u8]) -> bool>(&mut self, pred: F) -> io::Result<usize> {
+        self.reject_in_append_mode("删除")?;
+        let mut deleted = 0;
+        for block in self.buf_mgr.handle.data_blocks() {
+            let mut frame = self.buf_mgr.fetch(block)?;
+            let mut page = match Page::load(&frame) {
+                Ok(page) => page,
+                // 未格式化为数据页的块
+                Err(err) if err.kind() == ErrorKind::InvalidData => {
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            let matches: Vec<u16> = page
+                .iter_records()
+                .filter(|&(_, data)| pred(data))
+                .map(|(slot, _)| slot)
+                .collect();
+            if matches.is_empty() {
+                continue;
+            }
+            // 事务中首次修改该页时保存前像（此时页内容尚未改动）
+            if let Some(undo) = self.undo.as_mut().filter(|undo| undo.needs_image(block)) {
+                undo.before_images.insert(block, frame.to_vec());
+            }
+            let _locks = match &self.locks {
+                Some(locks) => matches
+                    .iter()
+                    .map(|&slot| LockManager::acquire(locks, (block, slot), LockMode::Exclusive))
+                    .collect::<io::Result<Vec<_>>>()?,
+                None => Vec::new(),
+            };
+            for &slot in &matches {
+                page.delete_record(slot)?;
+            }
+            page.flush(&mut frame)?;
+            // guard 被写过，Drop 时自动标记脏页并 unpin
+            drop(frame);
+            deleted += matches.len();
+        }
+        Ok(deleted)
+    }
+
+    // 清空表：丢弃缓冲区中该表的所有帧（脏页不再写回），并把文件截断到只剩文件头块
+    pub fn truncate(&mut self) -> io::Result<()> {
+        self.append_tail = None;
+        self.buf_mgr.discard_all()?;
+        self.buf_mgr.handle.truncate()
+    }
+
+    // 统计表中有效记录数（只读取页头与槽目录）
+    pub fn count(&mut self) -> io::Result<u64> {
+        let (count, _) = self.live_stats()?;
+        Ok(count)
+    }
+
+    // 有效记录的平均长度（字节），空表返回 0
+    pub fn average_record_size(&mut self) -> io::Result<f64> {
+        let (count, bytes) = self.live_stats()?;
+        if count == 0 {
+            return Ok(0.0);
+        }
+        Ok(bytes as f64 / count as f64)
+    }
+
+    // 按页面填充程度统计数据页数量：第 i 个桶对应已用空间占页面 [i/8, (i+1)/8) 的页（满页计入最后一桶）
+    // 只读取页头，已用空间按 页面大小 - free_bytes 计算，删除留下的死空间也算作已用，
+    // 因此已用比例高但记录数少的页面说明紧缩能回收空间
+    pub fn free_space_histogram(&mut self) -> io::Result<[u32; 8]> {
+        let mut histogram = [0u32; 8];
+        for block in self.buf_mgr.handle.data_blocks() {
+            if self.buf_mgr.handle.is_metadata_block(block) {
+                continue;
+            }
+            let frame = self.buf_mgr.fetch(block)?;
+            let page_size = frame.len();
+            let parsed = Page::load_slots(&frame);
+            drop(frame);
+            // 只统计能按数据页格式完整解析的块，跳过空闲块和未格式化的块
+            let header = match parsed {
+                Ok((header, _)) => header,
+                Err(err) if err.kind() == ErrorKind::InvalidData => continue,
+                Err(err) => return Err(err),
+            };
+            let used = page_size.saturating_sub(header.free_bytes as usize);
+            let bucket = (used * histogram.len() / page_size).min(histogram.len() - 1);
+            histogram[bucket] += 1;
+        }
+        Ok(histogram)
+    }
+
+    // 遍历所有数据页，累计有效槽数量和有效记录字节数
+    fn live_stats(&mut self) -> io::Result<(u64, u64)> {
+        let mut count = 0u64;
+        let mut bytes = 0u64;
+        for block in self.buf_mgr.handle.data_blocks() {
+            if self.buf_mgr.handle.is_metadata_block(block) {
+                continue;
+            }
+            if let Some(slots) = self.load_page_slots(block)? {
+                for &(_, len) in slots.iter().filter(|&&(_, len)| len != 0) {
+                    count += 1;
+                    bytes += len as u64;
+                }
+            }
+        }
+        Ok((count, bytes))
+    }
+
+    // 读取块的槽目录；未格式化为数据页的块（空闲块、仅分配未初始化的块）返回 None
+    fn load_page_slots(&mut self, block: BlockId) -> io::Result<Option<Vec<(u32, u32)>>> {
+        let frame = self.buf_mgr.fetch(block)?;
+        let parsed = match Page::load_slots(&frame) {
+            Ok((_, slots)) => Some(slots),
+            Err(err) if err.kind() == ErrorKind::InvalidData => None,
+            Err(err) => return Err(err),
+        };
+        Ok(parsed)
+    }
+
+    // 开启撤销日志，之后的修改都会记录修改前镜像
+    pub(crate) fn begin_undo(&mut self) {
+        self.undo = Some(UndoLog::default());
+    }
+
+    // 取出并关闭撤销日志
+    pub(crate) fn take_undo(&mut self) -> UndoLog {
+        self.undo.take().unwrap_or_default()
+    }
+
+    // 挂接了锁表时为记录加锁，返回的 guard 离开作用域时释放；未挂接时不加锁
+    fn lock_record(&self, rid: Rid, mode: LockMode) -> io::Result<Option<LockGuard>> {
+        self.locks
+            .as_ref()
+            .map(|locks| LockManager::acquire(locks, rid, mode))
+            .transpose()
+    }
+
+    // 追加模式的表不支持删除与紧缩，直接报错，避免调用方误以为操作生效
+    fn reject_in_append_mode(&self, op: &str) -> io::Result<()> {
+        if self.mode == TableMode::AppendOnly {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                format!("追加模式的表不支持{}", op),
+            ));
+        }
+        Ok(())
+    }
+
+    // 事务中第一次修改某页前保存其当前内容
+    fn save_before_image(&mut self, block: BlockId) -> io::Result<()> {
+        if !self.undo.as_ref().is_some_and(|undo| undo.needs_image(block)) {
+            return Ok(());
+        }
+        let frame = self.buf_mgr.fetch(block)?;
+        let image = frame.to_vec();
+        drop(frame);
+        if let Some(undo) = &mut self.undo {
+            undo.before_images.insert(block, image);
+        }
+        Ok(())
+    }
+
+    // 把缓冲区中的所有脏页写回磁盘并同步文件；设置了自动紧缩阈值时先紧缩超过阈值的脏页
+    pub fn flush(&mut self) -> io::Result<()> {
+        let dirty = self.buf_mgr.dirty_page_ids();
+        self.compact_dirty_pages(&dirty)?;
+        self.buf_mgr.flush_all()
+    }
+
+    // 数据页在缓冲区中的 pin 计数（不在缓冲区中时为 None）；
+    // 所有操作返回后（包括出错返回）都应为 0
+    pub(crate) fn pin_count(&self, block: BlockId) -> Option<usize> {
+        self.buf_mgr.pin_count(block)
+    }
+
+    // 表使用的缓冲区管理器，供测试在表操作之外 pin 住页面
+    pub(crate) fn buffer_manager(&mut self) -> &mut BufferManager {
+        &mut self.buf_mgr
+    }
+
+    // 写回指定页面并同步文件，紧缩规则与 flush 相同
+    pub(crate) fn flush_pages(&mut self, blocks: impl Iterator<Item = BlockId>) -> io::Result<()> {
+        let blocks: Vec<BlockId> = blocks.collect();
+        self.compact_dirty_pages(&blocks)?;
+        for &block in &blocks {
+            self.buf_mgr.flush_page(block)?;
+        }
+        self.buf_mgr.handle.flush()
+    }
+
+    // 写回前按自动紧缩阈值紧缩给定的脏页，并更新空闲空间映射；未设置阈值时不做任何事。
+    // 元数据块和未格式化为数据页的块跳过
+    fn compact_dirty_pages(&mut self, blocks: &[BlockId]) -> io::Result<()> {
+        let Some(threshold) = self.compact_on_flush else {
+            return Ok(());
+        };
+        let page_size = self.buf_mgr.handle.block_size();
+        for &block in blocks {
+            if self.buf_mgr.handle.is_metadata_block(block) || !self.buf_mgr.is_dirty(block) {
+                continue;
+            }
+            let mut frame = self.buf_mgr.fetch(block)?;
+            let mut page = match Page::load(&frame) {
+                Ok(page) => page,
+                Err(err) if err.kind() == ErrorKind::InvalidData => continue,
+                Err(err) => return Err(err),
+            };
+            let reclaimable = page
+                .free_space_after_compact()
+                .saturating_sub(page.header.free_bytes);
+            if reclaimable as f64 <= threshold * page_size as f64 {
+                continue;
+            }
+            page.compact()?;
+            page.flush(&mut frame)?;
+            drop(frame);
+            self.buf_mgr
+                .handle
+                .record_free_space(block, page.header.free_bytes)?;
+        }
+        Ok(())
+    }
+
+    // 按撤销日志恢复页面：还原修改前镜像，释放事务内新分配的页面
+    pub(crate) fn apply_undo(&mut self, undo: UndoLog) -> io::Result<()> {
+        for (block, image) in undo.before_images {
+            let mut frame = self.buf_mgr.fetch(block)?;
+            // guard 被写过，Drop 时自动标记脏页并 unpin
+            frame.copy_from_slice(&image);
+        }
+        for &block in undo.allocated.iter().rev() {
+            self.buf_mgr.free_page(block)?;
+            self.buf_mgr.handle.release_block(block)?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+use std::io::{self, ErrorKind};
+
+// 列类型：定长类型的宽度固定，变长类型存放在记录尾部的堆区
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColumnType {
+    U32,
+    I32,
+    F64,
+    Str,
+    Bytes,
+}
+
+impl ColumnType {
+    // 定长类型的编码字节数，变长类型返回 None
+    pub fn fixed_size(self) -> Option<usize> {
+        match self {
+            ColumnType::U32 | ColumnType::I32 => Some(4),
+            ColumnType::F64 => Some(8),
+            ColumnType::Str | ColumnType::Bytes => None,
+        }
+    }
+}
+
+// 列定义
+// compressed 为 true 的变长列在记录中以 LZ4 压缩后存放，定长列忽略该标志
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Column {
+    pub name: String,
+    pub col_type: ColumnType,
+    pub compressed: bool,
+}
+
+impl Column {
+    pub fn new(name: impl Into<String>, col_type: ColumnType) -> Self {
+        Column {
+            name: name.into(),
+            col_type,
+            compressed: false,
+        }
+    }
+
+    // 把列声明为压缩列（只对 Str、Bytes 等变长列生效）
+    pub fn compressed(mut self) -> Self {
+        self.compressed = true;
+        self
+    }
+
+    // 该列的值是否以压缩形式存放
+    fn is_compressed(&self) -> bool {
+        self.compressed && self.col_type.fixed_size().is_none()
+    }
+
+    // 变长列在变长列槽区占用的字节数；压缩列额外记录原始长度
+    fn var_slot_bytes(&self) -> usize {
+        if self.is_compressed() {
+            COMPRESSED_SLOT_BYTES
+        } else {
+            VAR_SLOT_BYTES
+        }
+    }
+}
+
+// 变长列槽的字节数：2 字节偏移 + 2 字节长度
+const VAR_SLOT_BYTES: usize = 4;
+
+// 压缩列槽的字节数：2 字节偏移 + 2 字节存放长度 + 2 字节原始（解压后）长度
+const COMPRESSED_SLOT_BYTES: usize = 6;
+
+// 表结构，同时定义记录的二进制布局：
+// [NULL 位图][定长列区][变长列槽区][堆区]
+// - NULL 位图：每列 1 位（第 i 列对应第 i/8 字节的第 i%8 位），置 1 表示 NULL
+// - 定长列区：所有定长列按声明顺序紧密排列，NULL 列同样占位（填 0），保证偏移固定
+// - 变长列槽区：每个变长列一个槽，记录该列在记录内的偏移与长度（u16 小端）；
+//   压缩列的槽另有 2 字节原始长度，存放长度与原始长度相等表示压缩无收益、按原样存放
+// - 堆区：变长列的实际内容（压缩列为 LZ4 块格式的压缩数据）
+// 每列的位置都可由 Schema 直接算出，读取任意列无需扫描其他列
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schema {
+    columns: Vec<Column>,
+    // 每列在定长列区（定长列）或变长列槽区（变长列）中的起始偏移，相对记录开头
+    offsets: Vec<usize>,
+    // 变长列槽区结束位置，即堆区起始偏移
+    heap_start: usize,
+}
+
+impl Schema {
+    pub fn new(columns: Vec<Column>) -> Self {
+        let bitmap_len = columns.len().div_ceil(8);
+        let fixed_len: usize = columns.iter().filter_map(|c| c.col_type.fixed_size()).sum();
+        let mut offsets = Vec::with_capacity(columns.len());
+        let mut fixed_pos = bitmap_len;
+        let mut var_pos = bitmap_len + fixed_len;
+        for column in &columns {
+            match column.col_type.fixed_size() {
+                Some(size) => {
+                    offsets.push(fixed_pos);
+                    fixed_pos += size;
+                }
+                None => {
+                    offsets.push(var_pos);
+                    var_pos += column.var_slot_bytes();
+                }
+            }
+        }
+        Schema {
+            columns,
+            offsets,
+            heap_start: var_pos,
+        }
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.columns.len()
+    }
+
+    // 按列名查找列下标
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c.name == name)
+    }
+
+    // 序列化表结构（只保存列定义，布局偏移在反序列化时重新计算）
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        bincode::serialize(&self.columns).map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))
+    }
+
+    // 从 to_bytes 的输出恢复表结构
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Schema> {
+        let columns: Vec<Column> =
+            bincode::deserialize(bytes).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        Ok(Schema::new(columns))
+    }
+
+    // NULL 位图的字节数
+    fn bitmap_len(&self) -> usize {
+        self.columns.len().div_ceil(8)
+    }
+
+    // 按 Schema 编码一条记录，values[i] 为 None 表示第 i 列为 NULL
+    // 列数不符、定长列宽度不符或记录超过 u16 可寻址范围时返回 InvalidInput
+    pub fn encode(&self, values: &[Option<&[u8]>]) -> io::Result<Vec<u8>> {
+        if values.len() != self.columns.len() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "列数不匹配：Schema 有 {} 列，实际提供 {} 列",
+                    self.columns.len(),
+                    values.len()
+                ),
+            ));
+        }
+        let mut record = vec![0u8; self.heap_start];
+        for (i, (column, value)) in self.columns.iter().zip(values).enumerate() {
+            let offset = self.offsets[i];
+            let Some(value) = value else {
+                record[i / 8] |= 1 << (i % 8);
+                continue;
+            };
+            match column.col_type.fixed_size() {
+                Some(size) => {
+                    if value.len() != size {
+                        return Err(io::Error::new(
+                            ErrorKind::InvalidInput,
+                            format!(
+                                "列 {} 需要 {} 字节，实际为 {} 字节",
+                                column.name,
+                                size,
+                                value.len()
+                            ),
+                        ));
+                    }
+                    record[offset..offset + size].copy_from_slice(value);
+                }
+                None => {
+                    // 压缩没有收益时按原样存放
+                    let packed = column
+                        .is_compressed()
+                        .then(|| lz4_flex::block::compress(value))
+                        .filter(|packed| packed.len() < value.len());
+                    let stored = packed.as_deref().unwrap_or(value);
+                    let start = record.len();
+                    if start + stored.len() > u16::MAX as usize || value.len() > u16::MAX as usize {
+                        return Err(io::Error::new(
+                            ErrorKind::InvalidInput,
+                            format!("记录超过 {} 字节，无法编码列 {}", u16::MAX, column.name),
+                        ));
+                    }
+                    record[offset..offset + 2].copy_from_slice(&(start as u16).to_le_bytes());
+                    record[offset + 2..offset + 4]
+                        .copy_from_slice(&(stored.len() as u16).to_le_bytes());
+                    if column.is_compressed() {
+                        record[offset + 4..offset + 6]
+                            .copy_from_slice(&(value.len() as u16).to_le_bytes());
+                    }
+                    record.extend_from_slice(stored);
+                }
+            }
+        }
+        Ok(record)
+    }
+
+    // 第 col 列是否为 NULL；列号越界或记录过短时返回 None
+    pub fn is_null(&self, record: &[u8], col: usize) -> Option<bool> {
+        if col >= self.columns.len() || record.len() < self.bitmap_len() {
+            return None;
+        }
+        Some(record[col / 8] & (1 << (col % 8)) != 0)
+    }
+
+    // O(1) 读取第 col 列在记录中存放的字节；NULL 列、列号越界或记录损坏时返回 None
+    // 压缩列返回的是压缩后的字节，需要原始值时使用 decode
+    pub fn column_bytes<'a>(&self, record: &'a [u8], col: usize) -> Option<&'a [u8]> {
+        self.stored_column(record, col).map(|(bytes, _)| bytes)
+    }
+
+    // 第 col 列存放的字节及其原始长度（只有压缩列的两者可能不同）
+    fn stored_column<'a>(&self, record: &'a [u8], col: usize) -> Option<(&'a [u8], usize)> {
+        if self.is_null(record, col)? {
+            return None;
+        }
+        let offset = self.offsets[col];
+        let column = &self.columns[col];
+        match column.col_type.fixed_size() {
+            Some(size) => record.get(offset..offset + size).map(|bytes| (bytes, size)),
+            None => {
+                let slot = record.get(offset..offset + column.var_slot_bytes())?;
+                let start = u16::from_le_bytes(slot[0..2].try_into().unwrap()) as usize;
+                let len = u16::from_le_bytes(slot[2..4].try_into().unwrap()) as usize;
+                let raw_len = if column.is_compressed() {
+                    u16::from_le_bytes(slot[4..6].try_into().unwrap()) as usize
+                } else {
+                    len
+                };
+                if start < self.heap_start {
+                    return None;
+                }
+                record.get(start..start + len).map(|bytes| (bytes, raw_len))
+            }
+        }
+    }
+
+    // 解码整条记录，NULL 列为 None，压缩列还原为原始值；
+    // 记录过短、变长槽越界或压缩数据损坏时返回 InvalidData
+    pub fn decode(&self, record: &[u8]) -> io::Result<Vec<Option<Vec<u8>>>> {
+        if record.len() < self.heap_start {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "记录长度 {} 小于 Schema 要求的最小长度 {}",
+                    record.len(),
+                    self.heap_start
+                ),
+            ));
+        }
+        (0..self.columns.len())
+            .map(|col| {
+                if self.is_null(record, col) == Some(true) {
+                    return Ok(None);
+                }
+                let name = &self.columns[col].name;
+                let (stored, raw_len) = self.stored_column(record, col).ok_or_else(|| {
+                    io::Error::new(ErrorKind::InvalidData, format!("列 {} 的变长槽越界", name))
+                })?;
+                if stored.len() == raw_len {
+                    return Ok(Some(stored.to_vec()));
+                }
+                match lz4_flex::block::decompress(stored, raw_len) {
+                    Ok(value) if value.len() == raw_len => Ok(Some(value)),
+                    Ok(value) => Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "列 {} 解压后长度为 {}，记录的原始长度为 {}",
+                            name,
+                            value.len(),
+                            raw_len
+                        ),
+                    )),
+                    Err(err) => Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("列 {} 的压缩数据损坏：{}", name, err),
+                    )),
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::io;
+
+use crate::fm::BlockId;
+use crate::rm::rm_manager::TableManager;
+use crate::rm::types::Rid;
+
+// 事务撤销日志：记录每个页面在事务内第一次被修改前的镜像，以及事务内新分配的页面
+#[derive(Default)]
+pub(crate) struct UndoLog {
+    pub(crate) before_images: HashMap<BlockId, Vec<u8>>,
+    pub(crate) allocated: Vec<BlockId>,
+}
+
+impl UndoLog {
+    // 该页是否需要保存修改前镜像（事务内新分配的页回滚时直接释放，无需镜像）
+    pub(crate) fn needs_image(&self, block: BlockId) -> bool {
+        !self.before_images.contains_key(&block) && !self.allocated.contains(&block)
+    }
+}
+
+// 事务：借用 TableManager，把多次记录操作组合为一个原子单元
+// - 事务内的读取可以看到本事务尚未提交的修改（共用同一个缓冲区）
+// - commit 将事务涉及的页面写回磁盘并 fsync
+// - rollback 用修改前镜像恢复页面，并释放事务内新分配的页面
+// - 既未提交也未回滚就被 Drop 时，自动尽力回滚
+pub struct Transaction<'a> {
+    table: &'a mut TableManager,
+    finished: bool,
+}
+
+impl<'a> Transaction<'a> {
+    pub(crate) fn new(table: &'a mut TableManager) -> Self {
+        table.begin_undo();
+        Transaction {
+            table,
+            finished: false,
+        }
+    }
+
+    // 在事务内插入一条记录
+    pub fn insert(&mut self, data: &[u8]) -> io::Result<Rid> {
+        self.table.insert(data)
+    }
+
+    // 在事务内读取记录（可见本事务未提交的写入）
+    pub fn get(&mut self, rid: Rid) -> io::Result<Vec<u8>> {
+        self.table.get(rid)
+    }
+
+    // 在事务内读取记录，记录不存在时返回 Ok(None)
+    pub fn get_optional(&mut self, rid: Rid) -> io::Result<Option<Vec<u8>>> {
+        self.table.get_optional(rid)
+    }
+
+    // 在事务内删除记录
+    pub fn delete(&mut self, rid: Rid) -> io::Result<()> {
+        self.table.delete(rid)
+    }
+
+    // 提交：写回事务修改过的所有页面并同步到磁盘
+    pub fn commit(mut self) -> io::Result<()> {
+        self.finished = true;
+        let undo = self.table.take_undo();
+        let blocks = undo.before_images.keys().chain(undo.allocated.iter());
+        self.table.flush_pages(blocks.copied())
+    }
+
+    // 回滚：恢复事务开始前的页面内容
+    pub fn rollback(mut self) -> io::Result<()> {
+        self.finished = true;
+        let undo = self.table.take_undo();
+        self.table.apply_undo(undo)
+    }
+}
+
+impl<'a> Drop for Transaction<'a> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let undo = self.table.take_undo();
+        if let Err(err) = self.table.apply_undo(undo) {
+            eprintln!("警告: 事务自动回滚失败: {}", err);
+        }
+    }
+}
@@ -1,9 +1,43 @@
+use super::schema::Schema;
+use crate::fm::BlockId;
+use std::io::{self, ErrorKind};
+
 // 记录标识符：指定页号(block)和槽(slot)
-pub type Rid = (u32, u16);
+pub type Rid = (BlockId, u16);
+
+// Rid 的规范字节表示（小端）：4 字节 block + 2 字节 slot，需要把 Rid 写入磁盘时使用
+pub mod rid {
+    use super::Rid;
+    use std::convert::TryInto;
+    use std::io::{self, ErrorKind};
+
+    // 编码后的字节数
+    pub const ENCODED_LEN: usize = 6;
+
+    pub fn encode(rid: Rid) -> [u8; ENCODED_LEN] {
+        let mut buf = [0u8; ENCODED_LEN];
+        buf[0..4].copy_from_slice(&rid.0.get().to_le_bytes());
+        buf[4..6].copy_from_slice(&rid.1.to_le_bytes());
+        buf
+    }
+
+    // 从字节切片前 6 字节解码 Rid，长度不足时返回 InvalidData
+    pub fn decode(bytes: &[u8]) -> io::Result<Rid> {
+        if bytes.len() < ENCODED_LEN {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Rid 编码需要 {} 字节，实际只有 {} 字节", ENCODED_LEN, bytes.len()),
+            ));
+        }
+        let block = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+        let slot = u16::from_le_bytes(bytes[4..6].try_into().unwrap());
+        Ok((block.into(), slot))
+    }
+}
 
-// 记录插入时的简单容器（列名-值）
+// 记录插入时的简单容器（列名-值），值为 None 表示该列为 NULL（区别于空字节串）
 pub struct RecAux {
-    pub cols: Vec<(String, Vec<u8>)>,
+    pub cols: Vec<(String, Option<Vec<u8>>)>,
 }
 
 impl RecAux {
@@ -11,6 +45,51 @@ impl RecAux {
         RecAux { cols: Vec::new() }
     }
     pub fn push(&mut self, col: impl Into<String>, val: Vec<u8>) {
-        self.cols.push((col.into(), val));
+        self.cols.push((col.into(), Some(val)));
+    }
+    // 将列设为 NULL
+    pub fn push_null(&mut self, col: impl Into<String>) {
+        self.cols.push((col.into(), None));
+    }
+
+    // 查找列值：外层 None 表示没有该列，内层 None 表示该列为 NULL
+    pub fn get(&self, col: &str) -> Option<Option<&[u8]>> {
+        self.cols
+            .iter()
+            .rev()
+            .find(|(name, _)| name == col)
+            .map(|(_, val)| val.as_deref())
+    }
+
+    // 按 Schema 编码为记录字节（带 NULL 位图）；未提供的列按 NULL 处理
+    // 出现 Schema 中不存在的列名时返回 InvalidInput
+    pub fn to_bytes(&self, schema: &Schema) -> io::Result<Vec<u8>> {
+        if let Some((name, _)) = self
+            .cols
+            .iter()
+            .find(|(name, _)| schema.column_index(name).is_none())
+        {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("Schema 中不存在列 {}", name),
+            ));
+        }
+        let values: Vec<Option<&[u8]>> = schema
+            .columns()
+            .iter()
+            .map(|column| self.get(&column.name).flatten())
+            .collect();
+        schema.encode(&values)
+    }
+
+    // 按 Schema 解码记录字节，列按 Schema 顺序排列，NULL 列的值为 None
+    pub fn from_bytes(schema: &Schema, bytes: &[u8]) -> io::Result<Self> {
+        let cols = schema
+            .columns()
+            .iter()
+            .map(|column| column.name.clone())
+            .zip(schema.decode(bytes)?)
+            .collect();
+        Ok(RecAux { cols })
     }
 }
\ No newline at end of file
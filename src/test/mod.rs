@@ -1,3 +1,35 @@
 pub mod test1;
+pub mod test_fm;
+pub mod test_mm;
+pub mod test_rm;
+pub mod test_wal;
 
-pub use test1::test1;
\ No newline at end of file
+pub use test1::test1;
+pub use test_fm::test_fm;
+pub use test_mm::test_mm;
+pub use test_rm::test_rm;
+pub use test_wal::test_wal;
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::fm::{FileHandle, FileManager, FileManagerConfig};
+
+// 为单个测试准备空的数据目录 data/<name>：已存在时先清空，保证每次运行互不影响
+pub fn test_dir(name: &str) -> io::Result<PathBuf> {
+    let dir = PathBuf::from("data").join(name);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+// 在 data/<name> 下按默认配置创建一个空表文件并打开
+pub fn new_table_file(name: &str) -> io::Result<FileHandle> {
+    let path = test_dir(name)?.join("t.tbl");
+    let file_manager = FileManager::new(FileManagerConfig::default());
+    file_manager.create_table_file(&path)?;
+    file_manager.open_file(&path)
+}
@@ -32,11 +32,9 @@ pub fn test1() -> Result<(), Box<dyn Error>> {
         file_manager.create_table_file(&table_path)?;
         // 预分配磁盘块（模拟磁盘空间的块划分）
         {
-            // 打开 FileHandle 后调用 allocate_block 多次预先分配
+            // 打开 FileHandle 后一次性扩展文件预先分配
             let mut handle = file_manager.open_file(&table_path)?;
-            for _ in 0..available_disk_blocks {
-                let _ = handle.allocate_block()?;
-            }
+            handle.preallocate(available_disk_blocks, false)?;
             println!("预分配 {} 个磁盘块完成", available_disk_blocks);
         }
     }
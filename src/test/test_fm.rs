@@ -0,0 +1,1113 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use super::{new_table_file, test_dir};
+use crate::fm::fm_page_header::PageHeader;
+#[cfg(feature = "mmap")]
+use crate::fm::MmapFileHandle;
+use crate::fm::{
+    BlockId, CrashSim, DurabilityMode, FileHeader, FileManager, FileManagerConfig, FreeListPolicy,
+    GrowthPolicy, WriteInterceptor,
+};
+use crate::mm::page::Page;
+use crate::rm::{Column, ColumnType, Schema, TableManager};
+
+// File Manager 相关测试：依次运行本文件中的各项检查
+pub fn test_fm() -> Result<(), Box<dyn Error>> {
+    println!("=== 开始 File Manager 测试 ===");
+    test_rename_table()?;
+    test_header_checksum()?;
+    test_free_space_map_layout()?;
+    test_growth_policy()?;
+    test_block_id()?;
+    test_block_range()?;
+    test_custom_block_size()?;
+    test_block_size_persisted()?;
+    test_durability_mode()?;
+    test_copy_table_file()?;
+    test_read_only_open()?;
+    test_truncated_last_block()?;
+    test_defragment()?;
+    test_encrypted_blocks()?;
+    test_never_allocate_header_block()?;
+    test_free_blocks()?;
+    test_open_or_create_table()?;
+    test_open_without_validation()?;
+    test_file_stat()?;
+    test_free_list_policy()?;
+    #[cfg(feature = "mmap")]
+    test_mmap_backend()?;
+    test_create_table_file_force()?;
+    test_list_tables()?;
+    test_crash_during_insert()?;
+    test_zero_block()?;
+    test_block_error_context()?;
+    test_preallocate()?;
+    test_iter_blocks()?;
+    test_extents()?;
+    println!("=== File Manager 测试完成 ===");
+    Ok(())
+}
+
+// 重命名表文件：目标已存在时拒绝覆盖且源文件不变，成功后源路径消失、记录可从新路径读出
+fn test_rename_table() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("rename_table")?;
+    let file_manager = FileManager::new(FileManagerConfig::default());
+    let src = dir.join("a.tbl");
+    let occupied = dir.join("b.tbl");
+    file_manager.create_table_file(&src)?;
+    file_manager.create_table_file(&occupied)?;
+    let rid = {
+        let mut table_mgr = TableManager::new(file_manager.open_file(&src)?, 4);
+        let rid = table_mgr.insert(b"row")?;
+        table_mgr.flush()?;
+        rid
+    };
+
+    let err = file_manager.rename_table(&src, &occupied).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    assert!(src.exists());
+    let mut occupied_mgr = TableManager::new(file_manager.open_file(&occupied)?, 4);
+    assert_eq!(occupied_mgr.count()?, 0);
+    drop(occupied_mgr);
+
+    let dst = dir.join("sub").join("c.tbl");
+    file_manager.rename_table(&src, &dst)?;
+    assert!(!src.exists());
+    let mut table_mgr = TableManager::new(file_manager.open_file(&dst)?, 4);
+    assert_eq!(table_mgr.get(rid)?, b"row");
+    drop(table_mgr);
+
+    // 带元数据块的表：元数据块中的表名随之改为新文件名，表结构与记录不变
+    let schema = Schema::new(vec![Column::new("id", ColumnType::U32)]);
+    let orders = dir.join("orders.tbl");
+    file_manager.create_table_file_with_schema(&orders, &schema)?;
+    assert_eq!(
+        file_manager.open_file(&orders)?.table_name(),
+        Some("orders")
+    );
+    let rid = {
+        let mut table_mgr = TableManager::new(file_manager.open_file(&orders)?, 4);
+        let rid = table_mgr.insert(&7u32.to_le_bytes())?;
+        table_mgr.flush()?;
+        rid
+    };
+    let err = file_manager.rename_table(&orders, &dst).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    assert_eq!(
+        file_manager.open_file(&orders)?.table_name(),
+        Some("orders")
+    );
+
+    let archived = dir.join("archived_orders.tbl");
+    file_manager.rename_table(&orders, &archived)?;
+    assert!(!orders.exists());
+    let handle = file_manager.open_file(&archived)?;
+    assert_eq!(handle.table_name(), Some("archived_orders"));
+    assert_eq!(handle.schema(), Some(&schema));
+    let mut table_mgr = TableManager::new(handle, 4);
+    assert_eq!(table_mgr.get(rid)?, 7u32.to_le_bytes());
+    println!("rename_table 验证通过");
+    Ok(())
+}
+
+// 文件头校验和：当前格式的文件头校验和被清零或字段被改动时拒绝打开；
+// 只有旧格式（前 16 字节之后全为 0）的文件头允许不带校验和
+fn test_header_checksum() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("header_checksum")?;
+    let file_manager = FileManager::new(FileManagerConfig::default());
+    let path = dir.join("a.tbl");
+    file_manager.create_table_file(&path)?;
+
+    // 清零校验和
+    {
+        let mut file = OpenOptions::new().write(true).open(&path)?;
+        file.seek(SeekFrom::Start(32))?;
+        file.write_all(&[0u8; 4])?;
+    }
+    match file_manager.open_file(&path) {
+        Ok(_) => panic!("校验和被清零的文件头仍能打开"),
+        Err(err) => assert_eq!(err.kind(), ErrorKind::InvalidData),
+    }
+
+    // 恢复正确的文件头后再改动一个字段
+    let header = FileHeader::from_bytes_unverified(&std::fs::read(&path)?)?;
+    let mut bytes = header.to_bytes();
+    {
+        let mut file = OpenOptions::new().write(true).open(&path)?;
+        file.write_all(&bytes)?;
+    }
+    drop(file_manager.open_file(&path)?);
+    bytes[0] ^= 1;
+    assert_eq!(
+        FileHeader::from_bytes(&bytes).unwrap_err().kind(),
+        ErrorKind::InvalidData
+    );
+
+    // 旧格式文件头：只有前 16 字节
+    let mut legacy = [0u8; FileHeader::BYTE_SIZE];
+    legacy[0..4].copy_from_slice(&3u32.to_le_bytes());
+    legacy[4..8].copy_from_slice(&(-1i32).to_le_bytes());
+    let header = FileHeader::from_bytes(&legacy)?;
+    assert_eq!(header.block_count, 3);
+    assert_eq!(header.first_free_hole, -1);
+    println!("文件头校验和验证通过");
+    Ok(())
+}
+
+// 空闲空间映射块：条目从偏移 16 开始，偏移 12（数据页的页格式版本）恒为 0，
+// 即使第一个条目的块号低字节恰好等于页格式版本，映射块也不会被当作数据页解析
+fn test_free_space_map_layout() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("free_space_map_layout")?;
+    let file_manager = FileManager::new(FileManagerConfig::default());
+    let path = dir.join("a.tbl");
+    file_manager.create_table_file(&path)?;
+    let map_block = {
+        let mut handle = file_manager.open_file(&path)?;
+        for _ in 0..4 {
+            handle.allocate_block()?;
+        }
+        handle.record_free_space(BlockId::new(4), 100)?;
+        handle.flush()?;
+        BlockId::from(handle.header().free_space_map as u32)
+    };
+
+    let mut handle = file_manager.open_file(&path)?;
+    assert_eq!(handle.free_space_entries(), &[(BlockId::new(4), 100)]);
+    let mut buffer = vec![0u8; handle.block_size()];
+    handle.read_block(map_block, &mut buffer)?;
+    assert_eq!(buffer[12], 0);
+    assert!(Page::load(&buffer).is_err());
+    println!("空闲空间映射块布局验证通过");
+    Ok(())
+}
+
+// 文件增长策略：追加块时一次扩展多个块，多出的块进入空闲链表并被之后的分配依次取用，
+// 文件长度始终等于 block_count 个块
+fn test_growth_policy() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("growth_policy")?;
+    for (growth, first_step) in [(GrowthPolicy::Fixed(8), 8), (GrowthPolicy::Doubling, 1)] {
+        let file_manager = FileManager::new(FileManagerConfig {
+            growth,
+            preallocate_bytes: 0,
+            ..FileManagerConfig::default()
+        });
+        let path = dir.join(format!("{:?}.tbl", growth));
+        file_manager.create_table_file(&path)?;
+        let mut handle = file_manager.open_file(&path)?;
+        let block_size = handle.block_size() as u64;
+        assert_eq!(handle.growth_policy(), growth);
+
+        assert_eq!(handle.allocate_block()?, BlockId::new(1));
+        assert_eq!(handle.header().block_count, 1 + first_step);
+        assert_eq!(handle.free_blocks()?.len() as u32, first_step - 1);
+
+        let mut blocks = Vec::new();
+        for _ in 0..20 {
+            blocks.push(handle.allocate_block()?);
+            let header = handle.header();
+            let len = std::fs::metadata(&path)?.len();
+            assert_eq!(len, header.block_count as u64 * block_size);
+        }
+        assert_eq!(blocks, (2..22).map(BlockId::from).collect::<Vec<_>>());
+        let free = handle.free_blocks()?.len() as u32;
+        assert_eq!(handle.header().block_count - free, 22);
+    }
+    println!("文件增长策略验证通过");
+    Ok(())
+}
+
+// 编译期断言：类型 $ty 没有实现 $trait。若实现了，两个 AmbiguousIfImpl 实现同时适用，
+// 类型推断失败，整个 crate 无法编译
+macro_rules! assert_not_impl {
+    ($ty:ty: $trait:path) => {
+        const _: fn() = || {
+            trait AmbiguousIfImpl<A> {
+                fn some_item() {}
+            }
+            impl<T: ?Sized> AmbiguousIfImpl<()> for T {}
+            struct Invalid;
+            impl<T: ?Sized + $trait> AmbiguousIfImpl<Invalid> for T {}
+            let _ = <$ty as AmbiguousIfImpl<_>>::some_item;
+        };
+    };
+}
+
+// 块号与槽号（u16）、块数量等普通整数不能隐式混用：以下任一断言不成立都会导致编译失败
+assert_not_impl!(BlockId: From<u16>);
+assert_not_impl!(BlockId: From<usize>);
+assert_not_impl!(u16: From<BlockId>);
+assert_not_impl!(BlockId: PartialEq<u32>);
+assert_not_impl!(BlockId: PartialEq<u16>);
+assert_not_impl!(BlockId: std::ops::Add<u32>);
+
+// 块号新类型：与 u32 之间显式转换可以往返，Display 输出裸块号
+fn test_block_id() -> Result<(), Box<dyn Error>> {
+    let block = BlockId::from(7u32);
+    assert_eq!(block, BlockId::new(7));
+    assert_eq!(u32::from(block), 7);
+    let raw: u32 = block.into();
+    assert_eq!(raw, block.get());
+    assert_eq!(block.to_string(), "7");
+    assert!(BlockId::new(1) < BlockId::new(2));
+    println!("块号类型验证通过");
+    Ok(())
+}
+
+// 块号范围检查：最后一个有效块可以读写，超出 block_count 一块即报 InvalidInput，
+// 重新打开文件后范围不变
+fn test_block_range() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("block_range")?.join("a.tbl");
+    let file_manager = FileManager::new(FileManagerConfig::default());
+    file_manager.create_table_file(&path)?;
+    let last = {
+        let mut handle = file_manager.open_file(&path)?;
+        let mut last = handle.allocate_block()?;
+        for _ in 0..4 {
+            last = handle.allocate_block()?;
+        }
+        assert_eq!(last.get() + 1, handle.header().block_count);
+        handle.write_block(last, &vec![0x5a; handle.block_size()])?;
+        handle.flush()?;
+        last
+    };
+    let past = BlockId::new(last.get() + 1);
+
+    let mut handle = file_manager.open_file(&path)?;
+    let mut buffer = vec![0u8; handle.block_size()];
+    handle.read_block(last, &mut buffer)?;
+    assert!(buffer.iter().all(|&b| b == 0x5a));
+    let err = handle.read_block(past, &mut buffer).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = handle.write_block(past, &buffer).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    println!("块号范围检查验证通过");
+    Ok(())
+}
+
+// 非默认块大小：块大小写入文件头，之后用默认配置打开仍按文件自身的块大小读写，
+// 块按该大小在文件中定位，长度不符的 buffer 被拒绝
+fn test_custom_block_size() -> Result<(), Box<dyn Error>> {
+    const BLOCK_SIZE: usize = 16 * 1024;
+    let path = test_dir("custom_block_size")?.join("a.tbl");
+    let file_manager = FileManager::new(FileManagerConfig {
+        block_size: BLOCK_SIZE,
+        preallocate_bytes: 0,
+        ..FileManagerConfig::default()
+    });
+    file_manager.create_table_file(&path)?;
+    let block = {
+        let mut handle = file_manager.open_file(&path)?;
+        assert_eq!(handle.block_size(), BLOCK_SIZE);
+        handle.allocate_block()?;
+        let block = handle.allocate_block()?;
+        handle.write_block(block, &vec![0xa5; BLOCK_SIZE])?;
+        handle.flush()?;
+        block
+    };
+    let len = std::fs::metadata(&path)?.len();
+    assert_eq!(len, 3 * BLOCK_SIZE as u64);
+
+    let mut handle = FileManager::new(FileManagerConfig::default()).open_file(&path)?;
+    assert_eq!(handle.block_size(), BLOCK_SIZE);
+    assert_eq!(handle.header().block_size as usize, BLOCK_SIZE);
+    let mut buffer = vec![0u8; BLOCK_SIZE];
+    handle.read_block(block, &mut buffer)?;
+    assert!(buffer.iter().all(|&b| b == 0xa5));
+    let raw = std::fs::read(&path)?;
+    assert!(raw[2 * BLOCK_SIZE..].iter().all(|&b| b == 0xa5));
+    let mut small = vec![0u8; 4096];
+    let err = handle.read_block(block, &mut small).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    println!("非默认块大小验证通过");
+    Ok(())
+}
+
+// 块大小记录在文件头中：8 KB 块的表用默认（4 KB）配置重新打开，仍按 8 KB 定位块，
+// 跨多个数据页的记录全部原样读回
+fn test_block_size_persisted() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("block_size_persisted")?.join("a.tbl");
+    let file_manager = FileManager::new(FileManagerConfig {
+        block_size: 8192,
+        ..FileManagerConfig::default()
+    });
+    file_manager.create_table_file(&path)?;
+    let records: Vec<Vec<u8>> = (0..6u8).map(|i| vec![i + 1; 3000]).collect();
+    let rids = {
+        let mut table_mgr = TableManager::new(file_manager.open_file(&path)?, 4);
+        let rids = records
+            .iter()
+            .map(|record| table_mgr.insert(record))
+            .collect::<Result<Vec<_>, _>>()?;
+        table_mgr.flush()?;
+        rids
+    };
+    assert!(rids.iter().any(|rid| rid.0 != rids[0].0));
+
+    let default_manager = FileManager::new(FileManagerConfig::default());
+    let handle = default_manager.open_file(&path)?;
+    assert_eq!(handle.block_size(), 8192);
+    assert_eq!(handle.header().block_size, 8192);
+    let mut table_mgr = TableManager::new(handle, 4);
+    for (rid, record) in rids.iter().zip(&records) {
+        assert_eq!(&table_mgr.get(*rid)?, record);
+    }
+    println!("文件头记录的块大小验证通过");
+    Ok(())
+}
+
+// 统计 FileHandle 发出 fsync 的次数，写入全部放行
+struct SyncCounter(Arc<AtomicUsize>);
+
+impl WriteInterceptor for SyncCounter {
+    fn allow_write(&mut self, _block: BlockId) -> bool {
+        true
+    }
+
+    fn on_sync(&mut self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// 持久化模式：Full 与 DataOnly 在 flush 时发出 fsync，None 完全不 fsync，数据仍然写入文件
+fn test_durability_mode() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("durability_mode")?;
+    for (durability, expect_sync) in [
+        (DurabilityMode::Full, true),
+        (DurabilityMode::DataOnly, true),
+        (DurabilityMode::None, false),
+    ] {
+        let file_manager = FileManager::new(FileManagerConfig {
+            durability,
+            ..FileManagerConfig::default()
+        });
+        let path = dir.join(format!("{:?}.tbl", durability));
+        file_manager.create_table_file(&path)?;
+        let mut handle = file_manager.open_file(&path)?;
+        assert_eq!(handle.durability(), durability);
+        let syncs = Arc::new(AtomicUsize::new(0));
+        handle.set_write_interceptor(Some(Box::new(SyncCounter(Arc::clone(&syncs)))));
+
+        let block = handle.allocate_block()?;
+        handle.write_block(block, &vec![3u8; handle.block_size()])?;
+        handle.flush()?;
+        assert_eq!(syncs.load(Ordering::Relaxed) > 0, expect_sync);
+        drop(handle);
+
+        let mut handle = file_manager.open_file(&path)?;
+        let mut buffer = vec![0u8; handle.block_size()];
+        handle.read_block(block, &mut buffer)?;
+        assert!(buffer.iter().all(|&b| b == 3));
+    }
+    println!("持久化模式验证通过");
+    Ok(())
+}
+
+// 复制表文件：副本中的记录与源表逐条相同，只拷贝已分配的块（不含源文件尾部的预分配空间），
+// 目标已存在时拒绝覆盖
+fn test_copy_table_file() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("copy_table_file")?;
+    let file_manager = FileManager::new(FileManagerConfig::default());
+    let src = dir.join("a.tbl");
+    let dst = dir.join("b.tbl");
+    file_manager.create_table_file(&src)?;
+    let records: Vec<Vec<u8>> = (0..50u32)
+        .map(|i| vec![i as u8; 10 + i as usize % 7 * 20])
+        .collect();
+    let rids = {
+        let mut table_mgr = TableManager::new(file_manager.open_file(&src)?, 4);
+        let rids = records
+            .iter()
+            .map(|record| table_mgr.insert(record))
+            .collect::<Result<Vec<_>, _>>()?;
+        table_mgr.flush()?;
+        rids
+    };
+
+    file_manager.copy_table_file(&src, &dst)?;
+    let err = file_manager.copy_table_file(&src, &dst).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+
+    let handle = file_manager.open_file(&dst)?;
+    let copied_len = handle.header().block_count as u64 * handle.block_size() as u64;
+    assert_eq!(std::fs::metadata(&dst)?.len(), copied_len);
+    assert!(std::fs::metadata(&src)?.len() > copied_len);
+    let mut table_mgr = TableManager::new(handle, 4);
+    assert_eq!(table_mgr.count()?, records.len() as u64);
+    for (rid, record) in rids.iter().zip(&records) {
+        assert_eq!(&table_mgr.get(*rid)?, record);
+    }
+
+    // 带表结构的表：副本能重新读出表结构和记录，源文件一字节不变
+    let schema = Schema::new(vec![
+        Column::new("id", ColumnType::U32),
+        Column::new("name", ColumnType::Str),
+    ]);
+    let schema_src = dir.join("schema.tbl");
+    let schema_dst = dir.join("schema_copy.tbl");
+    file_manager.create_table_file_with_schema(&schema_src, &schema)?;
+    let rid = {
+        let mut table_mgr = TableManager::new(file_manager.open_file(&schema_src)?, 4);
+        let rid = table_mgr.insert(b"with schema")?;
+        table_mgr.flush()?;
+        rid
+    };
+    let before = std::fs::read(&schema_src)?;
+    file_manager.copy_table_file(&schema_src, &schema_dst)?;
+    assert_eq!(std::fs::read(&schema_src)?, before);
+    let handle = file_manager.open_file(&schema_dst)?;
+    assert_eq!(handle.schema(), Some(&schema));
+    let mut table_mgr = TableManager::new(handle, 4);
+    assert_eq!(table_mgr.get(rid)?, b"with schema");
+    println!("复制表文件验证通过");
+    Ok(())
+}
+
+// 只读打开：可以正常读块，任何修改都返回 PermissionDenied，关闭后文件内容一字节不变
+fn test_read_only_open() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("read_only_open")?.join("a.tbl");
+    let file_manager = FileManager::new(FileManagerConfig::default());
+    file_manager.create_table_file(&path)?;
+    let block = {
+        let mut handle = file_manager.open_file(&path)?;
+        let block = handle.allocate_block()?;
+        handle.write_block(block, &vec![7u8; handle.block_size()])?;
+        handle.flush()?;
+        block
+    };
+    let before = std::fs::read(&path)?;
+
+    let mut handle = file_manager.open_file_readonly(&path)?;
+    assert!(handle.is_read_only());
+    let mut buffer = vec![0u8; handle.block_size()];
+    handle.read_block(block, &mut buffer)?;
+    assert!(buffer.iter().all(|&b| b == 7));
+    let denied = [
+        handle.write_block(block, &buffer).err(),
+        handle.allocate_block().err(),
+        handle.release_block(block).err(),
+        handle.zero_block(block).err(),
+        handle.take_auto_id().err(),
+    ];
+    for err in denied {
+        assert_eq!(err.map(|err| err.kind()), Some(ErrorKind::PermissionDenied));
+    }
+    handle.flush()?;
+    drop(handle);
+    assert_eq!(std::fs::read(&path)?, before);
+    println!("只读打开验证通过");
+    Ok(())
+}
+
+// 文件比文件头声明的块数短（最后一块只写了一部分）时，打开即报 InvalidData，
+// 而不是在之后读到该块时才遇到 EOF
+fn test_truncated_last_block() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("truncated_last_block")?.join("a.tbl");
+    let file_manager = FileManager::new(FileManagerConfig {
+        preallocate_bytes: 0,
+        ..FileManagerConfig::default()
+    });
+    file_manager.create_table_file(&path)?;
+    let block_size = {
+        let mut handle = file_manager.open_file(&path)?;
+        handle.allocate_block()?;
+        handle.allocate_block()?;
+        handle.flush()?;
+        handle.block_size() as u64
+    };
+    let file = OpenOptions::new().write(true).open(&path)?;
+    assert_eq!(file.metadata()?.len(), 3 * block_size);
+    drop(file_manager.open_file(&path)?);
+
+    file.set_len(3 * block_size - 1)?;
+    match file_manager.open_file(&path) {
+        Ok(_) => panic!("最后一块不完整的文件仍能打开"),
+        Err(err) => assert_eq!(err.kind(), ErrorKind::InvalidData),
+    }
+    println!("不完整尾块检测验证通过");
+    Ok(())
+}
+
+// 物理重排：按给定顺序搬运块（源与目标位置重叠，形成置换环），每个移动过的块在新位置上的内容
+// 与移动前一致，不在 order 中的块不动；空闲块或重复块号不能参与重排
+fn test_defragment() -> Result<(), Box<dyn Error>> {
+    let mut handle = new_table_file("defragment")?;
+    let block_size = handle.block_size();
+    let mut blocks = Vec::new();
+    let mut contents = Vec::new();
+    for i in 0..6u8 {
+        let block = handle.allocate_block()?;
+        let mut buffer = vec![i + 10; block_size];
+        buffer[0] = i;
+        handle.write_block(block, &buffer)?;
+        blocks.push(block);
+        contents.push(buffer);
+    }
+
+    let order = [blocks[4], blocks[1], blocks[5], blocks[0], blocks[2]];
+    let moves = handle.defragment(&order)?;
+    assert_eq!(moves.len(), 4);
+    let mut buffer = vec![0u8; block_size];
+    for (old, new) in &moves {
+        let index = blocks.iter().position(|block| block == old).unwrap();
+        handle.read_block(*new, &mut buffer)?;
+        assert_eq!(buffer, contents[index]);
+    }
+    for unmoved in [blocks[1], blocks[3]] {
+        let index = blocks.iter().position(|&block| block == unmoved).unwrap();
+        handle.read_block(unmoved, &mut buffer)?;
+        assert_eq!(buffer, contents[index]);
+    }
+
+    handle.release_block(blocks[3])?;
+    assert!(handle.defragment(&[blocks[3], blocks[0]]).is_err());
+    assert!(handle.defragment(&[blocks[1], blocks[1]]).is_err());
+    println!("块物理重排验证通过");
+    Ok(())
+}
+
+// 加密表文件：读写往返一致，磁盘上不是明文且相同明文在不同块得到不同密文；
+// 加密文件不能按普通方式打开，已有数据的普通文件也不能按加密方式打开
+fn test_encrypted_blocks() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("encrypted_blocks")?;
+    let fm = FileManager::new(FileManagerConfig::default());
+    let path = dir.join("secret.tbl");
+    fm.create_table_file(&path)?;
+    let key = [7u8; 32];
+    let mut handle = fm.open_file_encrypted(&path, key)?;
+    assert!(handle.is_encrypted());
+    let block_size = handle.block_size();
+    let first = handle.allocate_block()?;
+    let second = handle.allocate_block()?;
+    let plain = vec![0xabu8; block_size];
+    handle.write_block(first, &plain)?;
+    handle.write_block(second, &plain)?;
+    handle.flush()?;
+    drop(handle);
+
+    let raw = std::fs::read(&path)?;
+    let offset = |block: BlockId| block.get() as usize * block_size;
+    let first_raw = &raw[offset(first)..offset(first) + block_size];
+    let second_raw = &raw[offset(second)..offset(second) + block_size];
+    assert_ne!(first_raw, &plain[..]);
+    assert_ne!(first_raw, second_raw);
+
+    assert!(fm.open_file(&path).is_err());
+    let mut handle = fm.open_file_encrypted(&path, key)?;
+    let mut buffer = vec![0u8; block_size];
+    handle.read_block(first, &mut buffer)?;
+    assert_eq!(buffer, plain);
+    handle.read_block(second, &mut buffer)?;
+    assert_eq!(buffer, plain);
+    drop(handle);
+
+    let plain_path = dir.join("plain.tbl");
+    fm.create_table_file(&plain_path)?;
+    let mut handle = fm.open_file(&plain_path)?;
+    assert!(!handle.is_encrypted());
+    handle.allocate_block()?;
+    drop(handle);
+    assert!(fm.open_file_encrypted(&plain_path, key).is_err());
+    println!("加密表文件验证通过");
+    Ok(())
+}
+
+// 文件头块不会被分配：空闲链表头被破坏成指向块 0 时，分配报 InvalidData 且不改动文件头
+fn test_never_allocate_header_block() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("never_allocate_header_block")?;
+    let fm = FileManager::new(FileManagerConfig::default());
+    let path = dir.join("table.tbl");
+    fm.create_table_file(&path)?;
+    fm.open_file(&path)?.allocate_block()?;
+    let header = fm.open_file(&path)?.header();
+    let mut file = OpenOptions::new().write(true).open(&path)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(
+        &FileHeader {
+            first_free_hole: 0,
+            ..header
+        }
+        .to_bytes(),
+    )?;
+    drop(file);
+
+    let mut handle = fm.open_file(&path)?;
+    let err = handle.allocate_block().err().map(|err| err.kind());
+    assert_eq!(err, Some(ErrorKind::InvalidData));
+    assert_eq!(handle.header().first_free_hole, 0);
+    println!("文件头块不可分配验证通过");
+    Ok(())
+}
+
+// 空闲块列表：按空闲链表列出所有空闲块，与释放的块集合一致（不论顺序），数量与 free_list_len 相同
+fn test_free_blocks() -> Result<(), Box<dyn Error>> {
+    let mut handle = new_table_file("free_blocks")?;
+    let mut blocks = Vec::new();
+    for _ in 0..6 {
+        blocks.push(handle.allocate_block()?);
+    }
+    assert!(handle.free_blocks()?.is_empty());
+    for i in [1, 4, 2] {
+        handle.release_block(blocks[i])?;
+    }
+    let mut free = handle.free_blocks()?;
+    free.sort();
+    assert_eq!(free, vec![blocks[1], blocks[2], blocks[4]]);
+    assert_eq!(handle.free_list_len()?, 3);
+    println!("空闲块列表验证通过");
+    Ok(())
+}
+
+// 打开或创建：文件不存在时（连同父目录）创建，已存在时直接打开且不覆盖已有数据；
+// 多个线程同时对同一路径调用时都能成功打开同一个新文件
+fn test_open_or_create_table() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("open_or_create_table")?;
+    let fm = FileManager::new(FileManagerConfig::default());
+    let path = dir.join("nested").join("table.tbl");
+    fm.open_or_create_table(&path)?.allocate_block()?;
+    assert_eq!(fm.open_or_create_table(&path)?.header().block_count, 2);
+
+    let race_path = dir.join("race.tbl");
+    let workers: Vec<_> = (0..8)
+        .map(|_| {
+            let path = race_path.clone();
+            std::thread::spawn(move || {
+                FileManager::new(FileManagerConfig::default())
+                    .open_or_create_table(&path)
+                    .map(|handle| handle.header().block_count)
+            })
+        })
+        .collect();
+    for worker in workers {
+        assert_eq!(worker.join().unwrap()?, 1);
+    }
+    println!("打开或创建表文件验证通过");
+    Ok(())
+}
+
+// 跳过校验的快速打开：validate 关闭时校验和被破坏的文件头也能打开，默认配置仍然拒绝；
+// 数据页的结构校验不受该选项影响，访问被写坏的数据页时照样报 InvalidData
+fn test_open_without_validation() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("open_without_validation")?;
+    let file_manager = FileManager::new(FileManagerConfig::default());
+    let path = dir.join("a.tbl");
+    file_manager.create_table_file(&path)?;
+    let rid = {
+        let mut tm = TableManager::new(file_manager.open_file(&path)?, 4);
+        let rid = tm.insert(b"record")?;
+        tm.flush()?;
+        rid
+    };
+    let mut handle = file_manager.open_file(&path)?;
+    handle.write_block(rid.0, &vec![0xffu8; handle.block_size()])?;
+    drop(handle);
+
+    // 清零校验和
+    {
+        let mut file = OpenOptions::new().write(true).open(&path)?;
+        file.seek(SeekFrom::Start(32))?;
+        file.write_all(&[0u8; 4])?;
+    }
+    assert!(file_manager.open_file(&path).is_err());
+
+    let fast = FileManager::new(FileManagerConfig {
+        validate: false,
+        ..Default::default()
+    });
+    let mut tm = TableManager::new(fast.open_file(&path)?, 4);
+    let err = tm.get(rid).err().map(|err| err.kind());
+    assert_eq!(err, Some(ErrorKind::InvalidData));
+    println!("跳过文件头校验的快速打开验证通过");
+    Ok(())
+}
+
+// 文件统计：已用块 + 空闲块 + 文件头块 = 总块数，空闲字节为各空闲页页头记录的可用字节之和，
+// 文件大小至少容纳全部块
+fn test_file_stat() -> Result<(), Box<dyn Error>> {
+    let mut handle = new_table_file("file_stat")?;
+    let block_size = handle.block_size() as u64;
+    let mut blocks = Vec::new();
+    for _ in 0..5 {
+        blocks.push(handle.allocate_block()?);
+    }
+    handle.release_block(blocks[1])?;
+    handle.release_block(blocks[3])?;
+
+    let stat = handle.stat()?;
+    assert_eq!(stat.total_blocks, 6);
+    assert_eq!(stat.free_blocks, 2);
+    assert_eq!(stat.used_blocks + stat.free_blocks + 1, stat.total_blocks);
+    assert_eq!(
+        stat.total_free_bytes,
+        2 * (block_size - PageHeader::BYTE_SIZE as u64)
+    );
+    assert!(stat.file_size_bytes >= stat.total_blocks as u64 * block_size);
+    println!("文件统计验证通过");
+    Ok(())
+}
+
+// 空闲块分配策略：首次适应取链表中第一个空间足够的块，最佳适应取空间足够的块中可用字节最少的一个；
+// 没有合适的空闲块时追加新块
+fn test_free_list_policy() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("free_list_policy")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut handle = fm.open_file(&path)?;
+    let block_size = handle.block_size() as u64;
+    let mut blocks = Vec::new();
+    for _ in 0..6 {
+        blocks.push(handle.allocate_block()?);
+    }
+    // 释放后空闲链表顺序为 blocks[4]、blocks[3]、blocks[2]、blocks[1]
+    for &block in &blocks[1..5] {
+        handle.release_block(block)?;
+    }
+    drop(handle);
+
+    // 直接改写各空闲块页头中的可用字节数
+    {
+        let mut file = OpenOptions::new().read(true).write(true).open(&path)?;
+        for (index, free_bytes) in [(4, 3000), (3, 500), (2, 1200), (1, 900)] {
+            let offset = blocks[index].get() as u64 * block_size;
+            let mut bytes = [0u8; PageHeader::BYTE_SIZE];
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut bytes)?;
+            let header = PageHeader {
+                free_bytes,
+                ..PageHeader::from_bytes(&bytes)?
+            };
+            file.seek(SeekFrom::Start(offset))?;
+            file.write_all(&header.to_bytes())?;
+        }
+    }
+
+    let mut handle = fm.open_file(&path)?;
+    assert_eq!(handle.allocate_block_with_space(800)?, blocks[4]);
+    handle.set_free_list_policy(FreeListPolicy::BestFit);
+    assert_eq!(handle.allocate_block_with_space(800)?, blocks[1]);
+    assert_eq!(handle.free_blocks()?, vec![blocks[3], blocks[2]]);
+    let appended = handle.allocate_block_with_space(2000)?;
+    assert!(!blocks.contains(&appended));
+    assert_eq!(handle.allocate_block_with_space(1000)?, blocks[2]);
+    assert_eq!(handle.free_blocks()?, vec![blocks[3]]);
+    println!("空闲块分配策略验证通过");
+    Ok(())
+}
+
+// 内存映射后端：随机读取的结果与系统调用读取逐块一致；文件增长后重新映射，新块可以写入并读回；
+// 文件头块与越界块不能读取
+#[cfg(feature = "mmap")]
+fn test_mmap_backend() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("mmap_backend")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut handle = fm.open_file(&path)?;
+    let block_size = handle.block_size();
+    let mut blocks = Vec::new();
+    for i in 0..20u32 {
+        let block = handle.allocate_block()?;
+        let mut buffer = vec![0u8; block_size];
+        buffer[..4].copy_from_slice(&i.to_le_bytes());
+        buffer[block_size - 1] = i as u8;
+        handle.write_block(block, &buffer)?;
+        blocks.push(block);
+    }
+    handle.flush()?;
+
+    let mut mapped = MmapFileHandle::new(handle)?;
+    let mut syscall = fm.open_file_readonly(&path)?;
+    let mut seed = 12345u32;
+    let mut from_map = vec![0u8; block_size];
+    let mut from_file = vec![0u8; block_size];
+    for _ in 0..200 {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        let block = blocks[(seed >> 8) as usize % blocks.len()];
+        mapped.read_block(block, &mut from_map)?;
+        syscall.read_block(block, &mut from_file)?;
+        assert_eq!(from_map, from_file);
+    }
+
+    for _ in 0..50 {
+        let block = mapped.handle().allocate_block()?;
+        let buffer = vec![block.get() as u8; block_size];
+        mapped.write_block(block, &buffer)?;
+        mapped.read_block(block, &mut from_map)?;
+        assert_eq!(from_map, buffer);
+    }
+    for block in [0, 1000].map(BlockId::new) {
+        assert!(mapped.read_block(block, &mut from_map).is_err());
+    }
+    println!("内存映射后端验证通过");
+    Ok(())
+}
+
+// 覆盖保护与强制重建：目标已存在时报 AlreadyExists 且错误信息带路径，force 为 false 时同样拒绝；
+// force 为 true 时删除原文件重新创建为空表，目标不存在时照常创建
+fn test_create_table_file_force() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("create_table_file_force")?;
+    let file_manager = FileManager::new(FileManagerConfig::default());
+    let path = dir.join("sub").join("a.tbl");
+    file_manager.create_table_file(&path)?;
+    TableManager::new(file_manager.open_file(&path)?, 4).insert(b"x")?;
+
+    let err = file_manager.create_table_file(&path).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    assert!(err.to_string().contains("a.tbl"));
+    let err = file_manager
+        .create_table_file_force(&path, false)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+
+    file_manager.create_table_file_force(&path, true)?;
+    let mut tm = TableManager::new(file_manager.open_file(&path)?, 4);
+    assert_eq!(tm.count()?, 0);
+    file_manager.create_table_file_force(dir.join("b.tbl"), true)?;
+    assert!(dir.join("b.tbl").exists());
+    println!("覆盖保护与强制重建验证通过");
+    Ok(())
+}
+
+// 列出表文件：只返回目录下扩展名为 .tbl 的普通文件（按路径排序），忽略其他文件、子目录及其中的表；
+// 目录不存在时报错
+fn test_list_tables() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("list_tables")?;
+    let file_manager = FileManager::new(FileManagerConfig::default());
+    for name in ["b.tbl", "a.tbl", "c.tbl"] {
+        file_manager.create_table_file(dir.join(name))?;
+    }
+    std::fs::write(dir.join("notes.txt"), b"x")?;
+    std::fs::write(dir.join("tbl"), b"x")?;
+    file_manager.create_table_file(dir.join("sub").join("d.tbl"))?;
+    std::fs::create_dir_all(dir.join("dir.tbl"))?;
+
+    let expected = vec![dir.join("a.tbl"), dir.join("b.tbl"), dir.join("c.tbl")];
+    assert_eq!(file_manager.list_tables(&dir)?, expected);
+    assert!(file_manager.list_tables(dir.join("missing")).is_err());
+    println!("列出表文件验证通过");
+    Ok(())
+}
+
+// 断电模拟：已提交的 10 条记录之后继续插入，在第 n 次写入后“断电”（之后的写入全部丢弃）；
+// 重新打开后每条可见记录都完整（内容一致、来自某次插入），断电前已提交的记录一条不少
+fn test_crash_during_insert() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("crash_during_insert")?;
+    let fm = FileManager::new(FileManagerConfig::default());
+    for writes in [0, 1, 3, 5, 8, 13, 21, 40] {
+        let path = dir.join(format!("crash_{}.tbl", writes));
+        fm.create_table_file(&path)?;
+        {
+            let mut tm = TableManager::new(fm.open_file(&path)?, 4);
+            for i in 0..10u8 {
+                tm.insert(&[i; 1500])?;
+            }
+            tm.flush()?;
+        }
+
+        let mut handle = fm.open_file(&path)?;
+        let sim = CrashSim::after_writes(writes);
+        let dropped = sim.dropped_writes();
+        handle.set_write_interceptor(Some(Box::new(sim)));
+        {
+            let mut tm = TableManager::new(handle, 3);
+            // 断电后的写入被静默丢弃，之后读回这些块的操作可能失败；
+            // 断电之后的执行结果无关紧要，只检查重新打开后的状态
+            let _ = (10..40u8)
+                .try_for_each(|i| tm.insert(&[i; 1500]).map(drop))
+                .and_then(|_| tm.flush());
+        }
+        assert!(writes >= 40 || dropped.load(Ordering::Relaxed) > 0);
+
+        let mut tm = TableManager::new(fm.open_file(&path)?, 4);
+        let mut seen = HashSet::new();
+        for rid in tm.scan_all()? {
+            let record = tm.get(rid)?;
+            assert_eq!(record.len(), 1500);
+            assert!(record.iter().all(|&b| b == record[0]));
+            assert!(record[0] < 40);
+            seen.insert(record[0]);
+        }
+        assert!((0..10u8).all(|i| seen.contains(&i)));
+    }
+    println!("断电模拟验证通过");
+    Ok(())
+}
+
+// 安全擦除：释放的块在磁盘上除页头外全部为 0；zero_block 保留块首页头、把其余字节覆写为 0，
+// 不改变空闲链表；文件头块不能擦除
+fn test_zero_block() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("zero_block")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut handle = fm.open_file(&path)?;
+    let block_size = handle.block_size();
+    let released = handle.allocate_block()?;
+    let erased = handle.allocate_block()?;
+    handle.write_block(released, &vec![0xab; block_size])?;
+    handle.write_block(erased, &vec![0xcd; block_size])?;
+    handle.release_block(released)?;
+    handle.zero_block(erased)?;
+    handle.flush()?;
+
+    let raw = std::fs::read(&path)?;
+    let block_bytes = |block: BlockId| {
+        let start = block.get() as usize * block_size;
+        &raw[start..start + block_size]
+    };
+    let released_bytes = block_bytes(released);
+    let erased_bytes = block_bytes(erased);
+    let header_len = PageHeader::BYTE_SIZE;
+    assert!(released_bytes[header_len..].iter().all(|&b| b == 0));
+    assert!(erased_bytes[..header_len].iter().all(|&b| b == 0xcd));
+    assert!(erased_bytes[header_len..].iter().all(|&b| b == 0));
+    assert_eq!(handle.free_blocks()?, vec![released]);
+    assert!(handle.zero_block(BlockId::new(0)).is_err());
+    println!("安全擦除验证通过");
+    Ok(())
+}
+
+// 块读写错误的上下文：越界读取、写块 0 的错误信息包含文件路径和块号，ErrorKind 保持原样
+fn test_block_error_context() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("block_error_context")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut handle = fm.open_file(&path)?;
+    let mut buffer = vec![0u8; handle.block_size()];
+
+    let err = handle
+        .read_block(BlockId::new(77), &mut buffer)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let message = err.to_string();
+    assert!(message.contains("块 77"), "{}", message);
+    assert!(message.contains(&path.display().to_string()), "{}", message);
+
+    let err = handle.write_block(BlockId::new(0), &buffer).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("块 0"), "{}", err);
+    println!("块读写错误上下文验证通过");
+    Ok(())
+}
+
+// 批量预分配：一次扩展 1000 个块，文件长度与 block_count 立即到位，新块按块号顺序进入空闲链表，
+// 之后分配不再扩展文件；不进空闲链表的预分配直接视为已分配，重新打开后块数与链表仍然正确
+fn test_preallocate() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("preallocate")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig {
+        growth: GrowthPolicy::Fixed(1),
+        preallocate_bytes: 0,
+        ..FileManagerConfig::default()
+    });
+    fm.create_table_file(&path)?;
+    let mut handle = fm.open_file(&path)?;
+    let block_size = handle.block_size() as u64;
+    let range = handle.preallocate(1000, true)?;
+    assert_eq!(range, BlockId::new(1)..BlockId::new(1001));
+    assert_eq!(handle.header().block_count, 1001);
+    let len = std::fs::metadata(&path)?.len();
+    assert_eq!(len, 1001 * block_size);
+    assert_eq!(handle.free_list_len()?, 1000);
+    assert_eq!(handle.allocate_block()?, BlockId::new(1));
+    assert_eq!(handle.allocate_block()?, BlockId::new(2));
+    assert_eq!(std::fs::metadata(&path)?.len(), len);
+
+    let range = handle.preallocate(5, false)?;
+    assert_eq!(range, BlockId::new(1001)..BlockId::new(1006));
+    assert_eq!(handle.free_list_len()?, 998);
+    handle.release_block(BlockId::new(1))?;
+    let range = handle.preallocate(2, true)?;
+    assert_eq!(handle.free_list_len()?, 1001);
+    let free: HashSet<_> = handle.free_blocks()?.into_iter().collect();
+    assert!(free.contains(&BlockId::new(1)) && free.contains(&range.start));
+    handle.flush()?;
+    drop(handle);
+
+    let mut handle = fm.open_file(&path)?;
+    assert_eq!(handle.header().block_count, 1008);
+    assert_eq!(handle.allocate_block()?, BlockId::new(1006));
+    println!("批量预分配验证通过");
+    Ok(())
+}
+
+// 遍历数据块：iter_blocks 跳过文件头块，按块号顺序给出每个数据块的块号和内容
+fn test_iter_blocks() -> Result<(), Box<dyn Error>> {
+    let mut handle = new_table_file("iter_blocks")?;
+    let block_size = handle.block_size();
+    let mut blocks = Vec::new();
+    for _ in 0..4 {
+        blocks.push(handle.allocate_block()?);
+    }
+    for &block in &blocks {
+        handle.write_block(block, &vec![block.get() as u8 + 10; block_size])?;
+    }
+    let scanned = handle.iter_blocks().collect::<std::io::Result<Vec<_>>>()?;
+    let ids: Vec<_> = scanned.iter().map(|(block, _)| *block).collect();
+    assert_eq!(ids, blocks);
+    assert_eq!(ids[0], BlockId::new(1));
+    for (block, data) in scanned {
+        assert!(data.iter().all(|&b| b == block.get() as u8 + 10));
+    }
+    println!("遍历数据块验证通过");
+    Ok(())
+}
+
+// 扩展段：每段 4 块时写入 9 个数据块会创建 .1、.2 两个段文件，主文件不超过段大小；
+// 段大小记录在文件头里，用不拆分的配置重新打开仍能跨段读出全部块，改名、截断、删除都会带上段文件
+fn test_extents() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("extents")?;
+    let path = dir.join("a.tbl");
+    let fm = FileManager::new(FileManagerConfig {
+        extent_size_bytes: 4 * 4096,
+        preallocate_bytes: 0,
+        ..FileManagerConfig::default()
+    });
+    fm.create_table_file(&path)?;
+    let mut handle = fm.open_file(&path)?;
+    assert_eq!(handle.extent_blocks(), 4);
+    let mut blocks = Vec::new();
+    for _ in 0..9 {
+        blocks.push(handle.allocate_block()?);
+    }
+    for &block in &blocks {
+        handle.write_block(block, &vec![block.get() as u8; 4096])?;
+    }
+    handle.flush()?;
+    assert!(dir.join("a.tbl.1").exists() && dir.join("a.tbl.2").exists());
+    assert!(!dir.join("a.tbl.3").exists());
+    assert_eq!(std::fs::metadata(&path)?.len(), 4 * 4096);
+    assert_eq!(handle.stat()?.file_size_bytes, 10 * 4096);
+    drop(handle);
+
+    let plain = FileManager::new(FileManagerConfig::default());
+    let mut handle = plain.open_file(&path)?;
+    let mut buffer = vec![0u8; 4096];
+    for &block in &blocks {
+        handle.read_block(block, &mut buffer)?;
+        assert!(buffer.iter().all(|&b| b == block.get() as u8));
+    }
+    drop(handle);
+
+    let renamed = dir.join("b.tbl");
+    plain.rename_table(&path, &renamed)?;
+    assert!(!dir.join("a.tbl.1").exists() && dir.join("b.tbl.2").exists());
+    let mut handle = plain.open_file(&renamed)?;
+    handle.read_block(BlockId::new(6), &mut buffer)?;
+    assert!(buffer.iter().all(|&b| b == 6));
+    handle.truncate()?;
+    assert!(!dir.join("b.tbl.1").exists());
+    drop(handle);
+    plain.delete_file(&renamed)?;
+    assert!(!renamed.exists());
+
+    let too_small = FileManager::new(FileManagerConfig {
+        extent_size_bytes: 4096,
+        ..FileManagerConfig::default()
+    });
+    let err = too_small.create_table_file(dir.join("x.tbl")).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    println!("扩展段验证通过");
+    Ok(())
+}
@@ -0,0 +1,1316 @@
+use std::error::Error;
+use std::io::ErrorKind;
+use std::sync::{Arc, Mutex};
+
+use super::{new_table_file, test_dir};
+use crate::fm::{BlockId, FileManager, FileManagerConfig, WriteInterceptor};
+use crate::mm::buffer_manager::{Cache, MemoryManager, ReplacementPolicy};
+use crate::mm::page::{InsertError, Page, PageInconsistency};
+use crate::mm::page_compact::PageCompact;
+use crate::mm::page_header::{PageHeader, NO_FREE_SLOT, PAGE_FORMAT_VERSION, SLOT_ENTRY_BYTES};
+use crate::mm::page_ops::PageOps;
+use crate::mm::{BufferManager, SharedBufferManager};
+
+// Memory Manager 相关测试：依次运行本文件中的各项检查
+pub fn test_mm() -> Result<(), Box<dyn Error>> {
+    println!("=== 开始 Memory Manager 测试 ===");
+    test_snapshot_page_lifecycle()?;
+    test_frame_size()?;
+    test_free_space_after_compact()?;
+    test_malformed_frames()?;
+    test_page_bloom_filter()?;
+    test_memory_manager_evict_all()?;
+    test_cache_watermarks()?;
+    test_record_framing()?;
+    test_dump_block()?;
+    test_recompute_free_bytes()?;
+    test_shrink_slot_dir()?;
+    test_dirty_page_threshold()?;
+    test_page_format_version()?;
+    test_page_guard_accessors()?;
+    test_page_guard_auto_dirty()?;
+    test_insert_record_at()?;
+    test_page_repair()?;
+    test_pin_count()?;
+    test_invalidate_plans()?;
+    test_read_write_page()?;
+    test_clock_hand_after_remove()?;
+    test_page_split()?;
+    test_cache_drain()?;
+    test_flush_free_bytes()?;
+    test_flush_and_evict()?;
+    test_slot_dir_checksum()?;
+    test_iter_records()?;
+    test_flush_progress()?;
+    test_insert_overlap_guard()?;
+    test_buffer_resize()?;
+    test_live_and_dead_bytes()?;
+    test_shared_buffer_manager()?;
+    test_slot_count_limit()?;
+    test_flush_sync_barrier()?;
+    test_cache_get_or_insert_with()?;
+    test_free_slot_list()?;
+    test_page_verify()?;
+    test_slot_location()?;
+    test_dirty_page_ids()?;
+    test_try_insert()?;
+    test_can_fit()?;
+    test_wide_slots()?;
+    println!("=== Memory Manager 测试完成 ===");
+    Ok(())
+}
+
+// 快照与页面分配/释放：快照开始后释放的页面（无论是否在缓冲区中）仍能按原内容读到，
+// 快照开始后新分配的数据页在快照视图中不是数据页
+fn test_snapshot_page_lifecycle() -> Result<(), Box<dyn Error>> {
+    let mut buf_mgr = BufferManager::new(new_table_file("snapshot_page_lifecycle")?, 4);
+    let cached = buf_mgr.allocate_data_page()?;
+    let evicted = buf_mgr.allocate_data_page()?;
+    for (block, value) in [(cached, 1u8), (evicted, 2u8)] {
+        let mut page = buf_mgr.fetch(block)?;
+        page[100] = value;
+    }
+    buf_mgr.flush_and_evict(evicted)?;
+    assert!(buf_mgr.contains(cached));
+    assert!(!buf_mgr.contains(evicted));
+
+    let snapshot = buf_mgr.begin_snapshot();
+    buf_mgr.free_page(cached)?;
+    buf_mgr.free_page(evicted)?;
+    let added = buf_mgr.allocate_data_page()?;
+    assert_eq!(buf_mgr.fetch_snapshot(snapshot, cached)?[100], 1);
+    assert_eq!(buf_mgr.fetch_snapshot(snapshot, evicted)?[100], 2);
+    assert!(Page::load(&buf_mgr.fetch_snapshot(snapshot, added)?).is_err());
+    assert!(Page::load(&buf_mgr.fetch(added)?).is_ok());
+    buf_mgr.end_snapshot(snapshot);
+    println!("快照下的页面分配/释放验证通过");
+    Ok(())
+}
+
+// 缓冲帧大小取自文件的块大小：8 KB 块的文件，帧为 8192 字节，
+// 写到帧末尾的字节换出后原样落在磁盘上的对应块中
+fn test_frame_size() -> Result<(), Box<dyn Error>> {
+    const BLOCK_SIZE: usize = 8192;
+    let path = test_dir("frame_size")?.join("t.tbl");
+    let file_manager = FileManager::new(FileManagerConfig {
+        block_size: BLOCK_SIZE,
+        ..FileManagerConfig::default()
+    });
+    file_manager.create_table_file(&path)?;
+    let mut buf_mgr = BufferManager::new(file_manager.open_file(&path)?, 2);
+    let block = buf_mgr.allocate_data_page()?;
+    {
+        let mut frame = buf_mgr.fetch(block)?;
+        assert_eq!(frame.len(), BLOCK_SIZE);
+        frame[BLOCK_SIZE - 1] = 0x7e;
+    }
+    buf_mgr.flush_and_evict(block)?;
+    assert!(!buf_mgr.contains(block));
+
+    let mut buffer = vec![0u8; BLOCK_SIZE];
+    buf_mgr.handle.read_block(block, &mut buffer)?;
+    assert_eq!(buffer[BLOCK_SIZE - 1], 0x7e);
+    assert_eq!(buf_mgr.fetch(block)?.len(), BLOCK_SIZE);
+    println!("8 KB 缓冲帧验证通过");
+    Ok(())
+}
+
+// 紧缩预测：free_space_after_compact 不修改页面，其结果与实际紧缩后的 free_bytes 一致
+fn test_free_space_after_compact() -> Result<(), Box<dyn Error>> {
+    let mut page = Page::new(4096);
+    let slots = [10usize, 200, 35, 1000, 7]
+        .iter()
+        .map(|&len| page.insert_record(&vec![len as u8; len]))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(page.free_space_after_compact(), page.header.free_bytes);
+
+    page.delete_record(slots[1])?;
+    page.delete_record(slots[3])?;
+    let before = page.header.free_bytes;
+    let predicted = page.free_space_after_compact();
+    assert_eq!(page.header.free_bytes, before);
+    assert!(predicted >= before + 1200);
+    page.compact()?;
+    assert_eq!(page.header.free_bytes, predicted);
+    assert_eq!(page.get_record(slots[4])?, vec![7u8; 7]);
+    println!("紧缩后可用空间预测验证通过");
+    Ok(())
+}
+
+// 畸形帧：槽数量超出页面容量、槽指向数据区之外、帧比页头还短时，Page::load 返回 InvalidData
+// 而不是越界 panic；槽目录校验和按篡改后的内容重算，确保触发的是边界检查
+fn test_malformed_frames() -> Result<(), Box<dyn Error>> {
+    const PAGE_SIZE: usize = 256;
+    let mut page = Page::new(PAGE_SIZE);
+    page.insert_record(b"hello")?;
+    page.insert_record(b"world")?;
+    let mut frame = vec![0u8; PAGE_SIZE];
+    page.flush(&mut frame)?;
+    assert!(Page::load(&frame).is_ok());
+
+    let mut huge_count = frame.clone();
+    let mut header = PageHeader::from_bytes(&huge_count)?;
+    header.slot_count = 60000;
+    header.to_bytes(&mut huge_count)?;
+    let kind = Page::load(&huge_count).err().map(|err| err.kind());
+    assert_eq!(kind, Some(ErrorKind::InvalidData));
+
+    // 槽 1 的偏移指向槽目录内部
+    let mut bad_offset = frame.clone();
+    let dir_base = PAGE_SIZE - 2 * SLOT_ENTRY_BYTES;
+    let entry = dir_base + SLOT_ENTRY_BYTES;
+    bad_offset[entry..entry + 2].copy_from_slice(&(dir_base as u16).to_le_bytes());
+    let mut header = PageHeader::from_bytes(&bad_offset)?;
+    header.slot_dir_checksum = PageHeader::slot_dir_checksum_of(&bad_offset[dir_base..]);
+    header.to_bytes(&mut bad_offset)?;
+    let kind = Page::load(&bad_offset).err().map(|err| err.kind());
+    assert_eq!(kind, Some(ErrorKind::InvalidData));
+
+    assert!(Page::load(&frame[..PageHeader::SIZE - 1]).is_err());
+    println!("畸形帧检查验证通过");
+    Ok(())
+}
+
+// 页内布隆过滤器：已插入的键一定命中，不存在的键大多被排除；删除记录后按剩余记录重建，
+// 过滤器随页面序列化保存
+fn test_page_bloom_filter() -> Result<(), Box<dyn Error>> {
+    let key = |i: usize| format!("key-{}", i).into_bytes();
+    let mut page = Page::new(4096);
+    page.enable_bloom();
+    let slots = (0..20)
+        .map(|i| page.insert_record(&key(i)))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert!((0..20).all(|i| page.may_contain(Page::key_hash(&key(i)))));
+    let false_positives = (100..1100)
+        .filter(|&i| page.may_contain(Page::key_hash(&key(i))))
+        .count();
+    assert!(false_positives < 200, "误判 {} / 1000", false_positives);
+
+    let mut frame = vec![0u8; 4096];
+    page.flush(&mut frame)?;
+    let mut loaded = Page::load(&frame)?;
+    assert!((0..20).all(|i| loaded.may_contain(Page::key_hash(&key(i)))));
+
+    // 删除全部记录后过滤器为空，任何键都判定为不存在
+    for &slot in &slots {
+        loaded.delete_record(slot)?;
+    }
+    assert!((0..1100).all(|i| !loaded.may_contain(Page::key_hash(&key(i)))));
+    println!("页内布隆过滤器验证通过");
+    Ok(())
+}
+
+// 内存压力下释放全部缓存：evict_all 先写回脏页再丢弃帧，各字符串缓存清空，
+// 之后的内存估算为 0，页面重新从磁盘加载且内容不丢
+fn test_memory_manager_evict_all() -> Result<(), Box<dyn Error>> {
+    let mut handle = new_table_file("memory_manager_evict_all")?;
+    let block = handle.allocate_block()?;
+    let block_size = handle.block_size();
+    let mut mem_mgr = MemoryManager::new(handle, 4, 4, 4, 4, ReplacementPolicy::LRU);
+    mem_mgr.query_cache.insert("select".into(), "plan".into());
+    mem_mgr.dict_cache.insert("t".into(), "schema".into());
+    mem_mgr.log_buffer.insert("1".into(), "insert".into());
+    {
+        let mut page = mem_mgr.fetch_page(block)?;
+        page[0] = 9;
+    }
+    // 一个缓冲帧，加上三个缓存条目的键与值字节数
+    assert_eq!(mem_mgr.approx_memory_bytes(), block_size + 10 + 7 + 7);
+
+    mem_mgr.evict_all()?;
+    assert_eq!(mem_mgr.approx_memory_bytes(), 0);
+    assert!(mem_mgr.query_cache.is_empty());
+    assert!(mem_mgr.dict_cache.is_empty());
+    assert!(mem_mgr.log_buffer.is_empty());
+    assert!(!mem_mgr.data_buffer.contains(block));
+
+    let mut buffer = vec![0u8; block_size];
+    mem_mgr.data_buffer.handle.read_block(block, &mut buffer)?;
+    assert_eq!(buffer[0], 9);
+    assert_eq!(mem_mgr.fetch_page(block)?[0], 9);
+    assert!(mem_mgr.data_buffer.contains(block));
+    println!("MemoryManager::evict_all 验证通过");
+    Ok(())
+}
+
+// 缓存水位线：条目数达到高水位后，下一次插入先一次性淘汰到低水位；
+// LRU 下被淘汰的是最久未使用的条目。默认水位仍是满容量时逐条替换
+fn test_cache_watermarks() -> Result<(), Box<dyn Error>> {
+    for policy in [ReplacementPolicy::LRU, ReplacementPolicy::CLOCK] {
+        let mut cache: Cache<u32> = Cache::with_watermarks(8, 4, 8, policy.clone());
+        assert_eq!(cache.capacity(), 8);
+        for i in 0..8 {
+            cache.insert(format!("k{}", i), i);
+        }
+        assert_eq!(cache.len(), 8);
+        cache.get("k0");
+        cache.insert("x".into(), 100);
+        assert_eq!(cache.len(), 5);
+        if matches!(policy, ReplacementPolicy::LRU) {
+            for evicted in ["k1", "k2", "k3", "k4"] {
+                assert!(cache.get(evicted).is_none());
+            }
+            assert_eq!(cache.get("k0"), Some(&0));
+        }
+        for i in 0..3 {
+            cache.insert(format!("y{}", i), i);
+        }
+        assert_eq!(cache.len(), 8);
+
+        let mut default: Cache<u32> = Cache::new(2, policy);
+        for i in 0..5 {
+            default.insert(format!("k{}", i), i);
+            assert!(default.len() <= 2);
+        }
+        assert_eq!(default.len(), 2);
+    }
+    println!("缓存水位线验证通过");
+    Ok(())
+}
+
+// 记录长度前缀：槽目录中的长度被改短（校验和同步重算）后，带前缀的页面读取时报 InvalidData，
+// 不带前缀的页面照旧返回截短的字节
+fn test_record_framing() -> Result<(), Box<dyn Error>> {
+    const PAGE_SIZE: usize = 4096;
+    for framed in [false, true] {
+        let mut page = Page::new(PAGE_SIZE);
+        if framed {
+            page.enable_framing()?;
+        }
+        let slot = page.insert_record(b"hello")?;
+        page.insert_record(b"world")?;
+        let mut frame = vec![0u8; PAGE_SIZE];
+        page.flush(&mut frame)?;
+        assert_eq!(Page::load(&frame)?.get_record(slot)?, b"hello");
+
+        let dir_base = PAGE_SIZE - 2 * SLOT_ENTRY_BYTES;
+        let len_at = dir_base + slot as usize * SLOT_ENTRY_BYTES + 2;
+        let len = u16::from_le_bytes([frame[len_at], frame[len_at + 1]]);
+        frame[len_at..len_at + 2].copy_from_slice(&(len - 1).to_le_bytes());
+        let mut header = PageHeader::from_bytes(&frame)?;
+        header.slot_dir_checksum = PageHeader::slot_dir_checksum_of(&frame[dir_base..]);
+        header.to_bytes(&mut frame)?;
+
+        let corrupted = Page::load(&frame)?;
+        match corrupted.get_record(slot) {
+            Ok(record) => {
+                assert!(!framed, "带长度前缀的页面没有发现槽长度被篡改");
+                assert_eq!(record, b"hell");
+            }
+            Err(err) => {
+                assert!(framed);
+                assert_eq!(err.kind(), ErrorKind::InvalidData);
+            }
+        }
+    }
+    println!("记录长度前缀验证通过");
+    Ok(())
+}
+
+// 块内容转储：数据页列出槽数与每个槽的状态和字节预览，已释放的块标注为非数据页
+fn test_dump_block() -> Result<(), Box<dyn Error>> {
+    let mut handle = new_table_file("dump_block")?;
+    let block_size = handle.block_size();
+    let block = handle.allocate_block()?;
+    let mut page = Page::new(block_size);
+    page.insert_record(b"hello")?;
+    page.insert_record(&[7u8; 40])?;
+    page.delete_record(0)?;
+    let mut frame = vec![0u8; block_size];
+    page.flush(&mut frame)?;
+    handle.write_block(block, &frame)?;
+
+    let dump = handle.dump_block(block)?;
+    assert!(dump.contains("slot_count=2"));
+    assert!(dump.contains("slot 0: next_free="));
+    assert!(dump.contains("deleted"));
+    assert!(dump.contains("len=40 live [07 07"));
+    assert!(dump.contains(" ..]"));
+
+    let freed = handle.allocate_block()?;
+    handle.release_block(freed)?;
+    assert!(handle.dump_block(freed)?.contains("not a data page"));
+    println!("块内容转储验证通过");
+    Ok(())
+}
+
+// 可用字节重算：被破坏的 free_bytes 按数据区与槽目录的实际占用恢复，compact 之后也保持一致
+fn test_recompute_free_bytes() -> Result<(), Box<dyn Error>> {
+    let page_size = 1024;
+    let mut page = Page::new(page_size);
+    page.insert_record(b"abc")?;
+    page.insert_record(b"defgh")?;
+    let expected = page_size - PageHeader::SIZE - (3 + 5) - 2 * SLOT_ENTRY_BYTES;
+    page.header.free_bytes = 7;
+    page.recompute_free_bytes();
+    assert_eq!(page.header.free_bytes as usize, expected);
+
+    page.delete_record(0)?;
+    page.compact()?;
+    let expected = page_size - PageHeader::SIZE - 5 - 2 * SLOT_ENTRY_BYTES;
+    assert_eq!(page.header.free_bytes as usize, expected);
+    println!("可用字节重算验证通过");
+    Ok(())
+}
+
+// 槽目录收缩：只裁掉末尾的已删除槽并归还其目录空间，中间的空洞保留且其后的记录仍按原槽号可读
+fn test_shrink_slot_dir() -> Result<(), Box<dyn Error>> {
+    let mut page = Page::new(1024);
+    for record in [b"a", b"b", b"c", b"d"] {
+        page.insert_record(record)?;
+    }
+    for slot in 1..4 {
+        page.delete_record(slot)?;
+    }
+    let free_bytes = page.header.free_bytes as usize;
+    assert_eq!(page.shrink_slot_dir(), 3);
+    assert_eq!(page.slots.len(), 1);
+    assert_eq!(page.header.free_slot_head, NO_FREE_SLOT);
+    assert_eq!(
+        page.header.free_bytes as usize,
+        free_bytes + 3 * SLOT_ENTRY_BYTES
+    );
+
+    let mut page = Page::new(1024);
+    for record in [b"a", b"b", b"c"] {
+        page.insert_record(record)?;
+    }
+    page.delete_record(0)?;
+    assert_eq!(page.shrink_slot_dir(), 0);
+    page.delete_record(2)?;
+    assert_eq!(page.shrink_slot_dir(), 1);
+    assert_eq!(page.slots.len(), 2);
+    assert_eq!(page.slots[0].1, 0);
+    assert_eq!(page.header.free_slot_head, 0);
+    assert_eq!(page.get_record(1)?, b"b");
+    println!("槽目录收缩验证通过");
+    Ok(())
+}
+
+// 脏页阈值：脏页数超过阈值时，在任何显式 flush 之前就按 LRU 顺序写回最旧的脏页
+fn test_dirty_page_threshold() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("dirty_page_threshold")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut handle = fm.open_file(&path)?;
+    let block_size = handle.block_size();
+    let mut blocks = Vec::new();
+    for _ in 0..5 {
+        blocks.push(handle.allocate_block()?);
+    }
+    handle.flush()?;
+
+    let mut bm = BufferManager::new(handle, 8);
+    bm.set_dirty_page_threshold(Some(2));
+    for &block in &blocks {
+        bm.fetch(block)?[100] = 0x5a;
+        bm.mark_dirty(block);
+    }
+    let raw = std::fs::read(&path)?;
+    let written: Vec<_> = blocks
+        .iter()
+        .copied()
+        .filter(|block| raw[block.get() as usize * block_size + 100] == 0x5a)
+        .collect();
+    assert_eq!(written, blocks[..3].to_vec());
+    println!("脏页阈值提前写回验证通过");
+    Ok(())
+}
+
+// 页面格式版本：新页面带当前版本号并能正常加载，伪造成未知版本的页面加载时报 InvalidData
+fn test_page_format_version() -> Result<(), Box<dyn Error>> {
+    let mut page = Page::new(512);
+    assert_eq!(page.header.version, PAGE_FORMAT_VERSION);
+    page.insert_record(b"x")?;
+    let mut frame = vec![0u8; 512];
+    page.flush(&mut frame)?;
+    assert_eq!(Page::load(&frame)?.header.version, PAGE_FORMAT_VERSION);
+
+    page.header.version = PAGE_FORMAT_VERSION + 1;
+    page.flush(&mut frame)?;
+    let err = Page::load(&frame).err().map(|err| err.kind());
+    assert_eq!(err, Some(ErrorKind::InvalidData));
+    println!("页面格式版本验证通过");
+    Ok(())
+}
+
+// PageGuard 访问器：guard 能报告自己对应的块号和脏标记，通过 guard 标脏的修改在 flush_all 后落盘
+fn test_page_guard_accessors() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("page_guard_accessors")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut handle = fm.open_file(&path)?;
+    let block_size = handle.block_size();
+    let block = handle.allocate_block()?;
+    let mut bm = BufferManager::new(handle, 2);
+    {
+        let mut guard = bm.fetch(block)?;
+        assert_eq!(guard.block_id(), block);
+        assert!(!guard.is_dirty());
+        guard[200] = 3;
+        guard.mark_dirty();
+        assert!(guard.is_dirty());
+    }
+    bm.flush_all()?;
+    let raw = std::fs::read(&path)?;
+    assert_eq!(raw[block.get() as usize * block_size + 200], 3);
+    println!("PageGuard 块号与脏标记验证通过");
+    Ok(())
+}
+
+// 通过 guard 写入自动标脏：经 DerefMut 修改过的页面在 guard 释放时自动标脏并在 flush_all 后落盘，
+// 只读访问的页面不会被标脏
+fn test_page_guard_auto_dirty() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("page_guard_auto_dirty")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut handle = fm.open_file(&path)?;
+    let block_size = handle.block_size();
+    let written = handle.allocate_block()?;
+    let read_only = handle.allocate_block()?;
+    let mut bm = BufferManager::new(handle, 4);
+    bm.fetch(written)?[300] = 9;
+    assert_eq!(bm.fetch(read_only)?[300], 0);
+    assert!(bm.is_dirty(written));
+    assert!(!bm.is_dirty(read_only));
+
+    bm.flush_all()?;
+    let raw = std::fs::read(&path)?;
+    assert_eq!(raw[written.get() as usize * block_size + 300], 9);
+    println!("PageGuard 写入自动标脏验证通过");
+    Ok(())
+}
+
+// 指定槽号插入：目标槽号超出目录时用空洞补齐（计入可用字节并挂到空闲槽链表），
+// 已占用的槽号报 AlreadyExists；之后的普通插入优先复用这些空洞，且序列化后依然成立
+fn test_insert_record_at() -> Result<(), Box<dyn Error>> {
+    let mut page = Page::new(512);
+    let free_bytes = page.header.free_bytes as usize;
+    page.insert_record_at(5, b"five")?;
+    assert_eq!(page.slots.len(), 6);
+    assert!(page.slots[..5].iter().all(|&(_, len)| len == 0));
+    assert_eq!(page.header.free_slot_head, 4);
+    assert_eq!(page.get_record(5)?, b"five");
+    assert_eq!(
+        page.header.free_bytes as usize,
+        free_bytes - 4 - 6 * SLOT_ENTRY_BYTES
+    );
+    assert_eq!(
+        page.insert_record_at(5, b"x").unwrap_err().kind(),
+        ErrorKind::AlreadyExists
+    );
+
+    page.insert_record_at(2, b"two")?;
+    assert_eq!(page.insert_record(b"six")?, 4);
+    let mut frame = vec![0u8; 512];
+    page.flush(&mut frame)?;
+    let mut loaded = Page::load(&frame)?;
+    assert_eq!(loaded.header.slot_count, 6);
+    assert_eq!(loaded.get_record(2)?, b"two");
+    assert_eq!(loaded.insert_record(b"x")?, 3);
+    assert_eq!(loaded.insert_record(b"y")?, 1);
+    println!("指定槽号插入验证通过");
+    Ok(())
+}
+
+// 槽目录修复：健康页面上 repair 不做任何修改；slot_count 与槽目录不一致、记录越界时，
+// repair 修正 slot_count 并清除越界的槽，之后完好的记录仍能正确读出
+fn test_page_repair() -> Result<(), Box<dyn Error>> {
+    let mut page = Page::new(512);
+    page.insert_record(b"aa")?;
+    page.insert_record(b"bbb")?;
+    assert!(page.repair().is_clean());
+
+    page.header.slot_count = 9;
+    page.slots[1].1 = 200;
+    let report = page.repair();
+    assert_eq!(report.slot_count, Some((9, 2)));
+    assert_eq!(report.dropped_slots, vec![1]);
+    assert_eq!(report.live_slots, 1);
+    assert_eq!(page.get_record(0)?, b"aa");
+    assert!(page.repair().is_clean());
+    println!("槽目录修复验证通过");
+    Ok(())
+}
+
+// 驻留与 pin 计数查询：未载入的块既不驻留也没有 pin 计数；两次 pin 后计数为 2，unpin 一次后为 1，
+// guard 释放后计数回到原值
+fn test_pin_count() -> Result<(), Box<dyn Error>> {
+    let mut handle = new_table_file("pin_count")?;
+    let block = handle.allocate_block()?;
+    let mut bm = BufferManager::new(handle, 2);
+    assert!(!bm.contains(block));
+    assert_eq!(bm.pin_count(block), None);
+
+    bm.pin(block)?;
+    bm.pin(block)?;
+    assert!(bm.contains(block));
+    assert_eq!(bm.pin_count(block), Some(2));
+    bm.unpin(block);
+    assert_eq!(bm.pin_count(block), Some(1));
+    drop(bm.fetch(block)?);
+    assert_eq!(bm.pin_count(block), Some(1));
+    println!("驻留与 pin 计数查询验证通过");
+    Ok(())
+}
+
+// 查询计划失效：invalidate_plans_for 只移除引用该表的计划，其他表的计划保留
+fn test_invalidate_plans() -> Result<(), Box<dyn Error>> {
+    let handle = new_table_file("invalidate_plans")?;
+    let mut mem_mgr = MemoryManager::new(handle, 4, 8, 4, 4, ReplacementPolicy::CLOCK);
+    for query in [
+        "SELECT * FROM users",
+        "SELECT id FROM users WHERE x = 1",
+        "SELECT * FROM orders",
+        "SELECT 1",
+    ] {
+        mem_mgr.query_cache.insert(query.into(), "plan".into());
+    }
+    mem_mgr.invalidate_plans_for("users");
+    assert_eq!(mem_mgr.query_cache.len(), 2);
+    assert!(mem_mgr.query_cache.get("SELECT * FROM users").is_none());
+    assert!(mem_mgr.query_cache.get("SELECT * FROM orders").is_some());
+    assert!(mem_mgr.query_cache.get("SELECT 1").is_some());
+    println!("查询计划按表失效验证通过");
+    Ok(())
+}
+
+// 按页读写：write_page 写入的页面重新打开文件后经 read_page 读回，记录内容与可用字节一致
+fn test_read_write_page() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("read_write_page")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut handle = fm.open_file(&path)?;
+    let block = handle.allocate_block()?;
+    let mut page = Page::new(handle.block_size());
+    let hello = page.insert_record(b"hello")?;
+    let world = page.insert_record(b"world!")?;
+    handle.write_page(block, &page)?;
+    drop(handle);
+
+    let loaded = fm.open_file(&path)?.read_page(block)?;
+    assert_eq!(loaded.get_record(hello)?, b"hello");
+    assert_eq!(loaded.get_record(world)?, b"world!");
+    assert_eq!(loaded.header.free_bytes, page.header.free_bytes);
+    println!("按页读写验证通过");
+    Ok(())
+}
+
+// CLOCK 指针维护：移除指针之前的条目后指针仍指向原条目，后续淘汰按环上的顺序进行，不会跳过条目
+fn test_clock_hand_after_remove() -> Result<(), Box<dyn Error>> {
+    let mut cache: Cache<String> = Cache::new(4, ReplacementPolicy::CLOCK);
+    for key in ["a", "b", "c", "d"] {
+        cache.insert(key.into(), key.into());
+    }
+    // 第一轮清除所有访问位后淘汰 a，指针停在 b
+    cache.insert("e".into(), "e".into());
+    assert!(cache.get("a").is_none());
+    // b 未被访问，直接淘汰
+    cache.insert("f".into(), "f".into());
+    assert!(cache.get("b").is_none());
+    // 给 c 一次机会：清除 c 的访问位后淘汰 d，指针停在 e（c 之后）
+    cache.get("c");
+    cache.insert("g".into(), "g".into());
+    assert!(cache.get("d").is_none());
+
+    // 移除指针之前的 c，指针应仍指向 e
+    cache.remove("c");
+    cache.insert("h".into(), "h".into());
+    // e、f、g、h 都被访问过：指针从 e 开始转一圈清除访问位后淘汰 e
+    cache.insert("i".into(), "i".into());
+    assert!(cache.get("e").is_none());
+    for key in ["f", "g", "h", "i"] {
+        assert!(cache.get(key).is_some());
+    }
+    println!("CLOCK 指针维护验证通过");
+    Ok(())
+}
+
+// 页面分裂：把写满的页面按字节数大致对半分到新页面，两页记录合起来与原记录集合相同且不重复；
+// 新页面继承分帧与布隆过滤器标志，两页的可用字节与重算结果一致
+fn test_page_split() -> Result<(), Box<dyn Error>> {
+    let mut page = Page::new(4096);
+    page.enable_framing()?;
+    page.enable_bloom();
+    let mut records = Vec::new();
+    for i in 0.. {
+        let record = format!("record-{:04}-{}", i, "x".repeat(i % 37));
+        if page.insert_record(record.as_bytes()).is_err() {
+            break;
+        }
+        records.push(record);
+    }
+    page.delete_record(3)?;
+    records.remove(3);
+
+    let mut other = Page::new(4096);
+    assert!(!page.split(&mut other)?.is_empty());
+    let mut found = Vec::new();
+    for part in [&page, &other] {
+        for slot in 0..part.slots.len() as u16 {
+            if let Ok(record) = part.get_record(slot) {
+                found.push(String::from_utf8(record.to_vec())?);
+            }
+        }
+    }
+    found.sort();
+    records.sort();
+    assert_eq!(found, records);
+    let (left, right) = (page.data.len(), other.data.len());
+    assert!(left * 3 > right && right * 3 > left);
+    assert!(other.header.has_framing() && other.header.has_bloom());
+    for part in [&mut page, &mut other] {
+        let free_bytes = part.header.free_bytes;
+        part.recompute_free_bytes();
+        assert_eq!(part.header.free_bytes, free_bytes);
+    }
+    println!("页面分裂验证通过");
+    Ok(())
+}
+
+// 缓存枚举与取出：keys 按 LRU 顺序列出 key 且不影响访问记录，drain 按同样顺序取出全部条目并清空缓存，
+// 之后缓存可以照常使用
+fn test_cache_drain() -> Result<(), Box<dyn Error>> {
+    let mut cache: Cache<String> = Cache::new(8, ReplacementPolicy::CLOCK);
+    for key in ["a", "b", "c"] {
+        cache.insert(key.into(), key.to_uppercase());
+    }
+    cache.get("a");
+    let keys: Vec<_> = cache.keys().cloned().collect();
+    assert_eq!(keys, vec!["b", "c", "a"]);
+    assert_eq!(cache.keys().count(), 3);
+
+    let drained = cache.drain();
+    assert_eq!(
+        drained,
+        vec![
+            ("b".to_string(), "B".to_string()),
+            ("c".to_string(), "C".to_string()),
+            ("a".to_string(), "A".to_string())
+        ]
+    );
+    assert!(cache.is_empty());
+    assert_eq!(cache.keys().count(), 0);
+    cache.insert("z".into(), "Z".into());
+    assert_eq!(cache.len(), 1);
+    println!("缓存枚举与取出验证通过");
+    Ok(())
+}
+
+// flush 写出正确的可用字节：内存中的 free_bytes 偏离实际时，flush 按数据区与槽目录重新计算后写出，
+// 重新加载的页面恰好还能再插入按实际空间算出的记录条数
+fn test_flush_free_bytes() -> Result<(), Box<dyn Error>> {
+    let page_size = 4096;
+    let mut page = Page::new(page_size);
+    for _ in 0..10 {
+        page.insert_record(&[7u8; 100])?;
+    }
+    page.delete_record(4)?;
+    page.header.free_bytes = 5;
+    let mut frame = vec![0u8; page_size];
+    page.flush(&mut frame)?;
+
+    let mut loaded = Page::load(&frame)?;
+    // 已删除记录的数据在紧缩前仍占用数据区；第一条插入复用空槽，不再占用槽目录空间
+    let free = page_size - PageHeader::SIZE - 10 * 100 - 10 * SLOT_ENTRY_BYTES;
+    let expected = 1 + (free - 100) / (100 + SLOT_ENTRY_BYTES);
+    let mut inserted = 0;
+    while loaded.insert_record(&[1u8; 100]).is_ok() {
+        inserted += 1;
+    }
+    assert_eq!(inserted, expected);
+    loaded.flush(&mut frame)?;
+    assert!(Page::load(&frame)?.repair().is_clean());
+    println!("flush 可用字节重算验证通过");
+    Ok(())
+}
+
+// 写回并逐出单个块：仍被 pin 的块拒绝逐出；脏页逐出前先写回，逐出后不再驻留，再次 fetch 读到写回的内容；
+// 不在缓冲区中的块直接返回成功
+fn test_flush_and_evict() -> Result<(), Box<dyn Error>> {
+    let handle = new_table_file("flush_and_evict")?;
+    let mut bm = BufferManager::new(handle, 4);
+    let block = bm.allocate_data_page()?;
+    bm.pin(block)?;
+    assert!(bm.flush_and_evict(block).is_err());
+    bm.unpin(block);
+
+    bm.fetch(block)?[200] = 9;
+    assert!(bm.is_dirty(block));
+    bm.flush_and_evict(block)?;
+    assert!(!bm.contains(block));
+    assert_eq!(bm.fetch(block)?[200], 9);
+    bm.flush_and_evict(BlockId::new(999))?;
+    println!("写回并逐出单个块验证通过");
+    Ok(())
+}
+
+// 槽目录校验和：把一个槽的长度改成仍在数据区范围内的值，只有槽目录校验和能发现，加载时报 InvalidData
+// 并指明是槽目录损坏；数据区的改动不影响槽目录校验；新建的空页面能正常加载
+fn test_slot_dir_checksum() -> Result<(), Box<dyn Error>> {
+    let page_size = 4096;
+    let mut page = Page::new(page_size);
+    for i in 0..5u8 {
+        page.insert_record(&[i; 10])?;
+    }
+    let mut frame = vec![0u8; page_size];
+    page.flush(&mut frame)?;
+    assert!(Page::load(&frame).is_ok());
+
+    // 槽目录的 5 个槽项位于页尾，每项为 2 字节偏移 + 2 字节长度；把槽 2 的长度从 10 改成 8
+    let dir_base = page_size - 5 * SLOT_ENTRY_BYTES;
+    let mut corrupted = frame.clone();
+    corrupted[dir_base + 2 * SLOT_ENTRY_BYTES + 2] = 8;
+    let err = Page::load(&corrupted).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err.to_string().contains("slot directory"));
+
+    let mut data_changed = frame.clone();
+    data_changed[100] ^= 0xff;
+    assert!(Page::load(&data_changed).is_ok());
+
+    let mut empty = vec![0u8; page_size];
+    PageHeader::new(page_size).to_bytes(&mut empty)?;
+    assert!(Page::load(&empty).is_ok());
+    println!("槽目录校验和验证通过");
+    Ok(())
+}
+
+// 遍历存活记录：iter_records 跳过已删除的槽，按槽号给出每条记录的内容；分帧页面给出去掉长度前缀的内容
+fn test_iter_records() -> Result<(), Box<dyn Error>> {
+    let mut page = Page::new(4096);
+    for i in 0..5 {
+        page.insert_record(format!("v{}", i).as_bytes())?;
+    }
+    page.delete_record(1)?;
+    page.delete_record(3)?;
+    let live: Vec<_> = page.iter_records().collect();
+    assert_eq!(
+        live,
+        vec![(0, &b"v0"[..]), (2, &b"v2"[..]), (4, &b"v4"[..])]
+    );
+
+    let mut framed = Page::new(4096);
+    framed.enable_framing()?;
+    framed.insert_record(b"abc")?;
+    let live: Vec<_> = framed.iter_records().collect();
+    assert_eq!(live, vec![(0, &b"abc"[..])]);
+    println!("遍历存活记录验证通过");
+    Ok(())
+}
+
+// 带进度的写回：每写回一个脏页回调一次 (已写回数, 脏页总数)，计数单调递增；没有脏页时不回调
+fn test_flush_progress() -> Result<(), Box<dyn Error>> {
+    let mut handle = new_table_file("flush_progress")?;
+    let mut blocks = Vec::new();
+    for _ in 0..6 {
+        blocks.push(handle.allocate_block()?);
+    }
+    let mut bm = BufferManager::new(handle, 8);
+    for &block in &blocks[..4] {
+        bm.fetch(block)?[100] = 1;
+    }
+    for &block in &blocks[4..] {
+        bm.fetch(block)?;
+    }
+
+    let mut calls = Vec::new();
+    bm.flush_all_with_progress(|flushed, total| calls.push((flushed, total)))?;
+    assert_eq!(calls, vec![(1, 4), (2, 4), (3, 4), (4, 4)]);
+    let mut count = 0;
+    bm.flush_all_with_progress(|_, _| count += 1)?;
+    assert_eq!(count, 0);
+    println!("带进度的写回验证通过");
+    Ok(())
+}
+
+// 插入时的重叠检查：free_bytes 被改大时，会让数据区压到槽目录的插入仍报 InvalidData，页面保持不变；
+// 放得下的记录照常插入，序列化后可以正确读出
+fn test_insert_overlap_guard() -> Result<(), Box<dyn Error>> {
+    let mut page = Page::new(256);
+    page.insert_record(&[1u8; 100])?;
+    page.header.free_bytes = 1000;
+    let err = page.insert_record(&[2u8; 150]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(page.slots.len(), 1);
+    assert_eq!(page.data.len(), 100);
+
+    page.insert_record(&[3u8; 50])?;
+    let mut frame = vec![0u8; 256];
+    page.flush(&mut frame)?;
+    assert_eq!(Page::load(&frame)?.get_record(1)?, &[3u8; 50][..]);
+    println!("数据区与槽目录重叠检查验证通过");
+    Ok(())
+}
+
+// 缓冲区扩容：扩容后已有帧（包括仍被 pin 的帧）的内容、脏标记和 pin 计数都保留，
+// 新增的帧可以容纳更多块而不替换原有帧；容量只能增大
+fn test_buffer_resize() -> Result<(), Box<dyn Error>> {
+    let mut handle = new_table_file("buffer_resize")?;
+    let mut blocks = Vec::new();
+    for _ in 0..3 {
+        blocks.push(handle.allocate_block()?);
+    }
+    let mut bm = BufferManager::new(handle, 2);
+    bm.pin(blocks[0])?;
+    bm.fetch(blocks[0])?[300] = 0x77;
+
+    bm.resize(1024)?;
+    for &block in &blocks[1..] {
+        bm.fetch(block)?;
+    }
+    assert!(blocks.iter().all(|&block| bm.contains(block)));
+    assert_eq!(bm.pin_count(blocks[0]), Some(1));
+    assert!(bm.is_dirty(blocks[0]));
+    let guard = bm.fetch(blocks[0])?;
+    assert_eq!(guard[300], 0x77);
+    assert_eq!(guard.len(), 4096);
+    drop(guard);
+    bm.unpin(blocks[0]);
+    assert!(bm.resize(1).is_err());
+    println!("缓冲区扩容验证通过");
+    Ok(())
+}
+
+// 存活/死空间字节：存活字节 + 死空间 + 可用字节 + 页头 + 保留的槽目录 = 页大小；
+// 死空间包含已删除记录的数据和末尾可裁掉的空槽项，紧缩并收缩槽目录后全部转为可用字节
+fn test_live_and_dead_bytes() -> Result<(), Box<dyn Error>> {
+    let page_size = 1024;
+    let mut page = Page::new(page_size);
+    let mut slots = Vec::new();
+    for i in 0..6 {
+        slots.push(page.insert_record(&vec![i as u8; 50 + i * 10])?);
+    }
+    let accounted = |page: &Page| {
+        let kept_slots = page
+            .slots
+            .iter()
+            .rposition(|&(_, len)| len != 0)
+            .map_or(0, |i| i + 1);
+        page.live_bytes()
+            + page.dead_bytes()
+            + page.header.free_bytes as usize
+            + PageHeader::SIZE
+            + kept_slots * SLOT_ENTRY_BYTES
+    };
+    assert_eq!(page.dead_bytes(), 0);
+    assert_eq!(accounted(&page), page_size);
+
+    for index in [1, 5, 4] {
+        page.delete_record(slots[index])?;
+    }
+    assert_eq!(page.live_bytes(), 50 + 70 + 80);
+    assert_eq!(page.dead_bytes(), 60 + 100 + 90 + 2 * SLOT_ENTRY_BYTES);
+    assert_eq!(accounted(&page), page_size);
+
+    let dead = page.dead_bytes();
+    let free = page.header.free_bytes as usize;
+    page.compact()?;
+    page.shrink_slot_dir();
+    assert_eq!(page.dead_bytes(), 0);
+    assert_eq!(page.header.free_bytes as usize, free + dead);
+    assert_eq!(accounted(&page), page_size);
+    println!("存活/死空间字节验证通过");
+    Ok(())
+}
+
+// 线程共享的缓冲池：两个线程同时通过各自的句柄修改不同的块，结束后 pin 全部释放，
+// 写回后两块的内容都完整落盘
+fn test_shared_buffer_manager() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("shared_buffer_manager")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut handle = fm.open_file(&path)?;
+    let block_size = handle.block_size();
+    let blocks = [handle.allocate_block()?, handle.allocate_block()?];
+    let pool = SharedBufferManager::new(BufferManager::new(handle, 4));
+
+    let workers: Vec<_> = blocks
+        .into_iter()
+        .zip([7u8, 9u8])
+        .map(|(block, value)| {
+            let pool = pool.clone();
+            std::thread::spawn(move || -> std::io::Result<()> {
+                for i in 0..50 {
+                    pool.fetch(block)?.write(|data| data[100 + i] = value)?;
+                }
+                Ok(())
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap()?;
+    }
+    for block in blocks {
+        assert_eq!(pool.lock().unwrap().pin_count(block), Some(0));
+    }
+
+    pool.flush_all()?;
+    let raw = std::fs::read(&path)?;
+    for (block, value) in blocks.into_iter().zip([7u8, 9u8]) {
+        let start = block.get() as usize * block_size + 100;
+        assert!(raw[start..start + 50].iter().all(|&b| b == value));
+    }
+    let guard = pool.fetch(blocks[1])?;
+    assert_eq!(guard.block_id(), blocks[1]);
+    assert_eq!(guard.read(|data| data[120])?, 9);
+
+    // 手动加上的 pin 通过共享句柄释放，只读过的块也能通过共享句柄标脏
+    pool.lock()?.pin(blocks[0])?;
+    assert_eq!(pool.lock()?.pin_count(blocks[0]), Some(1));
+    pool.unpin(blocks[0])?;
+    assert_eq!(pool.lock()?.pin_count(blocks[0]), Some(0));
+    pool.mark_dirty(blocks[0])?;
+    assert!(pool.lock()?.is_dirty(blocks[0]));
+    println!("线程共享缓冲池验证通过");
+    Ok(())
+}
+
+// 槽数上限：slot_count 为 u16，槽目录最多 u16::MAX 项；达到上限后继续插入报错而不是回绕，
+// insert_record_at 指定 u16::MAX 或超出页面容纳范围的槽号同样报错且不改动槽目录
+fn test_slot_count_limit() -> Result<(), Box<dyn Error>> {
+    let mut page = Page::new(60000);
+    page.page_size = 1 << 20;
+    page.slots = vec![(0, 0); u16::MAX as usize - 1];
+    page.header.slot_count = page.slots.len() as u16;
+    assert_eq!(page.insert_record(b"x")?, u16::MAX - 1);
+    assert_eq!(page.header.slot_count, u16::MAX);
+    let err = page.insert_record(b"y").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(page.slots.len(), u16::MAX as usize);
+
+    let mut page = Page::new(4096);
+    for slot in [u16::MAX, 20000] {
+        let err = page.insert_record_at(slot, b"z").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Other);
+    }
+    assert!(page.slots.is_empty());
+    println!("槽数上限验证通过");
+    Ok(())
+}
+
+// 按顺序记录 FileHandle 的块写入与 fsync，写入全部放行
+struct IoRecorder(Arc<Mutex<Vec<String>>>);
+
+impl WriteInterceptor for IoRecorder {
+    fn allow_write(&mut self, block: BlockId) -> bool {
+        self.0.lock().unwrap().push(format!("write {}", block));
+        true
+    }
+
+    fn on_sync(&mut self) {
+        self.0.lock().unwrap().push("sync".to_string());
+    }
+}
+
+// flush_all 的写入顺序：先写数据页，fsync 之后才写文件头（块 0），最后再 fsync 一次文件头
+fn test_flush_sync_barrier() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("flush_sync_barrier")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut bm = BufferManager::new(fm.open_file(&path)?, 4);
+    let block = bm.allocate_data_page()?;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    bm.handle
+        .set_write_interceptor(Some(Box::new(IoRecorder(Arc::clone(&log)))));
+    bm.fetch(block)?[100] = 1;
+    bm.flush_all()?;
+
+    let log = log.lock().unwrap().clone();
+    let position = |event: &str| log.iter().position(|e| e == event);
+    let page = position(&format!("write {}", block)).ok_or("数据页未写出")?;
+    let sync = position("sync").ok_or("未发出 fsync")?;
+    let header = position("write 0").ok_or("文件头未写出")?;
+    assert!(page < sync && sync < header, "{:?}", log);
+    assert_eq!(log.last().map(String::as_str), Some("sync"));
+    println!("flush_all 写入屏障验证通过");
+    Ok(())
+}
+
+// 计算式插入：key 缺失时才调用闭包计算并存入，命中时直接返回已有值且刷新使用记录，
+// 被刷新的 key 不会被随后的插入淘汰；容量为 0 的缓存仍返回计算结果
+fn test_cache_get_or_insert_with() -> Result<(), Box<dyn Error>> {
+    let mut cache: Cache<String> = Cache::new(2, ReplacementPolicy::LRU);
+    let mut calls = 0;
+    for fallback in ["A", "X"] {
+        let value = cache.get_or_insert_with("a".into(), || {
+            calls += 1;
+            fallback.to_string()
+        });
+        assert_eq!(value, "A");
+    }
+    assert_eq!(calls, 1);
+
+    cache.insert("b".into(), "B".into());
+    cache.get_or_insert_with("a".into(), || unreachable!());
+    cache.insert("c".into(), "C".into());
+    assert!(cache.get("b").is_none());
+    assert_eq!(cache.get("a").map(String::as_str), Some("A"));
+
+    let mut empty: Cache<String> = Cache::new(0, ReplacementPolicy::CLOCK);
+    assert_eq!(empty.get_or_insert_with("k".into(), || "v".into()), "v");
+    println!("缓存计算式插入验证通过");
+    Ok(())
+}
+
+// 空闲槽链表：删除的槽按删除顺序压入链表头，链表随页面落盘并在 load 后保留（紧缩也不打乱），
+// 插入按链表顺序复用这些槽，链表为空后才追加新槽；insert_record_at 占用链表中间的槽时把它摘出链表
+fn test_free_slot_list() -> Result<(), Box<dyn Error>> {
+    let page_size = 1024;
+    let mut page = Page::new(page_size);
+    for i in 0..8u8 {
+        page.insert_record(&[i; 5])?;
+    }
+    for slot in [2, 5, 3, 7] {
+        page.delete_record(slot)?;
+    }
+    assert_eq!(page.header.free_slot_head, 7);
+
+    let mut buffer = vec![0u8; page_size];
+    page.flush(&mut buffer)?;
+    let mut page = Page::load(&buffer)?;
+    page.compact()?;
+    let reused = (0..5)
+        .map(|_| page.insert_record(b"n"))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(reused, vec![7, 3, 5, 2, 8]);
+    assert_eq!(page.slots.len(), 9);
+    assert_eq!(page.header.free_slot_head, NO_FREE_SLOT);
+
+    page.delete_record(0)?;
+    page.delete_record(4)?;
+    page.insert_record_at(0, b"z")?;
+    assert_eq!(page.header.free_slot_head, 4);
+    assert_eq!(page.insert_record(b"w")?, 4);
+    println!("空闲槽链表验证通过");
+    Ok(())
+}
+
+// 页面自检：正常页面（含已删除的槽、落盘后重新加载）通过 verify；
+// 分别注入槽数、free_offset、越界槽、重叠记录、free_bytes 五类不一致，verify 报告对应的类型
+fn test_page_verify() -> Result<(), Box<dyn Error>> {
+    let build = || -> std::io::Result<Page> {
+        let mut page = Page::new(512);
+        for record in [b"aaaa", b"bbbb", b"cccc"] {
+            page.insert_record(record)?;
+        }
+        Ok(page)
+    };
+    let header = PageHeader::SIZE as u32;
+    assert_eq!(build()?.verify(), Ok(()));
+    let mut page = build()?;
+    page.delete_record(1)?;
+    assert_eq!(page.verify(), Ok(()));
+    let mut buffer = vec![0u8; 512];
+    page.flush(&mut buffer)?;
+    assert_eq!(Page::load(&buffer)?.verify(), Ok(()));
+
+    let mut page = build()?;
+    page.header.slot_count = 5;
+    let expected = PageInconsistency::SlotCountMismatch {
+        header: 5,
+        actual: 3,
+    };
+    assert_eq!(page.verify(), Err(expected));
+
+    let mut page = build()?;
+    page.header.free_offset += 1;
+    let expected = PageInconsistency::FreeOffsetMismatch {
+        header: header + 13,
+        expected: header as usize + 12,
+    };
+    assert_eq!(page.verify(), Err(expected));
+
+    let mut page = build()?;
+    page.slots[2] = (header + 10, 4);
+    let expected = PageInconsistency::SlotOutOfRange {
+        slot: 2,
+        offset: header + 10,
+        len: 4,
+    };
+    assert_eq!(page.verify(), Err(expected));
+
+    let mut page = build()?;
+    page.slots[2] = (header + 2, 4);
+    let expected = PageInconsistency::OverlappingRecords {
+        first: 0,
+        second: 2,
+    };
+    assert_eq!(page.verify(), Err(expected.clone()));
+    assert!(!expected.to_string().is_empty());
+
+    let mut page = build()?;
+    page.header.free_bytes -= 1;
+    let result = page.verify();
+    assert!(matches!(
+        result,
+        Err(PageInconsistency::FreeBytesMismatch { .. })
+    ));
+    println!("页面自检验证通过");
+    Ok(())
+}
+
+// 槽的物理位置：slot_location 给出的 (偏移, 长度) 经 record_at 取出的字节与 get_record 一致，
+// 落盘后在页面缓冲区的同一位置也是这些字节；启用长度前缀时同样成立，空槽和无效槽号返回 None
+fn test_slot_location() -> Result<(), Box<dyn Error>> {
+    let page_size = 512;
+    for framed in [false, true] {
+        let mut page = Page::new(page_size);
+        if framed {
+            page.enable_framing()?;
+        }
+        for record in [&b"alpha"[..], b"be", b"gamma!"] {
+            page.insert_record(record)?;
+        }
+        page.delete_record(1)?;
+        assert_eq!(page.slot_location(1), None);
+        assert_eq!(page.slot_location(9), None);
+
+        let mut buffer = vec![0u8; page_size];
+        page.flush(&mut buffer)?;
+        for slot in [0, 2] {
+            let (offset, len) = page.slot_location(slot).ok_or("有效槽没有位置")?;
+            let record = page.get_record(slot)?;
+            assert_eq!(page.record_at(offset, len), record);
+            assert_eq!(&buffer[offset as usize..(offset + len) as usize], record);
+        }
+    }
+    println!("槽物理位置验证通过");
+    Ok(())
+}
+
+// 脏页快照：只读取过的页不计入，dirty_page_ids 按块号顺序返回被修改过的页；
+// 单页写回后该页移出集合，flush_all 之后集合为空
+fn test_dirty_page_ids() -> Result<(), Box<dyn Error>> {
+    let mut handle = new_table_file("dirty_page_ids")?;
+    let mut blocks = Vec::new();
+    for _ in 0..6 {
+        blocks.push(handle.allocate_block()?);
+    }
+    let mut bm = BufferManager::new(handle, 8);
+    assert!(bm.dirty_page_ids().is_empty());
+    for &block in &blocks {
+        bm.fetch(block)?;
+    }
+    for index in [4, 1, 3] {
+        bm.fetch(blocks[index])?[64] = 1;
+    }
+    assert_eq!(bm.dirty_page_ids(), vec![blocks[1], blocks[3], blocks[4]]);
+
+    bm.flush_page(blocks[3])?;
+    assert_eq!(bm.dirty_page_ids(), vec![blocks[1], blocks[4]]);
+    bm.flush_all()?;
+    assert!(bm.dirty_page_ids().is_empty());
+    println!("脏页快照验证通过");
+    Ok(())
+}
+
+// 可判别的插入失败：装不下且紧缩后也装不下时报 Full，紧缩后能装下时报 NeedsCompaction，
+// 失败不改动页面；紧缩后同样的记录插入成功并复用被删除的槽
+fn test_try_insert() -> Result<(), Box<dyn Error>> {
+    let mut page = Page::new(512);
+    for _ in 0..4 {
+        page.try_insert(&[7u8; 100])?;
+    }
+    let free = page.header.free_bytes as usize;
+    assert_eq!(page.try_insert(&vec![1u8; free]), Err(InsertError::Full));
+
+    page.delete_record(1)?;
+    let before = (page.slots.clone(), page.data.len(), page.header.free_bytes);
+    let fits_compacted = vec![1u8; free + 50];
+    assert_eq!(
+        page.try_insert(&fits_compacted),
+        Err(InsertError::NeedsCompaction)
+    );
+    assert_eq!(
+        page.try_insert(&vec![1u8; free + 101]),
+        Err(InsertError::Full)
+    );
+    let after = (page.slots.clone(), page.data.len(), page.header.free_bytes);
+    assert_eq!(after, before);
+
+    page.compact()?;
+    assert_eq!(page.try_insert(&fits_compacted)?, 1);
+    println!("可判别的插入失败验证通过");
+    Ok(())
+}
+
+// 装入预判：can_fit 在边界处与新增槽目录项、长度前缀的开销一致，复用已删除的槽时不计槽目录项；
+// can_fit_after_compact 把已删除记录的空间计入，预判可以装下的记录确实插入成功
+fn test_can_fit() -> Result<(), Box<dyn Error>> {
+    let mut page = Page::new(512);
+    let free = page.header.free_bytes as usize;
+    assert!(page.can_fit(free - SLOT_ENTRY_BYTES));
+    assert!(!page.can_fit(free - SLOT_ENTRY_BYTES + 1));
+    page.enable_framing()?;
+    assert!(page.can_fit(free - SLOT_ENTRY_BYTES - 2));
+    assert!(!page.can_fit(free - SLOT_ENTRY_BYTES - 1));
+
+    page.insert_record(&[1u8; 100])?;
+    page.insert_record(&[2u8; 100])?;
+    let free = page.header.free_bytes as usize;
+    assert!(page.can_fit(free - SLOT_ENTRY_BYTES - 2));
+    assert!(!page.can_fit(free - SLOT_ENTRY_BYTES - 1));
+    assert!(!page.can_fit_after_compact(free - SLOT_ENTRY_BYTES - 1));
+
+    page.delete_record(0)?;
+    assert!(page.can_fit(free - 2));
+    assert!(!page.can_fit(free - 1));
+    assert!(page.can_fit_after_compact(free + 100));
+    assert!(!page.can_fit_after_compact(free + 101));
+    assert!(page.insert_record(&vec![3u8; free - 2]).is_ok());
+    println!("装入预判验证通过");
+    Ok(())
+}
+
+// 宽槽目录项：超过 64 KB 的页面自动使用 8 字节槽目录项，偏移超过 65535 的记录落盘、加载、
+// 按帧读取、紧缩后都能正确读出；小页面只能在插入记录前启用宽槽，未启用时超出范围的插入报错
+fn test_wide_slots() -> Result<(), Box<dyn Error>> {
+    let page_size = 128 * 1024;
+    let mut page = Page::new(page_size);
+    assert!(page.header.has_wide_slots());
+    assert_eq!(page.header.slot_entry_size(), 8);
+    let filler = vec![1u8; 60000];
+    page.insert_record(&filler)?;
+    let big = vec![2u8; 70000];
+    let big_slot = page.insert_record(&big)?;
+    let tail: Vec<u8> = (0..100).collect();
+    let tail_slot = page.insert_record(&tail)?;
+    assert!(page.slots[tail_slot as usize].0 > u16::MAX as u32);
+
+    let mut frame = vec![0u8; page_size];
+    page.flush(&mut frame)?;
+    let mut loaded = Page::load(&frame)?;
+    assert_eq!(loaded.get_record(tail_slot)?, &tail[..]);
+    assert_eq!(loaded.get_record(big_slot)?, &big[..]);
+    assert_eq!(Page::record_in_frame(&frame, tail_slot)?, &tail[..]);
+    assert_eq!(loaded.header.free_bytes, page.header.free_bytes);
+    assert_eq!(loaded.verify(), Ok(()));
+    loaded.delete_record(0)?;
+    loaded.compact()?;
+    assert_eq!(loaded.get_record(tail_slot)?, &tail[..]);
+
+    let mut small = Page::new(4096);
+    assert!(!small.header.has_wide_slots());
+    small.enable_wide_slots()?;
+    small.insert_record(b"abc")?;
+    assert_eq!(
+        small.header.free_bytes as usize,
+        4096 - PageHeader::SIZE - 3 - 8
+    );
+    let mut frame = vec![0u8; 4096];
+    small.flush(&mut frame)?;
+    assert_eq!(Page::load(&frame)?.get_record(0)?, b"abc");
+    let mut used = Page::new(4096);
+    used.insert_record(b"a")?;
+    assert!(used.enable_wide_slots().is_err());
+
+    let mut narrow = Page::new(page_size);
+    narrow.header.flags = 0;
+    narrow.insert_record(&filler)?;
+    narrow.insert_record(&filler[..10000])?;
+    let err = narrow.insert_record(b"x").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    println!("宽槽目录项验证通过");
+    Ok(())
+}
@@ -0,0 +1,836 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::ErrorKind;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::{new_table_file, test_dir};
+use crate::fm::{BlockId, FileManager, FileManagerConfig};
+use crate::mm::page::Page;
+use crate::mm::page_header::PageHeader;
+use crate::mm::page_ops::PageOps;
+use crate::rm::key::compare;
+use crate::rm::lock::LockState;
+use crate::rm::types::{rid, RecAux};
+use crate::rm::{
+    Column, ColumnType, KeyType, LockManager, LockMode, RecordTooLarge, Schema, TableManager,
+    TableMode,
+};
+
+// Record Manager 相关测试：依次运行本文件中的各项检查
+pub fn test_rm() -> Result<(), Box<dyn Error>> {
+    println!("=== 开始 Record Manager 测试 ===");
+    test_free_space_histogram()?;
+    test_record_size_limit()?;
+    test_rid_codec()?;
+    test_compressed_column()?;
+    test_count_and_average_size()?;
+    test_rid_stable_across_compaction()?;
+    test_get_optional_and_exists()?;
+    test_transaction_rollback()?;
+    test_scan_filter()?;
+    test_truncate_table()?;
+    test_insert_with_autoid()?;
+    test_key_compare()?;
+    test_schema_column_layout()?;
+    test_recaux_nulls()?;
+    test_update_in_place()?;
+    test_record_locks()?;
+    test_schema_block()?;
+    test_delete_where()?;
+    test_partial_page_reuse()?;
+    test_compact_on_flush()?;
+    test_get_many()?;
+    test_with_record()?;
+    test_compact_page()?;
+    test_plan_inserts()?;
+    test_pins_released_on_error()?;
+    test_find_record()?;
+    test_append_only_mode()?;
+    println!("=== Record Manager 测试完成 ===");
+    Ok(())
+}
+
+// 页面填充直方图只统计数据页：文件头块、空闲空间映射块和空闲链表中的块都不计入
+fn test_free_space_histogram() -> Result<(), Box<dyn Error>> {
+    let mut handle = new_table_file("free_space_histogram")?;
+    handle.preallocate(3, true)?;
+    let mut table_mgr = TableManager::new(handle, 4);
+    for i in 0..50 {
+        table_mgr.insert(format!("record-{:04}", i).as_bytes())?;
+    }
+    let histogram = table_mgr.free_space_histogram()?;
+    assert_eq!(histogram.iter().sum::<u32>(), 1);
+    println!("free_space_histogram 验证通过");
+    Ok(())
+}
+
+// 单条记录上限：恰好等于页面扣除页头和一个槽目录项后的空间，超出时返回可区分的 RecordTooLarge；
+// 宽槽目录项的页面不受 u16 长度上限限制
+fn test_record_size_limit() -> Result<(), Box<dyn Error>> {
+    let mut table_mgr = TableManager::new(new_table_file("record_size_limit")?, 4);
+    let max = table_mgr.max_record_size();
+    assert_eq!(max, 4096 - PageHeader::SIZE - 4);
+    let rid = table_mgr.insert(&vec![7u8; max])?;
+    assert_eq!(table_mgr.get(rid)?.len(), max);
+    let err = table_mgr.insert(&vec![7u8; max + 1]).unwrap_err();
+    assert_eq!(
+        RecordTooLarge::from_io_error(&err),
+        Some(&RecordTooLarge { len: max + 1, max })
+    );
+    let err = table_mgr.plan_inserts(&[10, max + 1]).unwrap_err();
+    assert!(RecordTooLarge::from_io_error(&err).is_some());
+    let err = table_mgr.get((BlockId::new(0), 0)).unwrap_err();
+    assert!(RecordTooLarge::from_io_error(&err).is_none());
+
+    let path = test_dir("record_size_limit_wide")?.join("t.tbl");
+    let file_manager = FileManager::new(FileManagerConfig {
+        block_size: 128 * 1024,
+        ..FileManagerConfig::default()
+    });
+    file_manager.create_table_file(&path)?;
+    let mut table_mgr = TableManager::new(file_manager.open_file(&path)?, 2);
+    let max = table_mgr.max_record_size();
+    assert_eq!(max, 128 * 1024 - PageHeader::SIZE - 8);
+    let rid = table_mgr.insert(&vec![9u8; max])?;
+    assert_eq!(table_mgr.get(rid)?.len(), max);
+    println!("单条记录上限验证通过");
+    Ok(())
+}
+
+// Rid 编码：6 字节小端 block + slot，解码还原原值；输入不足 6 字节时报 InvalidData
+fn test_rid_codec() -> Result<(), Box<dyn Error>> {
+    for (block, slot) in [(1, 0), (0x0102_0304, 0x0506), (u32::MAX, u16::MAX)] {
+        let original = (BlockId::from(block), slot);
+        let bytes = rid::encode(original);
+        assert_eq!(bytes.len(), rid::ENCODED_LEN);
+        assert_eq!(rid::decode(&bytes)?, original);
+    }
+    assert_eq!(
+        rid::encode((BlockId::new(0x0102_0304), 0x0506)),
+        [0x04, 0x03, 0x02, 0x01, 0x06, 0x05]
+    );
+    let err = rid::decode(&[0u8; 5]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    println!("Rid 编码/解码验证通过");
+    Ok(())
+}
+
+// 压缩列：重复性强的大字符串列写入页面后明显小于原始值，读出后完整还原；
+// 压缩没有收益的短值按原样存放，未声明压缩的列不受影响
+fn test_compressed_column() -> Result<(), Box<dyn Error>> {
+    let schema = Schema::new(vec![
+        Column::new("id", ColumnType::U32),
+        Column::new("body", ColumnType::Str).compressed(),
+        Column::new("note", ColumnType::Str).compressed(),
+        Column::new("raw", ColumnType::Bytes),
+    ]);
+    let body = "the quick brown fox jumps over the lazy dog. ".repeat(60);
+    let mut row = RecAux::new();
+    row.push("id", 7u32.to_le_bytes().to_vec());
+    row.push("body", body.as_bytes().to_vec());
+    row.push("note", b"xy".to_vec());
+    row.push("raw", body.as_bytes()[..100].to_vec());
+    let encoded = row.to_bytes(&schema)?;
+
+    let mut table_mgr = TableManager::new(new_table_file("compressed_column")?, 4);
+    let rid = table_mgr.insert(&encoded)?;
+    let stored = table_mgr.get(rid)?;
+    assert!(stored.len() < body.len());
+    assert!(schema.column_bytes(&stored, 1).unwrap().len() < body.len());
+    assert_eq!(schema.column_bytes(&stored, 2), Some(&b"xy"[..]));
+
+    let decoded = RecAux::from_bytes(&schema, &stored)?;
+    assert_eq!(decoded.get("body"), Some(Some(body.as_bytes())));
+    assert_eq!(decoded.get("note"), Some(Some(&b"xy"[..])));
+    assert_eq!(decoded.get("raw"), Some(Some(&body.as_bytes()[..100])));
+
+    // 记录的原始长度与压缩数据不符时报告 InvalidData，而不是返回错误的值
+    // body 的槽位于 NULL 位图（1 字节）和 id 列（4 字节）之后，原始长度在槽内偏移 4
+    let mut corrupted = stored.clone();
+    let raw_len = u16::from_le_bytes([corrupted[9], corrupted[10]]);
+    corrupted[9..11].copy_from_slice(&(raw_len + 1).to_le_bytes());
+    let err = schema.decode(&corrupted).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    println!("压缩列验证通过");
+    Ok(())
+}
+
+// 记录数与平均长度：跨多个数据页统计，已删除的记录不计入；空表平均长度为 0
+fn test_count_and_average_size() -> Result<(), Box<dyn Error>> {
+    let mut table_mgr = TableManager::new(new_table_file("count_and_average_size")?, 4);
+    assert_eq!(table_mgr.count()?, 0);
+    assert_eq!(table_mgr.average_record_size()?, 0.0);
+
+    let mut rids = Vec::new();
+    for i in 0..40 {
+        let len = if i % 2 == 0 { 100 } else { 300 };
+        rids.push(table_mgr.insert(&vec![i as u8; len])?);
+    }
+    assert!(rids.iter().any(|rid| rid.0 != rids[0].0));
+    assert_eq!(table_mgr.count()?, 40);
+    assert_eq!(table_mgr.average_record_size()?, 200.0);
+
+    // 删除 10 条 300 字节的记录后剩 20 条 100 字节、10 条 300 字节
+    for rid in rids.iter().skip(1).step_by(2).take(10) {
+        table_mgr.delete(*rid)?;
+    }
+    assert_eq!(table_mgr.count()?, 30);
+    assert_eq!(table_mgr.average_record_size()?, 5000.0 / 30.0);
+    println!("记录数与平均长度验证通过");
+    Ok(())
+}
+
+// 紧缩不改变槽号：页内前面的记录删除后紧缩，之前保存的 Rid 仍指向原来的记录，
+// 被删除的槽依旧读不到
+fn test_rid_stable_across_compaction() -> Result<(), Box<dyn Error>> {
+    let mut table_mgr = TableManager::new(new_table_file("rid_stable_across_compaction")?, 4);
+    let first = table_mgr.insert(&[1u8; 300])?;
+    let second = table_mgr.insert(&[2u8; 300])?;
+    let kept = table_mgr.insert(b"kept record")?;
+    let last = table_mgr.insert(b"last record")?;
+    assert!([second, kept, last].iter().all(|rid| rid.0 == first.0));
+
+    // 没有死空间时 compact_page 不修改页面，直接返回当前的 free_bytes
+    let free_before = table_mgr.compact_page(first.0)?;
+    table_mgr.delete(first)?;
+    table_mgr.delete(second)?;
+    assert_eq!(table_mgr.compact_page(first.0)?, free_before + 600);
+    assert_eq!(table_mgr.get(kept)?, b"kept record");
+    assert_eq!(table_mgr.get(last)?, b"last record");
+    assert!(table_mgr.get_optional(first)?.is_none());
+    assert!(table_mgr.get_optional(second)?.is_none());
+    println!("紧缩后 Rid 保持不变验证通过");
+    Ok(())
+}
+
+// 查找不存在的记录不报错：已删除的槽、越界的槽号和块号都返回 None / false，
+// 有效记录正常返回；get 对同样的 Rid 仍然报错
+fn test_get_optional_and_exists() -> Result<(), Box<dyn Error>> {
+    let mut table_mgr = TableManager::new(new_table_file("get_optional_and_exists")?, 4);
+    let live = table_mgr.insert(b"live")?;
+    let deleted = table_mgr.insert(b"deleted")?;
+    table_mgr.delete(deleted)?;
+
+    assert_eq!(table_mgr.get_optional(live)?, Some(b"live".to_vec()));
+    assert!(table_mgr.exists(live)?);
+    let missing = [
+        deleted,
+        (live.0, 99),
+        (BlockId::new(99), 0),
+        (BlockId::new(0), 0),
+    ];
+    for rid in missing {
+        assert_eq!(table_mgr.get_optional(rid)?, None);
+        assert!(!table_mgr.exists(rid)?);
+        assert!(table_mgr.get(rid).is_err());
+    }
+    println!("get_optional / exists 验证通过");
+    Ok(())
+}
+
+// 事务：事务内能读到自己未提交的写入；回滚后插入的记录全部不可见、删除的记录恢复；
+// 未提交就丢弃的事务等同回滚；提交后重新打开表仍能看到事务内的修改
+fn test_transaction_rollback() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("transaction_rollback")?;
+    let fm = FileManager::new(FileManagerConfig::default());
+    let path = dir.join("table.tbl");
+    fm.create_table_file(&path)?;
+    let mut tm = TableManager::new(fm.open_file(&path)?, 4);
+    let keep = tm.insert(b"keep")?;
+
+    let mut txn = tm.begin();
+    let mut rids = Vec::new();
+    for i in 0..3 {
+        rids.push(txn.insert(format!("record {}", i).as_bytes())?);
+    }
+    assert_eq!(txn.get(rids[1])?, b"record 1");
+    txn.delete(keep)?;
+    assert_eq!(txn.get_optional(keep)?, None);
+    txn.rollback()?;
+    for &rid in &rids {
+        assert!(!tm.exists(rid)?);
+    }
+    assert_eq!(tm.get(keep)?, b"keep");
+    assert_eq!(tm.count()?, 1);
+
+    tm.begin().insert(b"dropped")?;
+    assert_eq!(tm.count()?, 1);
+
+    let mut txn = tm.begin();
+    let committed = txn.insert(b"committed")?;
+    txn.delete(keep)?;
+    txn.commit()?;
+    drop(tm);
+    let mut tm = TableManager::new(fm.open_file(&path)?, 4);
+    assert_eq!(tm.get(committed)?, b"committed");
+    assert!(!tm.exists(keep)?);
+    println!("事务提交/回滚验证通过");
+    Ok(())
+}
+
+// 带谓词的扫描：只返回满足条件的存活记录，已删除的记录即使内容匹配也不返回
+fn test_scan_filter() -> Result<(), Box<dyn Error>> {
+    let mut tm = TableManager::new(new_table_file("scan_filter")?, 4);
+    let mut rids = Vec::new();
+    for record in ["apple", "banana", "pineapple", "cherry"] {
+        rids.push(tm.insert(record.as_bytes())?);
+    }
+    tm.delete(rids[2])?;
+    let matched = tm.scan_filter(|record| record.windows(3).any(|w| w == b"app"))?;
+    assert_eq!(matched, vec![rids[0]]);
+    assert!(tm.scan_filter(|_| false)?.is_empty());
+    println!("带谓词扫描验证通过");
+    Ok(())
+}
+
+// 清空表：truncate 后扫描为空，文件缩到只剩文件头块，之后插入的记录重新从第一个数据块开始
+fn test_truncate_table() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("truncate_table")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let handle = fm.open_file(&path)?;
+    let block_size = handle.block_size() as u64;
+    let mut tm = TableManager::new(handle, 4);
+    for i in 0..50 {
+        tm.insert(format!("record {}", i).as_bytes())?;
+    }
+    assert_eq!(tm.scan_all()?.len(), 50);
+    let before = std::fs::metadata(&path)?.len();
+
+    tm.truncate()?;
+    assert!(tm.scan_all()?.is_empty());
+    let after = std::fs::metadata(&path)?.len();
+    assert!(after < before);
+    assert_eq!(after, block_size);
+    let rid = tm.insert(b"new")?;
+    assert_eq!(rid.0, BlockId::new(1));
+    assert_eq!(tm.get(rid)?, b"new");
+    println!("清空表验证通过");
+    Ok(())
+}
+
+// 自增 id：insert_with_autoid 返回的 id 从 1 开始递增并写在记录开头，重新打开表后继续递增、不会复用
+fn test_insert_with_autoid() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("insert_with_autoid")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut ids = Vec::new();
+    {
+        let mut tm = TableManager::new(fm.open_file(&path)?, 4);
+        for _ in 0..3 {
+            let (id, rid) = tm.insert_with_autoid(b"x")?;
+            assert_eq!(&tm.get(rid)?[..8], &id.to_le_bytes());
+            ids.push(id);
+        }
+    }
+    let mut tm = TableManager::new(fm.open_file(&path)?, 4);
+    for _ in 0..3 {
+        ids.push(tm.insert_with_autoid(b"y")?.0);
+    }
+    assert_eq!(ids, vec![1, 2, 3, 4, 5, 6]);
+    println!("自增 id 验证通过");
+    Ok(())
+}
+
+// 按键类型比较：负数 i32、跨字节的 u32 按数值排序（与直接比较小端字节的结果不同），
+// f64 负数排在正数前、NaN 排在最后，字符串按字节序比较
+fn test_key_compare() -> Result<(), Box<dyn Error>> {
+    let negative = (-5i32).to_le_bytes();
+    let positive = 3i32.to_le_bytes();
+    assert_eq!(compare(KeyType::I32, &negative, &positive), Ordering::Less);
+    assert_eq!(negative[..].cmp(&positive[..]), Ordering::Greater);
+
+    let big = 256u32.to_le_bytes();
+    let small = 1u32.to_le_bytes();
+    assert_eq!(compare(KeyType::U32, &big, &small), Ordering::Greater);
+    assert_eq!(big[..].cmp(&small[..]), Ordering::Less);
+
+    let minus = (-1.5f64).to_le_bytes();
+    let plus = 2.0f64.to_le_bytes();
+    let infinity = f64::INFINITY.to_le_bytes();
+    let nan = f64::NAN.to_le_bytes();
+    assert_eq!(compare(KeyType::F64, &minus, &plus), Ordering::Less);
+    assert_eq!(compare(KeyType::F64, &infinity, &nan), Ordering::Less);
+    assert_eq!(compare(KeyType::Str, b"abc", b"abd"), Ordering::Less);
+    assert_eq!(KeyType::F64.fixed_size(), Some(8));
+    assert_eq!(KeyType::Str.fixed_size(), None);
+    println!("按键类型比较验证通过");
+    Ok(())
+}
+
+// 定长/变长列布局：NULL 位图之后定长列按声明顺序连续存放，变长列放在末尾的堆区；
+// 每列都能按偏移直接取出，NULL 列返回 None，空字符串与 NULL 区分开；定长列长度不符时编码失败
+fn test_schema_column_layout() -> Result<(), Box<dyn Error>> {
+    let schema = Schema::new(vec![
+        Column::new("id", ColumnType::U32),
+        Column::new("name", ColumnType::Str),
+        Column::new("score", ColumnType::F64),
+        Column::new("note", ColumnType::Bytes),
+        Column::new("age", ColumnType::I32),
+    ]);
+    assert_eq!(schema.column_count(), 5);
+    let id = 7u32.to_le_bytes();
+    let score = 1.5f64.to_le_bytes();
+    let age = (-3i32).to_le_bytes();
+    let record = schema.encode(&[Some(&id), Some(b"alice"), Some(&score), None, Some(&age)])?;
+    assert_eq!(schema.column_bytes(&record, 0), Some(&id[..]));
+    assert_eq!(schema.column_bytes(&record, 1), Some(&b"alice"[..]));
+    assert_eq!(schema.column_bytes(&record, 2), Some(&score[..]));
+    assert_eq!(schema.column_bytes(&record, 3), None);
+    assert_eq!(schema.column_bytes(&record, 4), Some(&age[..]));
+    // 1 字节 NULL 位图之后依次是 id、score、age
+    assert_eq!(&record[1..5], &id);
+    assert_eq!(&record[5..13], &score);
+    assert_eq!(&record[13..17], &age);
+
+    let record = schema.encode(&[Some(&id), Some(b""), None, Some(b"x"), Some(&age)])?;
+    assert_eq!(schema.column_bytes(&record, 1), Some(&b""[..]));
+    assert_eq!(schema.column_bytes(&record, 2), None);
+    assert_eq!(
+        schema.decode(&record)?,
+        vec![
+            Some(id.to_vec()),
+            Some(Vec::new()),
+            None,
+            Some(b"x".to_vec()),
+            Some(age.to_vec())
+        ]
+    );
+    let short_id = [Some(&b"ab"[..]), None, None, None, None];
+    assert!(schema.encode(&short_id).is_err());
+    println!("定长/变长列布局验证通过");
+    Ok(())
+}
+
+// RecAux 的 NULL 列：push_null 的列和未赋值的列解码为 NULL，空字符串解码为空而不是 NULL；
+// 引用 Schema 中不存在的列时编码失败
+fn test_recaux_nulls() -> Result<(), Box<dyn Error>> {
+    let schema = Schema::new(vec![
+        Column::new("id", ColumnType::U32),
+        Column::new("name", ColumnType::Str),
+        Column::new("nick", ColumnType::Str),
+        Column::new("age", ColumnType::I32),
+    ]);
+    let mut rec = RecAux::new();
+    rec.push("id", 1u32.to_le_bytes().to_vec());
+    rec.push("name", Vec::new());
+    rec.push_null("nick");
+    let decoded = RecAux::from_bytes(&schema, &rec.to_bytes(&schema)?)?;
+    assert_eq!(decoded.get("id"), Some(Some(&1u32.to_le_bytes()[..])));
+    assert_eq!(decoded.get("name"), Some(Some(&b""[..])));
+    assert_eq!(decoded.get("nick"), Some(None));
+    assert_eq!(decoded.get("age"), Some(None));
+    assert_eq!(decoded.get("missing"), None);
+
+    rec.push("bogus", Vec::new());
+    assert!(rec.to_bytes(&schema).is_err());
+    println!("RecAux NULL 列验证通过");
+    Ok(())
+}
+
+// 等长原地更新：长度相同时直接覆盖原记录，长度不同时报 InvalidInput 且原记录不变
+fn test_update_in_place() -> Result<(), Box<dyn Error>> {
+    let mut tm = TableManager::new(new_table_file("update_in_place")?, 4);
+    let rid = tm.insert(b"abcd")?;
+    let other = tm.insert(b"other")?;
+    tm.update_in_place(rid, b"wxyz")?;
+    assert_eq!(tm.get(rid)?, b"wxyz");
+    assert_eq!(tm.get(other)?, b"other");
+
+    for data in [&b"toolong"[..], b"ab"] {
+        let err = tm.update_in_place(rid, data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+    assert_eq!(tm.get(rid)?, b"wxyz");
+    println!("等长原地更新验证通过");
+    Ok(())
+}
+
+// 记录锁：排他锁持有期间再加排他锁或共享锁都报 WouldBlock，共享锁可以重入；
+// 挂上锁管理器后，TableManager 的读取需要共享锁、修改和删除需要排他锁，锁释放后照常执行
+fn test_record_locks() -> Result<(), Box<dyn Error>> {
+    let target = (BlockId::new(1), 0);
+    let mut lm = LockManager::new();
+    lm.lock_exclusive(target)?;
+    let err = lm.lock_exclusive(target).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    assert!(lm.lock_shared(target).is_err());
+    lm.unlock(target)?;
+    lm.lock_shared(target)?;
+    lm.lock_shared(target)?;
+    assert!(lm.lock_exclusive(target).is_err());
+    lm.unlock(target)?;
+    assert_eq!(lm.state(target), Some(LockState::Shared(1)));
+
+    let mut tm = TableManager::new(new_table_file("record_locks")?, 4);
+    let rid = tm.insert(b"abc")?;
+    let locks = Arc::new(Mutex::new(LockManager::with_timeout(
+        Duration::from_millis(5),
+    )));
+    tm.set_lock_manager(Some(locks.clone()));
+    let held = LockManager::acquire(&locks, rid, LockMode::Exclusive)?;
+    assert_eq!(tm.delete(rid).unwrap_err().kind(), ErrorKind::WouldBlock);
+    assert!(tm.get(rid).is_err());
+    drop(held);
+    assert_eq!(tm.get(rid)?, b"abc");
+
+    let shared = LockManager::acquire(&locks, rid, LockMode::Shared)?;
+    assert_eq!(tm.get(rid)?, b"abc");
+    assert!(tm.update_in_place(rid, b"xyz").is_err());
+    drop(shared);
+    tm.update_in_place(rid, b"xyz")?;
+    tm.delete(rid)?;
+    assert_eq!(locks.lock().unwrap().state(rid), None);
+    println!("记录锁验证通过");
+    Ok(())
+}
+
+// 表结构元数据块：带 Schema 创建的表重新打开后 Schema 一致，数据块从块 2 开始，元数据块不能释放，
+// truncate 之后仍然保留；普通表没有 Schema，数据块从块 1 开始
+fn test_schema_block() -> Result<(), Box<dyn Error>> {
+    let dir = test_dir("schema_block")?;
+    let fm = FileManager::new(FileManagerConfig::default());
+    let schema = Schema::new(vec![
+        Column::new("id", ColumnType::U32),
+        Column::new("name", ColumnType::Str),
+    ]);
+    let path = dir.join("schema.tbl");
+    fm.create_table_file_with_schema(&path, &schema)?;
+    let handle = fm.open_file(&path)?;
+    assert_eq!(handle.schema(), Some(&schema));
+    assert_eq!(handle.first_data_block(), BlockId::new(2));
+
+    let mut tm = TableManager::new(handle, 4);
+    let rid = tm.insert(b"x")?;
+    assert_eq!(rid.0, BlockId::new(2));
+    assert_eq!(tm.count()?, 1);
+    assert_eq!(tm.get_optional((BlockId::new(1), 0))?, None);
+    tm.truncate()?;
+    drop(tm);
+
+    let mut handle = fm.open_file(&path)?;
+    assert_eq!(handle.schema(), Some(&schema));
+    assert_eq!(handle.header().block_count, 2);
+    assert!(handle.release_block(BlockId::new(1)).is_err());
+    assert_eq!(handle.allocate_block()?, BlockId::new(2));
+
+    let plain = dir.join("plain.tbl");
+    fm.create_table_file(&plain)?;
+    let handle = fm.open_file(&plain)?;
+    assert!(handle.schema().is_none());
+    assert_eq!(handle.first_data_block(), BlockId::new(1));
+    println!("表结构元数据块验证通过");
+    Ok(())
+}
+
+// 按谓词批量删除：返回删除条数，不匹配的记录全部保留；再次执行时没有可删的记录
+fn test_delete_where() -> Result<(), Box<dyn Error>> {
+    let mut tm = TableManager::new(new_table_file("delete_where")?, 4);
+    let mut rids = Vec::new();
+    for i in 0..600 {
+        rids.push(tm.insert(format!("r{:04}", i).as_bytes())?);
+    }
+    assert_eq!(tm.delete_where(|record| record.ends_with(b"0"))?, 60);
+    assert_eq!(tm.count()?, 540);
+    for (i, &rid) in rids.iter().enumerate() {
+        assert_eq!(tm.exists(rid)?, i % 10 != 0);
+    }
+    assert_eq!(tm.delete_where(|record| record.ends_with(b"0"))?, 0);
+    assert_eq!(tm.count()?, 540);
+    println!("按谓词批量删除验证通过");
+    Ok(())
+}
+
+// 部分空闲页复用：大记录写满一页后另开新页，之后的小记录回到仍有空间的旧页；
+// 重新打开后空闲空间表仍在（文件头指向它且不能释放），小记录继续复用已有页而不增加块数
+fn test_partial_page_reuse() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("partial_page_reuse")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut tm = TableManager::new(fm.open_file(&path)?, 4);
+    let big = vec![7u8; 3000];
+    let first = tm.insert(&big)?;
+    let second = tm.insert(&big)?;
+    assert_ne!(first.0, second.0);
+    assert_eq!(tm.insert(b"small")?.0, first.0);
+    drop(tm);
+
+    let handle = fm.open_file(&path)?;
+    let block_count = handle.header().block_count;
+    let map_block = handle.header().free_space_map;
+    assert!(map_block > 0);
+    let map_block = BlockId::from(map_block as u32);
+    let mut tm = TableManager::new(handle, 4);
+    let again = tm.insert(b"small again")?;
+    assert!(again.0 == first.0 || again.0 == second.0);
+    drop(tm);
+
+    let mut handle = fm.open_file(&path)?;
+    assert_eq!(handle.header().block_count, block_count);
+    assert!(handle.release_block(map_block).is_err());
+    handle.release_block(first.0)?;
+    assert!(handle
+        .find_block_with_space(100)
+        .is_some_and(|block| block != first.0));
+    println!("部分空闲页复用验证通过");
+    Ok(())
+}
+
+// flush 时紧缩：死空间未超过阈值时按原样写出，超过阈值后 flush 写出的是紧缩过的页面；
+// 紧缩只发生在 flush 时，删除本身不紧缩缓冲区中的页面；不经 TableManager 修改的脏页同样在 flush 时紧缩。
+// 槽号保持不变，存活记录仍按原 Rid 读出，已删除的记录仍然不存在
+fn test_compact_on_flush() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("compact_on_flush")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut tm = TableManager::new(fm.open_file(&path)?, 4);
+    tm.set_compact_on_flush(Some(0.25));
+    let mut rids = Vec::new();
+    for i in 0..8u8 {
+        rids.push(tm.insert(&[i; 400])?);
+    }
+    let block = rids[0].0;
+    assert!(rids.iter().all(|rid| rid.0 == block));
+
+    // 死空间 800 / 3200 字节，未超过阈值
+    tm.delete(rids[0])?;
+    tm.delete(rids[1])?;
+    tm.flush()?;
+    assert_eq!(fm.open_file(&path)?.read_page(block)?.data.len(), 3200);
+
+    tm.delete(rids[2])?;
+    let buffered = Page::load(&tm.buffer_manager().fetch(block)?)?;
+    assert_eq!(buffered.data.len(), 3200);
+    tm.flush()?;
+    let page = fm.open_file(&path)?.read_page(block)?;
+    assert_eq!(page.data.len(), 2000);
+    assert_eq!(page.slots.len(), 8);
+    for (i, &rid) in rids.iter().enumerate().skip(3) {
+        assert_eq!(tm.get(rid)?, vec![i as u8; 400]);
+    }
+    assert_eq!(tm.get_optional(rids[1])?, None);
+
+    // 绕过 TableManager 直接在缓冲区中删除三条记录（1200 字节死空间），flush 同样紧缩
+    {
+        let mut frame = tm.buffer_manager().fetch(block)?;
+        let mut page = Page::load(&frame)?;
+        page.delete_record(rids[3].1)?;
+        page.delete_record(rids[4].1)?;
+        page.delete_record(rids[5].1)?;
+        page.flush(&mut frame)?;
+    }
+    tm.flush()?;
+    let page = fm.open_file(&path)?.read_page(block)?;
+    assert_eq!(page.data.len(), 800);
+    assert_eq!(tm.get(rids[7])?, vec![7; 400]);
+    println!("flush 时紧缩验证通过");
+    Ok(())
+}
+
+// 批量读取：请求跨多个块、同一块内多条、重复的 Rid 时，结果顺序与输入一致，每个块只 fetch 一次；
+// 缓冲区只有一帧时同样正确；空输入返回空结果，任一记录不存在时整体报错
+fn test_get_many() -> Result<(), Box<dyn Error>> {
+    let mut tm = TableManager::new(new_table_file("get_many")?, 1);
+    let mut rids = Vec::new();
+    for i in 0..12u8 {
+        rids.push(tm.insert(&[i; 1000])?);
+    }
+    let mut blocks: Vec<_> = rids.iter().map(|rid| rid.0).collect();
+    blocks.dedup();
+    assert!(blocks.len() >= 3);
+
+    let wanted = [11, 0, 5, 1, 0, 7];
+    let requested: Vec<_> = wanted.iter().map(|&i| rids[i]).collect();
+    let distinct: HashSet<_> = requested.iter().map(|rid| rid.0).collect();
+    assert!(distinct.len() < requested.len());
+    let fetches = tm.buffer_manager().fetch_count();
+    let records = tm.get_many(&requested)?;
+    // 每个涉及的块只 fetch 一次
+    assert_eq!(
+        tm.buffer_manager().fetch_count() - fetches,
+        distinct.len() as u64
+    );
+    assert_eq!(records.len(), wanted.len());
+    for (record, &i) in records.iter().zip(&wanted) {
+        assert_eq!(record, &vec![i as u8; 1000]);
+    }
+    assert!(tm.get_many(&[])?.is_empty());
+    tm.delete(rids[3])?;
+    assert!(tm.get_many(&[rids[2], rids[3]]).is_err());
+    println!("批量读取验证通过");
+    Ok(())
+}
+
+// 回调读取记录：闭包直接作用在页内的记录字节上，结果与 get 复制出的内容一致；
+// 已删除的记录报 NotFound，不存在的槽号报 InvalidInput
+fn test_with_record() -> Result<(), Box<dyn Error>> {
+    let mut tm = TableManager::new(new_table_file("with_record")?, 4);
+    let data: Vec<u8> = (0..200).collect();
+    let rid = tm.insert(&data)?;
+    let sum = tm.with_record(rid, |record| record.iter().map(|&b| b as u64).sum::<u64>())?;
+    assert_eq!(sum, data.iter().map(|&b| b as u64).sum::<u64>());
+    assert_eq!(tm.with_record(rid, |record| record.len())?, 200);
+
+    tm.delete(rid)?;
+    let err = tm.with_record(rid, |record| record.len()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    let err = tm
+        .with_record((rid.0, 99), |record| record.len())
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    println!("回调读取记录验证通过");
+    Ok(())
+}
+
+// 单页紧缩：只紧缩指定的块并返回紧缩后的空闲字节，记录仍按原 Rid 读出；没有死空间时再次调用不做改动，
+// 同样有死空间的其他块在磁盘上保持原样
+fn test_compact_page() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("compact_page")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut tm = TableManager::new(fm.open_file(&path)?, 4);
+    let size = tm.max_record_size() / 3;
+    let mut rids = Vec::new();
+    for i in 0..6u8 {
+        rids.push(tm.insert(&vec![i; size])?);
+    }
+    let (target, other) = (rids[0].0, rids[3].0);
+    assert_ne!(target, other);
+    tm.delete(rids[0])?;
+    tm.delete(rids[3])?;
+    tm.flush()?;
+    let before = std::fs::read(&path)?;
+
+    let free_bytes = tm.compact_page(target)?;
+    assert!(free_bytes as usize >= size);
+    assert_eq!(tm.compact_page(target)?, free_bytes);
+    for i in [1, 2] {
+        assert_eq!(tm.get(rids[i])?, vec![i as u8; size]);
+    }
+    tm.flush()?;
+    let after = std::fs::read(&path)?;
+    let block_bytes = |raw: &[u8], block: BlockId| {
+        let start = block.get() as usize * 4096;
+        raw[start..start + 4096].to_vec()
+    };
+    assert_eq!(block_bytes(&before, other), block_bytes(&after, other));
+    assert_ne!(block_bytes(&before, target), block_bytes(&after, target));
+    println!("单页紧缩验证通过");
+    Ok(())
+}
+
+// 批量插入规划：plan_inserts 只模拟不写入，预测的复用页数、新页数与随后真正插入时一致；
+// 超过单条记录上限的尺寸直接报错
+fn test_plan_inserts() -> Result<(), Box<dyn Error>> {
+    let mut tm = TableManager::new(new_table_file("plan_inserts")?, 8);
+    let mut existing = HashSet::new();
+    for i in 0..5u8 {
+        existing.insert(tm.insert(&[i; 1500])?.0);
+    }
+    let sizes: Vec<usize> = (0..40).map(|i| 100 + (i * 37) % 900).collect();
+    let plan = tm.plan_inserts(&sizes)?;
+    assert_eq!(plan.total_bytes, sizes.iter().sum::<usize>());
+
+    let mut new_pages = HashSet::new();
+    let mut reused = HashSet::new();
+    for &size in &sizes {
+        let block = tm.insert(&vec![7; size])?.0;
+        if existing.contains(&block) {
+            reused.insert(block);
+        } else {
+            new_pages.insert(block);
+        }
+    }
+    assert_eq!(plan.new_pages, new_pages.len());
+    assert_eq!(plan.pages_reused, reused.len());
+    assert!(plan.new_pages > 0 && plan.pages_reused > 0);
+    assert!(tm.plan_inserts(&[tm.max_record_size() + 1]).is_err());
+    println!("批量插入规划验证通过");
+    Ok(())
+}
+
+// 出错时释放 pin：原地更新超长、读取无效槽、批量读取中途失败、重复删除都报错，
+// 每次出错后块的 pin 计数都回到 0；之后的插入与写回照常进行
+fn test_pins_released_on_error() -> Result<(), Box<dyn Error>> {
+    let path = test_dir("pins_released_on_error")?.join("t.tbl");
+    let fm = FileManager::new(FileManagerConfig::default());
+    fm.create_table_file(&path)?;
+    let mut tm = TableManager::new(fm.open_file(&path)?, 4);
+    let rid = tm.insert(b"hello")?;
+    let block = rid.0;
+    assert_eq!(tm.pin_count(block), Some(0));
+    assert!(tm.update_in_place(rid, b"toolong!").is_err());
+    assert_eq!(tm.pin_count(block), Some(0));
+    assert!(tm.get((block, 9)).is_err());
+    assert_eq!(tm.pin_count(block), Some(0));
+    assert!(tm.get_many(&[rid, (block, 7)]).is_err());
+    assert_eq!(tm.pin_count(block), Some(0));
+    tm.delete(rid)?;
+    assert!(tm.delete(rid).is_err());
+    assert_eq!(tm.pin_count(block), Some(0));
+
+    let rid = tm.insert(b"again")?;
+    assert_eq!(tm.pin_count(rid.0), Some(0));
+    tm.flush()?;
+    drop(tm);
+    let mut tm = TableManager::new(fm.open_file(&path)?, 4);
+    assert_eq!(tm.get(rid)?, b"again");
+    println!("出错时释放 pin 验证通过");
+    Ok(())
+}
+
+// 按内容查找记录：开启布隆过滤器后写入的页面维护过滤器，find_record 找到存在的记录、
+// 不存在的内容返回 None；记录删除后不再被找到，未开启过滤器的页面照常逐条比较
+fn test_find_record() -> Result<(), Box<dyn Error>> {
+    let mut tm = TableManager::new(new_table_file("find_record")?, 4);
+    let plain = tm.insert(b"before-bloom")?;
+    tm.set_bloom_filter(true);
+    let record = |i: usize| format!("record-{:03}-{}", i, "x".repeat(100)).into_bytes();
+    let mut rids = Vec::new();
+    for i in 0..40 {
+        rids.push(tm.insert(&record(i))?);
+    }
+    for i in [0, 17, 39] {
+        assert_eq!(tm.find_record(&record(i))?, Some(rids[i]));
+    }
+    assert_eq!(tm.find_record(&record(40))?, None);
+    assert_eq!(tm.find_record(b"before-bloom")?, Some(plain));
+
+    tm.delete(rids[17])?;
+    assert_eq!(tm.find_record(&record(17))?, None);
+    tm.flush()?;
+    assert_eq!(tm.find_record(&record(39))?, Some(rids[39]));
+    println!("按内容查找记录验证通过");
+    Ok(())
+}
+
+// 追加模式：切换后新记录从新分配的页开始按顺序追加，放不下时再开新页；删除、条件删除、
+// 单页紧缩都报 Unsupported，读取不受影响；切回普通模式后重新复用已有页面的空间
+fn test_append_only_mode() -> Result<(), Box<dyn Error>> {
+    let mut tm = TableManager::new(new_table_file("append_only_mode")?, 4);
+    let seed = tm.insert(b"seed")?;
+    tm.set_mode(TableMode::AppendOnly);
+    assert_eq!(tm.mode(), TableMode::AppendOnly);
+    let first = tm.insert(b"e1")?;
+    let second = tm.insert(b"e2")?;
+    assert_ne!(first.0, seed.0);
+    assert_eq!(second, (first.0, 1));
+    let big = tm.insert(&vec![1; tm.max_record_size()])?;
+    assert_ne!(big.0, first.0);
+
+    let err = tm.delete(first).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+    let err = tm.delete_where(|_| true).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+    let err = tm.compact_page(first.0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+    assert_eq!(tm.get(second)?, b"e2");
+
+    tm.set_mode(TableMode::Normal);
+    assert_eq!(tm.insert(b"n")?.0, seed.0);
+    tm.delete(first)?;
+    println!("追加模式验证通过");
+    Ok(())
+}
@@ -0,0 +1,4 @@
+// wal 模块：预写日志（Write-Ahead Log）的记录格式与读写
+pub mod wal_log; // 日志记录的追加写入与恢复读取
+
+pub use wal_log::{WalReader, WalWriter};
@@ -0,0 +1,239 @@
+use std::convert::TryInto;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// 每条日志记录的格式（小端）：
+// - len: u32，负载字节数
+// - crc: u32，负载的 CRC32
+// - payload: len 字节
+// 崩溃可能让最后一条记录只写了一部分，读取时遇到长度或 CRC 不合法的记录即视为崩溃点停止
+const RECORD_HEADER_SIZE: usize = 8;
+
+// 日志写入器：以追加方式写入记录
+pub struct WalWriter {
+    file: File,
+    path: PathBuf,
+    // 组提交：queue 进来、尚未写入文件的记录（已编码为 长度 + CRC + 负载）
+    pending: Vec<u8>,
+    pending_records: usize,
+    // 第一条待提交记录入队的时间，用于判断时间窗口是否已到
+    pending_since: Option<Instant>,
+    // 一组最多攒多少条记录；达到后 queue 立即触发 flush_group
+    group_max_records: usize,
+    // 一组最多等待多久；None 表示只按条数触发
+    group_window: Option<Duration>,
+    // 已入队记录的最大序号，以及已经 fsync 落盘的最大序号
+    queued_seq: u64,
+    durable_seq: u64,
+    // 累计 fsync 次数，便于观察组提交效果
+    sync_count: u64,
+}
+
+impl WalWriter {
+    // 打开（不存在时创建）日志文件，新记录追加到末尾
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            pending: Vec::new(),
+            pending_records: 0,
+            pending_since: None,
+            group_max_records: 1,
+            group_window: None,
+            queued_seq: 0,
+            durable_seq: 0,
+            sync_count: 0,
+        })
+    }
+
+    // 日志文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // 追加一条记录（长度前缀 + CRC + 负载），返回记录在日志中的起始偏移
+    // 只写入操作系统缓存，需要持久化时调用 sync；排队中的组提交记录会先写出，保证顺序
+    pub fn append(&mut self, payload: &[u8]) -> io::Result<u64> {
+        let buf = encode_record(payload)?;
+        self.write_pending()?;
+        let offset = self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(&buf)?;
+        Ok(offset)
+    }
+
+    // 将已追加（以及排队中）的记录同步到磁盘
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.write_pending()?;
+        self.file.flush()?;
+        self.file.sync_data()?;
+        self.sync_count += 1;
+        self.durable_seq = self.queued_seq;
+        Ok(())
+    }
+
+    // 配置组提交：一组最多 max_records 条记录，或第一条记录入队后最多等待 window
+    // max_records 为 1（默认）时每条 queue 的记录都会立即单独 fsync
+    pub fn set_group_commit(&mut self, max_records: usize, window: Option<Duration>) {
+        self.group_max_records = max_records.max(1);
+        self.group_window = window;
+    }
+
+    // 以组提交方式登记一条记录，返回其提交序号；记录先攒在内存中，
+    // 达到条数上限或时间窗口时和同组的其他记录一起写入并只 fsync 一次。
+    // 事务在报告提交成功前应调用 wait_durable(序号)
+    pub fn queue(&mut self, payload: &[u8]) -> io::Result<u64> {
+        let buf = encode_record(payload)?;
+        self.pending.extend_from_slice(&buf);
+        self.pending_records += 1;
+        self.pending_since.get_or_insert_with(Instant::now);
+        self.queued_seq += 1;
+        let seq = self.queued_seq;
+        if self.group_due() {
+            self.flush_group()?;
+        }
+        Ok(seq)
+    }
+
+    // 把当前排队的整组记录一次写入并 fsync，返回本次落盘的组提交记录数；
+    // 是否需要 fsync 按序号判断：排队记录可能已被 append 顺带写出（pending_records 归零），
+    // 但尚未 fsync，仍要同步。所有已入队记录都已落盘时不做 fsync
+    pub fn flush_group(&mut self) -> io::Result<usize> {
+        let records = (self.queued_seq - self.durable_seq) as usize;
+        if records == 0 {
+            return Ok(0);
+        }
+        self.sync()?;
+        Ok(records)
+    }
+
+    // 等待序号为 seq 的记录落盘：尚未落盘时触发一次组 fsync
+    pub fn wait_durable(&mut self, seq: u64) -> io::Result<()> {
+        if seq > self.durable_seq {
+            self.flush_group()?;
+        }
+        Ok(())
+    }
+
+    // 已确认落盘的最大提交序号
+    pub fn durable_seq(&self) -> u64 {
+        self.durable_seq
+    }
+
+    // 当前排队等待组提交的记录数
+    pub fn pending_records(&self) -> usize {
+        self.pending_records
+    }
+
+    // 累计执行过的 fsync 次数
+    pub fn sync_count(&self) -> u64 {
+        self.sync_count
+    }
+
+    // 当前这组是否已达到条数上限或时间窗口
+    fn group_due(&self) -> bool {
+        self.pending_records >= self.group_max_records
+            || matches!(
+                (self.pending_since, self.group_window),
+                (Some(since), Some(window)) if since.elapsed() >= window
+            )
+    }
+
+    // 把排队中的记录写入文件末尾（不 fsync）
+    fn write_pending(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(&self.pending)?;
+        self.pending.clear();
+        self.pending_records = 0;
+        self.pending_since = None;
+        Ok(())
+    }
+
+    // 把日志截断到指定长度（恢复时丢弃崩溃留下的不完整尾部记录）
+    pub fn truncate_to(&mut self, len: u64) -> io::Result<()> {
+        self.file.set_len(len)?;
+        self.file.sync_all()
+    }
+}
+
+impl Drop for WalWriter {
+    fn drop(&mut self) {
+        // 尽力把排队中的组提交记录写出并落盘
+        let _ = self.flush_group();
+    }
+}
+
+// 编码一条记录：长度前缀 + CRC + 负载
+fn encode_record(payload: &[u8]) -> io::Result<Vec<u8>> {
+    let len: u32 = payload
+        .len()
+        .try_into()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "日志记录过大"))?;
+    let mut buf = Vec::with_capacity(RECORD_HEADER_SIZE + payload.len());
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+    buf.extend_from_slice(payload);
+    Ok(buf)
+}
+
+// 日志读取器：按顺序返回完整且校验通过的记录，遇到第一条不完整或损坏的记录时停止
+pub struct WalReader {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl WalReader {
+    // 读取整个日志文件；文件不存在时视为空日志
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut data = Vec::new();
+        match File::open(path) {
+            Ok(mut file) => {
+                file.read_to_end(&mut data)?;
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+        Ok(Self { data, pos: 0 })
+    }
+
+    // 已读出的完整记录在日志中占用的字节数；读完后即为可信日志的长度（崩溃点位置）
+    pub fn valid_len(&self) -> u64 {
+        self.pos as u64
+    }
+
+    // 读取下一条完整记录；到达日志末尾或遇到损坏记录时返回 None
+    pub fn next_record(&mut self) -> Option<Vec<u8>> {
+        let rest = &self.data[self.pos..];
+        if rest.len() < RECORD_HEADER_SIZE {
+            return None;
+        }
+        let len = u32::from_le_bytes(rest[0..4].try_into().unwrap()) as usize;
+        let crc = u32::from_le_bytes(rest[4..8].try_into().unwrap());
+        let payload = rest.get(RECORD_HEADER_SIZE..RECORD_HEADER_SIZE + len)?;
+        if crc32fast::hash(payload) != crc {
+            return None;
+        }
+        let record = payload.to_vec();
+        self.pos += RECORD_HEADER_SIZE + len;
+        Some(record)
+    }
+}
+
+impl Iterator for WalReader {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        self.next_record()
+    }
+}